@@ -29,6 +29,7 @@ fi
 
 # Parse command line arguments
 FORCE_DOWNLOAD=false
+PIN_CHECKSUMS=false
 SPECIFIC_VERSION=""
 SPECIFIC_ARCH=""
 
@@ -46,6 +47,11 @@ while [[ $# -gt 0 ]]; do
             SPECIFIC_ARCH="$2"
             shift 2
             ;;
+        --pin)
+            PIN_CHECKSUMS=true
+            FORCE_DOWNLOAD=true
+            shift
+            ;;
         --help|-h)
             echo "Usage: $0 [OPTIONS]"
             echo ""
@@ -53,6 +59,7 @@ while [[ $# -gt 0 ]]; do
             echo "  -f, --force          Force re-download even if binaries exist"
             echo "  -v, --version VER    Download specific FFmpeg version"
             echo "  -a, --arch ARCH      Download only specific architecture (x86_64 or aarch64)"
+            echo "      --pin            Record the SHA-256 of each downloaded archive in ffmpeg-versions.json"
             echo "  -h, --help           Show this help message"
             echo ""
             echo "Examples:"
@@ -61,6 +68,7 @@ while [[ $# -gt 0 ]]; do
             echo "  $0 --arch aarch64   # Download only Apple Silicon version"
             echo "  $0 --version 7.0.2   # Download specific version"
             echo "  $0 --force           # Force re-download"
+            echo "  $0 --pin             # Pin archive checksums for the app's FFmpeg download"
             exit 0
             ;;
         *)
@@ -108,6 +116,19 @@ download_ffmpeg() {
         return 1
     }
     
+    # The app only installs an archive matching the pinned checksum
+    local archive_sha256=$(shasum -a 256 "$temp_dir/ffmpeg.zip" | cut -d ' ' -f 1)
+    local pinned_sha256=$(jq -r ".versions[\"$version\"].$arch_key.sha256 // empty" "$VERSIONS_FILE")
+    if [ "$PIN_CHECKSUMS" = true ]; then
+        local pinned_versions=$(jq --arg sha256 "$archive_sha256" ".versions[\"$version\"].$arch_key.sha256 = \$sha256" "$VERSIONS_FILE")
+        echo "$pinned_versions" > "$VERSIONS_FILE"
+        echo -e "${GREEN}📌 Pinned SHA-256 for $arch: $archive_sha256${NC}"
+    elif [ -n "$pinned_sha256" ] && [ "$pinned_sha256" != "$archive_sha256" ]; then
+        echo -e "${RED}❌ FFmpeg download for $arch doesn't match its pinned SHA-256 (got $archive_sha256)${NC}"
+        rm -rf "$temp_dir"
+        return 1
+    fi
+
     echo -e "${BLUE}📂 Extracting FFmpeg for $arch...${NC}"
     unzip -q "$temp_dir/ffmpeg.zip" -d "$temp_dir" || {
         echo -e "${RED}❌ Failed to extract FFmpeg for $arch${NC}"
@@ -184,15 +205,25 @@ else
     fi
 fi
 
-# Create version info file
+# Create version info file, with the size and hash of each binary so the app
+# can detect a truncated or damaged FFmpeg at startup
 VERSION_DATE=$(jq -r ".versions[\"$TARGET_VERSION\"].date" "$VERSIONS_FILE")
-cat > "$VERSION_INFO_FILE" << EOF
-{
-  "version": "$TARGET_VERSION",
-  "date": "$VERSION_DATE",
-  "updated": "$(date -u +"%Y-%m-%dT%H:%M:%SZ")"
-}
-EOF
+CHECKSUMS='{}'
+for binary in ffmpeg-x86_64-apple-darwin ffmpeg-aarch64-apple-darwin; do
+    if [ -e "$BINARIES_DIR/$binary" ]; then
+        size=$(wc -c < "$BINARIES_DIR/$binary" | tr -d ' ')
+        sha256=$(shasum -a 256 "$BINARIES_DIR/$binary" | cut -d ' ' -f 1)
+        CHECKSUMS=$(echo "$CHECKSUMS" | jq --arg name "$binary" --argjson size "$size" --arg sha256 "$sha256" \
+            '. + {($name): {size: $size, sha256: $sha256}}')
+    fi
+done
+
+jq -n \
+    --arg version "$TARGET_VERSION" \
+    --arg date "$VERSION_DATE" \
+    --arg updated "$(date -u +"%Y-%m-%dT%H:%M:%SZ")" \
+    --argjson binaries "$CHECKSUMS" \
+    '{version: $version, date: $date, updated: $updated, binaries: $binaries}' > "$VERSION_INFO_FILE"
 
 echo ""
 echo -e "${GREEN}🎉 FFmpeg setup complete!${NC}"
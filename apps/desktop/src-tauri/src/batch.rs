@@ -0,0 +1,72 @@
+use serde::Serialize;
+use crate::ffmpeg::{ConversionJob, JobStatus};
+
+/// Aggregate state of the jobs added together from one multi-file drop
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub batch_id: String,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// 0-100, weighted by each job's duration
+    pub progress: f32,
+    /// Remaining conversion time, once there's something to base it on
+    pub eta_seconds: Option<f64>,
+}
+
+pub fn batch_progress(jobs: &[ConversionJob], batch_id: &str) -> Option<BatchProgress> {
+    let batch: Vec<&ConversionJob> = jobs.iter()
+        .filter(|job| job.batch_id.as_deref() == Some(batch_id))
+        .collect();
+    if batch.is_empty() {
+        return None;
+    }
+
+    let count = |status: fn(&JobStatus) -> bool| batch.iter().filter(|job| status(&job.status)).count();
+    let completed = count(|status| matches!(status, JobStatus::Completed | JobStatus::CompletedWithWarnings));
+    let failed = count(|status| matches!(status, JobStatus::Failed));
+    let cancelled = count(|status| matches!(status, JobStatus::Cancelled));
+
+    // Jobs without a probed duration count as one second so they still move the bar
+    let weight = |job: &ConversionJob| job.duration.filter(|d| *d > 0.0).unwrap_or(1.0);
+    let total_weight: f64 = batch.iter().map(|job| weight(job)).sum();
+    let done_weight: f64 = batch.iter()
+        .map(|job| match job.status {
+            JobStatus::Completed | JobStatus::CompletedWithWarnings | JobStatus::Failed | JobStatus::Cancelled => weight(job),
+            _ => weight(job) * f64::from(job.progress) / 100.0,
+        })
+        .sum();
+
+    Some(BatchProgress {
+        batch_id: batch_id.to_string(),
+        total: batch.len(),
+        completed,
+        failed,
+        cancelled,
+        progress: (done_weight / total_weight * 100.0) as f32,
+        eta_seconds: batch_eta(&batch),
+    })
+}
+
+/// Sum of the remaining time of unfinished jobs, using each job's own estimate
+/// or the average speed of jobs already converted in this batch.
+fn batch_eta(batch: &[&ConversionJob]) -> Option<f64> {
+    let speeds: Vec<f64> = batch.iter()
+        .filter_map(|job| job.encode_stats?.speed)
+        .collect();
+    let batch_speed = (!speeds.is_empty()).then(|| speeds.iter().sum::<f64>() / speeds.len() as f64);
+
+    let mut eta = 0.0;
+    for job in batch {
+        if !matches!(job.status, JobStatus::Queued | JobStatus::Ready | JobStatus::Processing) {
+            continue;
+        }
+        let remaining = 1.0 - f64::from(job.progress) / 100.0;
+        let job_seconds = job.estimate.and_then(|estimate| estimate.seconds)
+            .or_else(|| Some(job.duration? / batch_speed?))?;
+        eta += job_seconds * remaining;
+    }
+    Some(eta)
+}
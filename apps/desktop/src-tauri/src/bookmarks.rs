@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+/// Security-scoped bookmark of a folder the user picked. A sandboxed macOS
+/// app loses access to anything outside its container on restart unless it
+/// keeps a bookmark of the folder and resolves it again at launch. Outside
+/// macOS bookmarks are empty and resolving one just returns the stored path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderBookmark {
+    pub path: String,
+    /// Base64 encoded bookmark data
+    pub bookmark: String,
+}
+
+/// A resolved bookmark that's accessible until `stop_access` is called
+pub struct ResolvedBookmark {
+    pub path: String,
+    /// The folder moved or changed; the bookmark should be created again
+    pub stale: bool,
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::runtime::Bool;
+    use objc2_foundation::{
+        NSData, NSError, NSString, NSURL, NSURLBookmarkCreationOptions, NSURLBookmarkResolutionOptions,
+    };
+    use super::ResolvedBookmark;
+
+    fn error_message(error: &NSError) -> String {
+        error.localizedDescription().to_string()
+    }
+
+    pub fn create_bookmark(path: &str) -> Result<Vec<u8>, String> {
+        let url = NSURL::fileURLWithPath(&NSString::from_str(path));
+        let data = url
+            .bookmarkDataWithOptions_includingResourceValuesForKeys_relativeToURL_error(
+                NSURLBookmarkCreationOptions::WithSecurityScope,
+                None,
+                None,
+            )
+            .map_err(|e| format!("Failed to create bookmark for {}: {}", path, error_message(&e)))?;
+        Ok(data.to_vec())
+    }
+
+    pub fn resolve_bookmark(bookmark: &[u8], _path: &str) -> Result<ResolvedBookmark, String> {
+        let data = NSData::with_bytes(bookmark);
+        let mut stale = Bool::NO;
+        // SAFETY: `stale` outlives the call and is only written to
+        let url = unsafe {
+            NSURL::URLByResolvingBookmarkData_options_relativeToURL_bookmarkDataIsStale_error(
+                &data,
+                NSURLBookmarkResolutionOptions::WithSecurityScope,
+                None,
+                &mut stale,
+            )
+        }
+        .map_err(|e| format!("Failed to resolve bookmark: {}", error_message(&e)))?;
+
+        // SAFETY: balanced by `stop_access`
+        if !unsafe { url.startAccessingSecurityScopedResource() } {
+            return Err("Access to the folder was denied".to_string());
+        }
+
+        let path = url.path()
+            .map(|path| path.to_string())
+            .ok_or_else(|| "Bookmark doesn't point to a file path".to_string())?;
+        Ok(ResolvedBookmark { path, stale: stale.as_bool() })
+    }
+
+    pub fn stop_access(bookmark: &[u8]) {
+        let data = NSData::with_bytes(bookmark);
+        let mut stale = Bool::NO;
+        // SAFETY: `stale` outlives the call and is only written to
+        let url = unsafe {
+            NSURL::URLByResolvingBookmarkData_options_relativeToURL_bookmarkDataIsStale_error(
+                &data,
+                NSURLBookmarkResolutionOptions::WithSecurityScope,
+                None,
+                &mut stale,
+            )
+        };
+        if let Ok(url) = url {
+            // SAFETY: only called for bookmarks started in `resolve_bookmark`
+            unsafe { url.stopAccessingSecurityScopedResource() };
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::ResolvedBookmark;
+
+    pub fn create_bookmark(_path: &str) -> Result<Vec<u8>, String> {
+        Ok(Vec::new())
+    }
+
+    pub fn resolve_bookmark(_bookmark: &[u8], path: &str) -> Result<ResolvedBookmark, String> {
+        Ok(ResolvedBookmark { path: path.to_string(), stale: false })
+    }
+
+    pub fn stop_access(_bookmark: &[u8]) {}
+}
+
+pub use platform::{create_bookmark, resolve_bookmark, stop_access};
@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use crate::ffmpeg::stderr_log_dir;
+use crate::history_thumbnails::history_thumbnail_dir;
+use crate::state::AppState;
+use crate::work_dir::work_dir;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheCategory {
+    Thumbnails,
+    /// Waveform images
+    Previews,
+    /// Leftovers in the work folder
+    WorkFiles,
+    /// FFmpeg output of recent conversions and the frontend log
+    Logs,
+}
+
+impl CacheCategory {
+    const ALL: [CacheCategory; 4] = [
+        CacheCategory::Thumbnails,
+        CacheCategory::Previews,
+        CacheCategory::WorkFiles,
+        CacheCategory::Logs,
+    ];
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryUsage {
+    pub category: CacheCategory,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheUsage {
+    pub total_bytes: u64,
+    pub categories: Vec<CategoryUsage>,
+}
+
+/// Folders and files that make up a category
+async fn category_paths(app_handle: &AppHandle, state: &AppState, category: CacheCategory) -> Result<Vec<PathBuf>, String> {
+    let path = app_handle.path();
+    Ok(match category {
+        CacheCategory::Thumbnails | CacheCategory::Previews => {
+            let cache_dir = path.app_cache_dir()
+                .map_err(|e| format!("Failed to get cache dir: {}", e))?;
+            if category == CacheCategory::Thumbnails {
+                vec![cache_dir.join("thumbnails"), history_thumbnail_dir(app_handle)?]
+            } else {
+                vec![cache_dir.join("waveforms")]
+            }
+        }
+        CacheCategory::WorkFiles => vec![work_dir(app_handle, &state.get_settings().await)?],
+        CacheCategory::Logs => {
+            let mut paths = vec![stderr_log_dir(app_handle)?];
+            if let Ok(dir) = path.app_local_data_dir() {
+                paths.push(dir.join("app-debug.log"));
+            }
+            paths
+        }
+    })
+}
+
+pub async fn get_cache_usage(app_handle: &AppHandle, state: &AppState) -> Result<CacheUsage, String> {
+    let mut categories = Vec::new();
+    for category in CacheCategory::ALL {
+        let mut usage = CategoryUsage { category, bytes: 0, files: 0 };
+        for path in category_paths(app_handle, state, category).await? {
+            add_usage(&path, &mut usage);
+        }
+        categories.push(usage);
+    }
+
+    Ok(CacheUsage {
+        total_bytes: categories.iter().map(|usage| usage.bytes).sum(),
+        categories,
+    })
+}
+
+fn add_usage(path: &Path, usage: &mut CategoryUsage) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    if !metadata.is_dir() {
+        usage.bytes += metadata.len();
+        usage.files += 1;
+        return;
+    }
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            add_usage(&entry.path(), usage);
+        }
+    }
+}
+
+/// Delete the files in the given categories and return the usage afterwards.
+/// Thumbnails of jobs still in the queue are kept, and work files can't be
+/// cleared while a conversion is running.
+pub async fn clear_cache(app_handle: &AppHandle, state: &AppState, categories: &[CacheCategory]) -> Result<CacheUsage, String> {
+    if categories.contains(&CacheCategory::WorkFiles) && state.is_any_job_processing().await {
+        return Err("Work files can't be cleared while a conversion is running".to_string());
+    }
+
+    let queued_thumbnails: HashSet<PathBuf> = state.get_all_jobs().await
+        .into_iter()
+        .filter_map(|job| job.thumbnail_path.map(PathBuf::from))
+        .collect();
+
+    for &category in categories {
+        for path in category_paths(app_handle, state, category).await? {
+            if path.is_dir() {
+                let Ok(entries) = fs::read_dir(&path) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let entry_path = entry.path();
+                    if category == CacheCategory::Thumbnails && queued_thumbnails.contains(&entry_path) {
+                        continue;
+                    }
+                    remove_path(&entry_path);
+                }
+            } else if path.exists() {
+                remove_path(&path);
+            }
+        }
+    }
+
+    get_cache_usage(app_handle, state).await
+}
+
+fn remove_path(path: &Path) {
+    let result = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+    if let Err(e) = result {
+        eprintln!("Failed to remove {}: {}", path.display(), e);
+    }
+}
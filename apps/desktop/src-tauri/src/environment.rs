@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::{NotificationExt, PermissionState};
+use tokio::process::Command;
+use crate::ffmpeg::get_ffmpeg_binary;
+use crate::output_locations::location_status;
+use crate::state::AppSettings;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EnvironmentCheckKind {
+    /// FFmpeg is bundled and can be started
+    FFmpeg,
+    CacheDirectory,
+    DataDirectory,
+    /// The output folder from settings exists and is writable
+    OutputDirectory,
+    Notifications,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentCheck {
+    pub kind: EnvironmentCheckKind,
+    pub passed: bool,
+    /// What's wrong, or a note worth showing even when the check passed
+    pub message: Option<String>,
+}
+
+/// Results of the environment checks, run on first launch so onboarding can
+/// help fix problems before the first job fails
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentReport {
+    /// The checks ran because this is the first launch
+    pub first_run: bool,
+    pub passed: bool,
+    pub checks: Vec<EnvironmentCheck>,
+    pub checked_at: String,
+}
+
+fn report_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle.path().app_data_dir()
+        .map(|dir| dir.join("environment_checks.json"))
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+/// The report from the first launch, or `None` if the checks never ran
+pub fn load_report(app_handle: &AppHandle) -> Option<EnvironmentReport> {
+    let content = fs::read_to_string(report_path(app_handle).ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save_report(app_handle: &AppHandle, report: &EnvironmentReport) -> Result<(), String> {
+    let path = report_path(app_handle)?;
+    let content = serde_json::to_string_pretty(report)
+        .map_err(|e| format!("Failed to serialize environment checks: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write environment checks: {}", e))
+}
+
+pub async fn run_checks(app_handle: &AppHandle, settings: &AppSettings, first_run: bool) -> EnvironmentReport {
+    let path = app_handle.path();
+    let checks = vec![
+        check(EnvironmentCheckKind::FFmpeg, check_ffmpeg(app_handle).await),
+        check(EnvironmentCheckKind::CacheDirectory, check_app_dir(path.app_cache_dir())),
+        check(EnvironmentCheckKind::DataDirectory, check_app_dir(path.app_data_dir())),
+        check(EnvironmentCheckKind::OutputDirectory, check_output_directory(&settings.output_directory)),
+        check(EnvironmentCheckKind::Notifications, check_notifications(app_handle)),
+    ];
+
+    EnvironmentReport {
+        first_run,
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// `Ok` carries an optional note for checks that passed
+fn check(kind: EnvironmentCheckKind, result: Result<Option<String>, String>) -> EnvironmentCheck {
+    match result {
+        Ok(message) => EnvironmentCheck { kind, passed: true, message },
+        Err(message) => EnvironmentCheck { kind, passed: false, message: Some(message) },
+    }
+}
+
+async fn check_ffmpeg(app_handle: &AppHandle) -> Result<Option<String>, String> {
+    let ffmpeg_path = get_ffmpeg_binary(app_handle)?;
+    let output = Command::new(&ffmpeg_path)
+        .args(["-hide_banner", "-version"])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("FFmpeg at {} can't be started: {}", ffmpeg_path.display(), e))?;
+    if !output.status.success() {
+        return Err(format!("FFmpeg at {} exited with {}", ffmpeg_path.display(), output.status));
+    }
+    Ok(None)
+}
+
+fn check_app_dir(dir: tauri::Result<PathBuf>) -> Result<Option<String>, String> {
+    let dir = dir.map_err(|e| format!("Failed to get directory: {}", e))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    if !location_status(&dir.to_string_lossy()).writable {
+        return Err(format!("{} is not writable", dir.display()));
+    }
+    Ok(None)
+}
+
+pub fn check_output_directory(output_directory: &str) -> Result<Option<String>, String> {
+    if output_directory.is_empty() {
+        return Ok(Some("Converted files are saved next to their originals".to_string()));
+    }
+    let status = location_status(output_directory);
+    if !status.exists {
+        Err(format!("Output folder {} is missing", output_directory))
+    } else if !status.writable {
+        Err(format!("Output folder {} is not writable", output_directory))
+    } else {
+        Ok(None)
+    }
+}
+
+fn check_notifications(app_handle: &AppHandle) -> Result<Option<String>, String> {
+    let state = app_handle.notification().permission_state()
+        .map_err(|e| format!("Failed to check notification permission: {}", e))?;
+    match state {
+        PermissionState::Granted => Ok(None),
+        PermissionState::Denied => Err("Notifications are turned off for Transpoze".to_string()),
+        PermissionState::Prompt | PermissionState::PromptWithRationale => {
+            Ok(Some("Transpoze will ask before showing notifications".to_string()))
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+/// Errors of the conversion path. A failed job keeps the message and its
+/// `ErrorCode`, which the frontend reads from the job to show an actionable
+/// message. Commands return `String`; both convert into each other so `?`
+/// works across the boundary.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("FFmpeg binary '{0}' not found")]
+    FfmpegNotFound(String),
+    #[error("Failed to start FFmpeg: {0}")]
+    SpawnFailed(#[source] std::io::Error),
+    /// FFmpeg ran and exited with an error. `stderr` is the line that
+    /// explains it, when FFmpeg printed one.
+    #[error("FFmpeg conversion failed: {}", stderr.as_deref().unwrap_or("unknown error"))]
+    EncodeFailed { stderr: Option<String> },
+    #[error("Conversion cancelled")]
+    Cancelled,
+    /// The app quit or crashed while the job was converting
+    #[error("Interrupted when the app was closed. The unfinished output was deleted.")]
+    Interrupted,
+    #[error("{context}: {source}")]
+    Io {
+        context: &'static str,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{0}")]
+    Parse(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+/// Stable identifier of an error kind, stored on failed jobs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    FfmpegNotFound,
+    SpawnFailed,
+    EncodeFailed,
+    Cancelled,
+    Interrupted,
+    Io,
+    Parse,
+    Other,
+}
+
+impl Error {
+    pub fn io(context: &'static str, source: std::io::Error) -> Self {
+        Error::Io { context, source }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::FfmpegNotFound(_) => ErrorCode::FfmpegNotFound,
+            Error::SpawnFailed(_) => ErrorCode::SpawnFailed,
+            Error::EncodeFailed { .. } => ErrorCode::EncodeFailed,
+            Error::Cancelled => ErrorCode::Cancelled,
+            Error::Interrupted => ErrorCode::Interrupted,
+            Error::Io { .. } => ErrorCode::Io,
+            Error::Parse(_) => ErrorCode::Parse,
+            Error::Other(_) => ErrorCode::Other,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_match_variants() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(Error::io("Failed to move the output", missing).code(), ErrorCode::Io);
+        assert_eq!(Error::EncodeFailed { stderr: None }.code(), ErrorCode::EncodeFailed);
+        assert_eq!(Error::from("Disk full".to_string()).code(), ErrorCode::Other);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::ffmpeg::{ConversionJob, VideoPreset};
+use crate::filters::output_duration_factor;
+
+/// x264 bits per pixel per frame at CRF 23, a middle-of-the-road figure for
+/// typical camera and screen footage.
+const BITS_PER_PIXEL_AT_CRF_23: f64 = 0.07;
+const DEFAULT_FPS: f64 = 30.0;
+const DEFAULT_AUDIO_KBPS: f64 = 128.0;
+/// Quality-based encodes of an already compressed source rarely end up much
+/// above the source bitrate, however detailed the frame size suggests it is
+const SOURCE_BITRATE_CEILING: f64 = 1.25;
+/// Share of a target size kept free for the container and bitrate overshoot
+const TARGET_SIZE_HEADROOM: f64 = 0.03;
+/// Older samples fade out once a bucket has this many, so the model follows
+/// hardware or FFmpeg upgrades instead of averaging over all time.
+const MAX_SAMPLE_WEIGHT: u32 = 20;
+
+/// Rough output size and conversion time for a job with its chosen preset
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputEstimate {
+    pub size_bytes: u64,
+    /// Only known once conversions have finished before
+    pub seconds: Option<f64>,
+    /// The output would take more bytes per second than the source, so the
+    /// conversion makes the file bigger instead of smaller
+    #[serde(default)]
+    pub larger_than_source: bool,
+}
+
+pub fn estimate_output(job: &ConversionJob, throughput: &ThroughputModel) -> Option<OutputEstimate> {
+    let source_duration = job.duration?;
+    let output_duration = output_duration(job)?;
+
+    let video_kbps = if job.preset.video_codec == "copy" {
+        // Stream copy keeps the source bitrate
+        let file_size = job.file_size? as f64;
+        file_size * 8.0 / 1000.0 / source_duration.max(f64::EPSILON)
+    } else if let Some(kbps) = target_video_kbps(job) {
+        kbps
+    } else {
+        let kbps = estimate_video_kbps(&job.preset, job.width?, job.height?, job.fps.unwrap_or(DEFAULT_FPS));
+        match job.bitrate_kbps {
+            Some(source_kbps) if job.preset.bitrate.is_none() => kbps.min(f64::from(source_kbps) * SOURCE_BITRATE_CEILING),
+            _ => kbps,
+        }
+    };
+    let audio_kbps = if job.preset.video_codec == "copy" && job.preset.audio_codec == "copy" {
+        // Already part of the source bitrate
+        0.0
+    } else if job.preset.is_animation() {
+        0.0
+    } else {
+        job.preset.audio_bitrate.as_deref()
+            .and_then(parse_bitrate_kbps)
+            .unwrap_or(DEFAULT_AUDIO_KBPS)
+    };
+
+    let size_bytes = ((video_kbps + audio_kbps) * 1000.0 / 8.0 * output_duration) as u64;
+    let seconds = throughput.predict(&job.preset.name, job.height).map(|speed| output_duration / speed);
+    // Compare with the source over the same length, so trimming doesn't hide growth
+    let larger_than_source = job.file_size.is_some_and(|file_size| {
+        size_bytes as f64 > file_size as f64 * output_duration / source_duration.max(f64::EPSILON)
+    });
+
+    Some(OutputEstimate { size_bytes, seconds, larger_than_source })
+}
+
+/// Seconds of output after trimming, looping and boomerang
+pub fn output_duration(job: &ConversionJob) -> Option<f64> {
+    let source_duration = job.duration?;
+    let (start, end) = match job.resolved_trim {
+        Some(range) => (range.start, range.end.min(source_duration)),
+        None => (
+            job.options.trim_start.unwrap_or(0.0).max(0.0),
+            job.options.trim_end.unwrap_or(source_duration).min(source_duration),
+        ),
+    };
+    Some((end - start).max(0.0) * output_duration_factor(job))
+}
+
+/// Video bitrate that fits a job into its target size once the audio and some
+/// headroom are taken off. MB are decimal so the output also fits limits
+/// meant as MiB. None without a target or a duration, or when the audio
+/// alone would fill the target.
+pub fn target_video_kbps(job: &ConversionJob) -> Option<f64> {
+    let target_mb = job.options.target_size_mb?;
+    let output_duration = output_duration(job).filter(|duration| *duration > 0.0)?;
+    let audio_kbps = job.preset.audio_bitrate.as_deref()
+        .and_then(parse_bitrate_kbps)
+        .unwrap_or(DEFAULT_AUDIO_KBPS);
+
+    let total_kbps = target_mb * 1_000_000.0 * 8.0 / 1000.0 / output_duration * (1.0 - TARGET_SIZE_HEADROOM);
+    let video_kbps = total_kbps - audio_kbps;
+    (video_kbps > 0.0).then_some(video_kbps)
+}
+
+fn estimate_video_kbps(preset: &VideoPreset, width: u32, height: u32, fps: f64) -> f64 {
+    let (width, height) = scaled_size(preset.scale.as_deref(), width, height);
+    let pixels_per_second = f64::from(width) * f64::from(height) * fps;
+
+    let crf_kbps = preset.crf.map(|crf| {
+        // Every 6 CRF steps roughly halves (or doubles) the bitrate
+        let quality = 2f64.powf((23.0 - f64::from(crf)) / 6.0);
+        pixels_per_second * BITS_PER_PIXEL_AT_CRF_23 * quality * codec_efficiency(&preset.video_codec) / 1000.0
+    });
+    let target_kbps = preset.bitrate.as_deref().and_then(parse_bitrate_kbps);
+
+    match (crf_kbps, target_kbps) {
+        (Some(crf_kbps), Some(target_kbps)) => crf_kbps.min(target_kbps),
+        (Some(kbps), None) | (None, Some(kbps)) => kbps,
+        (None, None) => pixels_per_second * BITS_PER_PIXEL_AT_CRF_23 / 1000.0,
+    }
+}
+
+/// Bitrate needed relative to x264 for similar quality
+fn codec_efficiency(codec: &str) -> f64 {
+    match codec {
+        "libx265" | "hevc_videotoolbox" => 0.6,
+        "libvpx-vp9" => 0.65,
+        "libaom-av1" | "libsvtav1" => 0.5,
+        _ => 1.0,
+    }
+}
+
+/// Apply a preset scale like "720:-1" to the source frame size
+pub fn scaled_size(scale: Option<&str>, width: u32, height: u32) -> (u32, u32) {
+    let Some((scale_width, scale_height)) = scale.and_then(|scale| scale.split_once(':')) else {
+        return (width, height);
+    };
+    let scale_width = scale_width.parse::<i64>().ok().filter(|w| *w > 0);
+    let scale_height = scale_height.parse::<i64>().ok().filter(|h| *h > 0);
+    let aspect = f64::from(width) / f64::from(height.max(1));
+
+    match (scale_width, scale_height) {
+        (Some(w), Some(h)) => (w as u32, h as u32),
+        (Some(w), None) => (w as u32, (w as f64 / aspect) as u32),
+        (None, Some(h)) => ((h as f64 * aspect) as u32, h as u32),
+        (None, None) => (width, height),
+    }
+}
+
+/// Parse FFmpeg bitrate strings such as "2M", "1500k" or "128000"
+fn parse_bitrate_kbps(bitrate: &str) -> Option<f64> {
+    let bitrate = bitrate.trim();
+    if let Some(value) = bitrate.strip_suffix(['M', 'm']) {
+        value.parse::<f64>().ok().map(|mbps| mbps * 1000.0)
+    } else if let Some(value) = bitrate.strip_suffix(['K', 'k']) {
+        value.parse::<f64>().ok()
+    } else {
+        bitrate.parse::<f64>().ok().map(|bps| bps / 1000.0)
+    }
+}
+
+/// Encoding speed learned from finished conversions, bucketed by preset and
+/// source resolution. Persisted separately from history so clearing the
+/// history doesn't throw away what we know about this machine.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThroughputModel {
+    entries: HashMap<String, ThroughputEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThroughputEntry {
+    preset: String,
+    resolution: String,
+    samples: u32,
+    /// Seconds of output encoded per second of wall time
+    speed: f64,
+}
+
+impl ThroughputModel {
+    pub fn record(&mut self, preset: &str, height: Option<u32>, speed: f64) {
+        if !speed.is_finite() || speed <= 0.0 {
+            return;
+        }
+
+        let resolution = resolution_bucket(height);
+        let entry = self.entries
+            .entry(format!("{}|{}", preset, resolution))
+            .or_insert(ThroughputEntry {
+                preset: preset.to_string(),
+                resolution: resolution.to_string(),
+                samples: 0,
+                speed,
+            });
+        let weight = entry.samples.min(MAX_SAMPLE_WEIGHT - 1);
+        entry.speed = (entry.speed * f64::from(weight) + speed) / f64::from(weight + 1);
+        entry.samples = entry.samples.saturating_add(1);
+    }
+
+    /// Expected speed for a preset at a resolution, falling back to the preset at
+    /// any resolution and then to everything we've measured.
+    pub fn predict(&self, preset: &str, height: Option<u32>) -> Option<f64> {
+        let resolution = resolution_bucket(height);
+        if let Some(entry) = self.entries.get(&format!("{}|{}", preset, resolution)) {
+            return Some(entry.speed);
+        }
+
+        self.weighted_speed(|entry| entry.preset == preset)
+            .or_else(|| self.weighted_speed(|_| true))
+    }
+
+    fn weighted_speed(&self, include: impl Fn(&ThroughputEntry) -> bool) -> Option<f64> {
+        let (total, weight) = self.entries.values()
+            .filter(|entry| include(entry))
+            .fold((0.0, 0.0), |(total, weight), entry| {
+                let samples = f64::from(entry.samples.max(1));
+                (total + entry.speed * samples, weight + samples)
+            });
+        (weight > 0.0).then(|| total / weight)
+    }
+}
+
+fn resolution_bucket(height: Option<u32>) -> &'static str {
+    match height {
+        None => "unknown",
+        Some(h) if h < 720 => "sd",
+        Some(h) if h < 1080 => "720p",
+        Some(h) if h < 1440 => "1080p",
+        Some(h) if h < 2160 => "1440p",
+        Some(_) => "2160p",
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+use serde::Serialize;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::ffmpeg::{ConversionJob, ProgressUpdate};
+use crate::state::{AppState, QueueProgress};
+
+/// The single event the frontend listens to for queue changes
+pub const JOBS_CHANGED: &str = "jobs-changed";
+
+/// Aggregated progress for compact windows that don't track every job
+pub const QUEUE_PROGRESS: &str = "queue-progress";
+
+/// Live stats of the running conversion, sent with every FFmpeg progress report
+pub const CONVERSION_PROGRESS: &str = "conversion-progress";
+
+/// Bumped whenever the payload shape changes incompatibly
+pub const JOBS_CHANGED_EVENT_VERSION: u32 = 2;
+
+/// Changes are collected for this long and sent as one event, so bulk adds
+/// and clears on huge queues don't flood the frontend
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobChange {
+    /// Status, status message, probe results or options changed
+    Updated,
+    Progress,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobUpdate {
+    pub change: JobChange,
+    /// Full snapshot of the job when the batch was sent
+    pub job: ConversionJob,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobsChangedEvent {
+    pub version: u32,
+    pub updates: Vec<JobUpdate>,
+    pub removed_job_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionProgressEvent<'a> {
+    pub job_id: &'a str,
+    #[serde(flatten)]
+    pub progress: ProgressUpdate,
+}
+
+/// Sent right away rather than batched, so the stats stay live
+pub fn emit_conversion_progress(app_handle: &AppHandle, job_id: &str, progress: ProgressUpdate) {
+    let event = ConversionProgressEvent { job_id, progress };
+    if let Err(e) = app_handle.emit(CONVERSION_PROGRESS, event) {
+        println!("ERROR: Failed to emit {} event: {}", CONVERSION_PROGRESS, e);
+    }
+}
+
+/// Queue a job's current snapshot for the next `jobs-changed` event.
+/// Every change except progress also saves the queue when the batch is sent,
+/// so it survives a restart.
+pub async fn emit_job_state(app_handle: &AppHandle, state: &AppState, job_id: &str, change: JobChange) {
+    if state.record_job_change(job_id, change).await {
+        schedule_flush(app_handle, state);
+    }
+}
+
+pub async fn emit_jobs_removed(app_handle: &AppHandle, state: &AppState, job_ids: Vec<String>) -> Result<(), String> {
+    if state.record_jobs_removed(job_ids).await {
+        schedule_flush(app_handle, state);
+    }
+    Ok(())
+}
+
+fn schedule_flush(app_handle: &AppHandle, state: &AppState) {
+    let app_handle = app_handle.clone();
+    let state = state.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FLUSH_INTERVAL).await;
+        flush_job_changes(&app_handle, &state).await;
+    });
+}
+
+/// Send everything that changed since the last batch. Also called on exit so
+/// the last changes are saved.
+pub async fn flush_job_changes(app_handle: &AppHandle, state: &AppState) {
+    let pending = state.take_job_changes().await;
+    if pending.order.is_empty() && pending.removed_job_ids.is_empty() {
+        return;
+    }
+
+    if pending.needs_save {
+        if let Err(e) = state.save_jobs(app_handle).await {
+            eprintln!("Failed to save jobs: {}", e);
+        }
+    }
+
+    let mut updates = Vec::with_capacity(pending.order.len());
+    for job_id in &pending.order {
+        // Jobs removed without going through emit_jobs_removed are skipped
+        if let (Some(job), Some(change)) = (state.get_job(job_id).await, pending.changes.get(job_id)) {
+            updates.push(JobUpdate { change: *change, job });
+        }
+    }
+
+    let event = JobsChangedEvent {
+        version: JOBS_CHANGED_EVENT_VERSION,
+        updates,
+        removed_job_ids: pending.removed_job_ids,
+    };
+    if let Err(e) = app_handle.emit(JOBS_CHANGED, event) {
+        println!("ERROR: Failed to emit {} event: {}", JOBS_CHANGED, e);
+    }
+
+    let progress = state.queue_progress().await;
+    show_taskbar_progress(app_handle, &progress);
+    if let Err(e) = app_handle.emit(QUEUE_PROGRESS, progress) {
+        println!("ERROR: Failed to emit {} event: {}", QUEUE_PROGRESS, e);
+    }
+}
+
+/// Mirror the queue run on the dock icon (macOS) or taskbar button (Windows)
+/// so it can be followed with the window minimized. Cleared once the run ends.
+fn show_taskbar_progress(app_handle: &AppHandle, progress: &QueueProgress) {
+    let Some(window) = app_handle.get_webview_window("main") else {
+        return;
+    };
+
+    let running = progress.finished_jobs < progress.total_jobs;
+    let state = if running {
+        ProgressBarState {
+            status: Some(ProgressBarStatus::Normal),
+            progress: Some(progress.percent.clamp(0.0, 100.0) as u64),
+        }
+    } else {
+        ProgressBarState { status: Some(ProgressBarStatus::None), progress: None }
+    };
+    if let Err(e) = window.set_progress_bar(state) {
+        eprintln!("Failed to update taskbar progress: {}", e);
+    }
+}
@@ -1,10 +1,22 @@
+use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::OnceLock;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
 use tokio::process::Command;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use crate::ffmpeg_parser::{parse_progress_line, parse_duration_from_info, parse_progress_time};
+use tokio::sync::watch;
+use crate::ffmpeg_parser::{parse_progress_line, FFmpegProgress, parse_progress_time, parse_video_resolution, parse_cropdetect_line, parse_silence_start, parse_silence_end, audible_range, parse_showinfo_pts_time, parse_timecode, shift_timecode, StreamInfo, StreamKind};
+#[cfg(unix)]
+use crate::ffmpeg_parser::parse_ps_usage;
+use crate::filters::{build_audio_filters, build_video_filters, output_duration_factor, validate_job_options};
+use crate::state::{AppSettings, AppState};
+use crate::error::{Error, ErrorCode};
+use crate::estimate::{output_duration, target_video_kbps, OutputEstimate};
+use crate::media_info::parse_media_info;
+use crate::quality::QualityScore;
+use crate::status_message::StatusMessage;
 use crate::{log_debug, log_ffmpeg, log_progress};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,18 +30,357 @@ pub struct ConversionJob {
     pub progress: f32,
     pub duration: Option<f64>,
     pub error: Option<String>,
-    pub status_message: Option<String>,
+    /// Kind of the error, for actionable messages and retry decisions
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+    /// Tail of FFmpeg's output from the last conversion attempt. Too big to
+    /// send with every job update, so it's only read through `get_job_log`.
+    #[serde(skip)]
+    pub ffmpeg_log: Vec<String>,
+    #[serde(default, deserialize_with = "crate::status_message::deserialize_status_message")]
+    pub status_message: Option<StatusMessage>,
     pub thumbnail_path: Option<String>,
+    /// Source timestamp the thumbnail was taken from
+    #[serde(default)]
+    pub thumbnail_time: Option<f64>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub video_codec: Option<String>,
+    #[serde(default)]
+    pub fps: Option<f64>,
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    #[serde(default)]
+    pub streams: Vec<StreamInfo>,
+    #[serde(default)]
+    pub source_timecode: Option<String>,
+    /// Display rotation of the first video stream, in degrees
+    #[serde(default)]
+    pub rotation: Option<f64>,
+    /// Overall bitrate of the source
+    #[serde(default)]
+    pub bitrate_kbps: Option<u32>,
+    /// Channels of the first audio stream
+    #[serde(default)]
+    pub audio_channels: Option<u32>,
+    #[serde(default)]
+    pub detected_crop: Option<CropRect>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Set while the output folder is missing or not writable
+    #[serde(default)]
+    pub output_problem: Option<String>,
+    #[serde(default)]
+    pub estimate: Option<OutputEstimate>,
+    #[serde(default)]
+    pub encode_stats: Option<EncodeStats>,
+    /// Latest resource sample of the running FFmpeg process
+    #[serde(default)]
+    pub resource_usage: Option<ResourceUsage>,
+    /// Part of the source that gets converted, resolved when the conversion
+    /// starts from the requested trim, silence detection and keyframes.
+    #[serde(default)]
+    pub resolved_trim: Option<TimeRange>,
+    #[serde(default)]
+    pub options: JobOptions,
+    /// Set on all jobs added together from one multi-file drop
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    /// How close the output is to the source, when quality scoring is on
+    #[serde(default)]
+    pub quality_score: Option<QualityScore>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Per-job conversion options chosen by the user on top of the preset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JobOptions {
+    /// Overrides the global `auto_crop` setting for this job when set.
+    pub apply_detected_crop: Option<bool>,
+    /// Pixels to cut from each edge. Replaces the detected crop when set.
+    pub crop_edges: Option<EdgeInsets>,
+    /// Pixels of black padding to add around each edge after cropping.
+    pub pad_edges: Option<EdgeInsets>,
+    /// Cut leading and trailing silence (video is trimmed along with it).
+    pub trim_silence: bool,
+    /// Shift audio against video. Positive values delay the audio,
+    /// negative values make it play earlier.
+    pub audio_delay_ms: i32,
+    /// Map every audio (and, where the container allows, subtitle) track
+    /// instead of letting FFmpeg pick a single stream of each type.
+    pub keep_all_streams: bool,
+    /// Copy data streams such as GoPro GPS/telemetry (gpmd). MOV only.
+    pub keep_data_streams: bool,
+    /// Play the clip this many times in a row (0 and 1 both mean once).
+    pub loop_count: u32,
+    /// Append a reversed copy of the clip.
+    pub boomerang: bool,
+    /// Start of the part to keep, in seconds.
+    pub trim_start: Option<f64>,
+    /// End of the part to keep, in seconds.
+    pub trim_end: Option<f64>,
+    /// Cut a trim without re-encoding, starting at the keyframe at or
+    /// before `trim_start`. Instant and lossless, but not frame-accurate.
+    pub fast_cut: bool,
+    /// SMPTE start timecode ("HH:MM:SS:FF") written instead of the source's.
+    pub start_timecode: Option<String>,
+    /// Copy AC-3/E-AC-3/DTS/TrueHD audio untouched instead of encoding it.
+    pub audio_passthrough: bool,
+    /// Largest output size in MB. Replaces the preset's quality with a
+    /// two-pass encode at the bitrate that fits. H.264 only.
+    pub target_size_mb: Option<f64>,
+    /// Advanced: FFmpeg options added after the preset's own extra options,
+    /// right before the output path.
+    pub extra_args: Vec<String>,
+}
+
+/// One progress report from a running conversion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressUpdate {
+    pub percent: f32,
+    pub fps: Option<f32>,
+    /// Multiple of realtime reported by FFmpeg
+    pub speed: Option<f32>,
+    pub bitrate_kbps: Option<f32>,
+    /// Bytes written to the output so far
+    pub output_bytes: Option<u64>,
+    pub eta_seconds: Option<f64>,
+}
+
+impl ProgressUpdate {
+    /// Progress at `time` seconds into `duration` seconds of output. The ETA
+    /// comes from FFmpeg's speed, or from the elapsed time while it reports none.
+    fn new(time: f64, duration: f64, elapsed: f64, stats: Option<&FFmpegProgress>) -> Self {
+        let fraction = if duration > 0.0 { (time / duration).clamp(0.0, 1.0) } else { 0.0 };
+        let speed = stats.and_then(|stats| stats.speed);
+        let eta_seconds = match speed {
+            Some(speed) if speed > 0.0 && duration > 0.0 => Some((duration - time).max(0.0) / f64::from(speed)),
+            _ if fraction > 0.0 => Some(elapsed * (1.0 - fraction) / fraction),
+            _ => None,
+        };
+
+        ProgressUpdate {
+            percent: (fraction * 100.0) as f32,
+            fps: stats.and_then(|stats| stats.fps),
+            speed,
+            bitrate_kbps: stats.and_then(FFmpegProgress::bitrate_kbps),
+            output_bytes: stats.and_then(FFmpegProgress::size_bytes),
+            eta_seconds,
+        }
+    }
+
+    /// Map progress within pass `pass` of `passes` onto the whole encode. Later
+    /// passes are assumed to take as long as this one, which overestimates a
+    /// little since the first x264 pass runs faster.
+    fn within_pass(self, pass: u8, passes: u8) -> Self {
+        let remaining_passes = f64::from(passes.saturating_sub(pass));
+        let fraction = f64::from(self.percent) / 100.0;
+        let eta_seconds = self.eta_seconds.map(|eta| {
+            let pass_seconds = if fraction < 1.0 { eta / (1.0 - fraction) } else { 0.0 };
+            eta + pass_seconds * remaining_passes
+        });
+        let final_pass = pass == passes;
+
+        ProgressUpdate {
+            percent: (f32::from(pass - 1) * 100.0 + self.percent) / f32::from(passes),
+            // Earlier passes write nothing but the log
+            bitrate_kbps: if final_pass { self.bitrate_kbps } else { None },
+            output_bytes: if final_pass { self.output_bytes } else { None },
+            eta_seconds,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUsage {
+    /// Percent of one core, so multi-threaded encodes go above 100
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    /// The encoder runs on VideoToolbox instead of the CPU. macOS doesn't
+    /// expose per-process GPU load, so this is what we can report for hardware use.
+    pub hardware_encoder: bool,
+}
+
+const RESOURCE_SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// FFmpeg output lines kept from the end of each conversion, for the job log
+/// and bug reports
+pub const STDERR_TAIL_LINES: usize = 200;
+/// Conversions whose FFmpeg output is kept on disk, newest first
+const MAX_STDERR_LOGS: usize = 20;
+
+/// Folder with the tail of FFmpeg's output for recent conversions, one file per job
+pub fn stderr_log_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle.path().app_log_dir()
+        .map(|dir| dir.join("ffmpeg"))
+        .map_err(|e| format!("Failed to get log dir: {}", e))
+}
+
+fn save_stderr_tail(app_handle: &AppHandle, job_id: &str, tail: &VecDeque<String>) {
+    let Ok(dir) = stderr_log_dir(app_handle) else {
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("Failed to create FFmpeg log directory: {}", e);
+        return;
+    }
+
+    let content: String = tail.iter().map(|line| format!("{}\n", line)).collect();
+    if let Err(e) = std::fs::write(dir.join(format!("{}.log", job_id)), content) {
+        eprintln!("Failed to save FFmpeg output: {}", e);
+    }
+
+    // Only keep the most recent conversions
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return;
+    };
+    let mut logs: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    logs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in logs.into_iter().skip(MAX_STDERR_LOGS) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// FFmpeg output saved for a job, if it's one of the recent conversions
+pub fn read_stderr_log(app_handle: &AppHandle, job_id: &str) -> Result<Option<Vec<String>>, String> {
+    let path = stderr_log_dir(app_handle)?.join(format!("{}.log", job_id));
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(Some(content.lines().map(str::to_string).collect())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read FFmpeg log: {}", e)),
+    }
+}
+
+#[cfg(unix)]
+async fn sample_process_usage(pid: u32, hardware_encoder: bool) -> Option<ResourceUsage> {
+    let output = Command::new("ps")
+        .args(["-o", "%cpu=,rss=", "-p", &pid.to_string()])
+        .output()
+        .await
+        .ok()?;
+    let (cpu_percent, memory_bytes) = parse_ps_usage(&String::from_utf8_lossy(&output.stdout))?;
+    Some(ResourceUsage { cpu_percent, memory_bytes, hardware_encoder })
+}
+
+/// There's no `ps` to ask, so jobs just don't show resource usage
+#[cfg(not(unix))]
+async fn sample_process_usage(_pid: u32, _hardware_encoder: bool) -> Option<ResourceUsage> {
+    None
+}
+
+/// How long the FFmpeg encode took once it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeStats {
+    pub elapsed_seconds: f64,
+    /// Seconds of output encoded per second of wall time (e.g. 2.5 = 2.5x realtime).
+    /// Unknown when the source duration couldn't be probed.
+    pub speed: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl TimeRange {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EdgeInsets {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl EdgeInsets {
+    pub fn is_empty(&self) -> bool {
+        self.top == 0 && self.bottom == 0 && self.left == 0 && self.right == 0
+    }
+
+    pub fn horizontal(&self) -> u32 {
+        self.left + self.right
+    }
+
+    pub fn vertical(&self) -> u32 {
+        self.top + self.bottom
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CropRect {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl CropRect {
+    pub fn to_filter(&self) -> String {
+        format!("crop={}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum JobStatus {
     Queued,
     Ready,
     Processing,
     Completed,
+    /// Converted, but verifying the output found problems (see `warnings`)
+    CompletedWithWarnings,
     Failed,
+    Cancelled,
+}
+
+/// x264/x265 `-preset`: slower settles on smaller files at the same quality
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderSpeed {
+    Ultrafast,
+    Superfast,
+    Veryfast,
+    Faster,
+    Fast,
+    #[default]
+    Medium,
+    Slow,
+    Slower,
+    Veryslow,
+}
+
+impl EncoderSpeed {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EncoderSpeed::Ultrafast => "ultrafast",
+            EncoderSpeed::Superfast => "superfast",
+            EncoderSpeed::Veryfast => "veryfast",
+            EncoderSpeed::Faster => "faster",
+            EncoderSpeed::Fast => "fast",
+            EncoderSpeed::Medium => "medium",
+            EncoderSpeed::Slow => "slow",
+            EncoderSpeed::Slower => "slower",
+            EncoderSpeed::Veryslow => "veryslow",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +393,28 @@ pub struct VideoPreset {
     pub bitrate: Option<String>,
     pub crf: Option<u8>,
     pub scale: Option<String>,
+    #[serde(default)]
+    pub audio_bitrate: Option<String>,
+    #[serde(default)]
+    pub audio_rate_control: Option<AudioRateControl>,
+    /// AAC VBR quality (`-q:a`, 0.1-2.0) used with variable rate control.
+    #[serde(default)]
+    pub audio_quality: Option<f32>,
+    /// Container the preset writes, e.g. `mov`. MP4 when unset.
+    #[serde(default)]
+    pub container: Option<String>,
+    /// Advanced: FFmpeg options added right before the output path
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioRateControl {
+    /// Fixed bitrate from `audio_bitrate`.
+    Constant,
+    /// Quality-based: `audio_quality` for AAC, `audio_bitrate` as the average target for Opus.
+    Variable,
 }
 
 impl VideoPreset {
@@ -55,6 +428,11 @@ impl VideoPreset {
                 bitrate: None,
                 crf: Some(18),
                 scale: None,
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: None,
+                extra_args: Vec::new(),
             },
             VideoPreset {
                 name: "Balanced".to_string(),
@@ -64,6 +442,11 @@ impl VideoPreset {
                 bitrate: None,
                 crf: Some(23),
                 scale: None,
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: None,
+                extra_args: Vec::new(),
             },
             VideoPreset {
                 name: "Web".to_string(),
@@ -73,6 +456,11 @@ impl VideoPreset {
                 bitrate: Some("2M".to_string()),
                 crf: Some(28),
                 scale: None,
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: None,
+                extra_args: Vec::new(),
             },
             VideoPreset {
                 name: "Mobile".to_string(),
@@ -82,11 +470,142 @@ impl VideoPreset {
                 bitrate: Some("1M".to_string()),
                 crf: Some(30),
                 scale: Some("720:-1".to_string()),
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: None,
+                extra_args: Vec::new(),
+            },
+            VideoPreset {
+                name: "Remux".to_string(),
+                description: "Change the container without re-encoding. Instant and lossless, same size as the original.".to_string(),
+                video_codec: "copy".to_string(),
+                audio_codec: "copy".to_string(),
+                bitrate: None,
+                crf: None,
+                scale: None,
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: None,
+                extra_args: Vec::new(),
+            },
+            VideoPreset {
+                name: "HEVC".to_string(),
+                description: "H.265 at about half the size of Balanced. Plays on Apple devices and recent TVs.".to_string(),
+                video_codec: "libx265".to_string(),
+                audio_codec: "aac".to_string(),
+                bitrate: None,
+                crf: Some(28),
+                scale: None,
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: Some("mp4".to_string()),
+                extra_args: Vec::new(),
+            },
+            VideoPreset {
+                name: "HEVC High".to_string(),
+                description: "H.265 close to the source quality. Smaller than High, slower to encode.".to_string(),
+                video_codec: "libx265".to_string(),
+                audio_codec: "aac".to_string(),
+                bitrate: None,
+                crf: Some(22),
+                scale: None,
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: Some("mp4".to_string()),
+                extra_args: Vec::new(),
+            },
+            VideoPreset {
+                name: "WebM".to_string(),
+                description: "VP9 with Opus audio for the web. Small files that play in any browser, though not in QuickTime.".to_string(),
+                video_codec: "libvpx-vp9".to_string(),
+                audio_codec: "libopus".to_string(),
+                bitrate: None,
+                crf: Some(32),
+                scale: None,
+                audio_bitrate: Some("96k".to_string()),
+                audio_rate_control: Some(AudioRateControl::Variable),
+                audio_quality: None,
+                container: Some("webm".to_string()),
+                extra_args: Vec::new(),
+            },
+            VideoPreset {
+                name: "GIF".to_string(),
+                description: "Looping animated GIF for chats and docs. 480 px wide at 15 fps, no sound.".to_string(),
+                video_codec: "gif".to_string(),
+                audio_codec: "none".to_string(),
+                bitrate: None,
+                crf: None,
+                scale: Some("480:-1".to_string()),
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: Some("gif".to_string()),
+                extra_args: Vec::new(),
+            },
+            VideoPreset {
+                name: "Animated WebP".to_string(),
+                description: "Looping animation with full color at a fraction of the GIF size. 480 px wide, no sound.".to_string(),
+                video_codec: "libwebp_anim".to_string(),
+                audio_codec: "none".to_string(),
+                bitrate: None,
+                crf: None,
+                scale: Some("480:-1".to_string()),
+                audio_bitrate: None,
+                audio_rate_control: None,
+                audio_quality: None,
+                container: Some("webp".to_string()),
+                extra_args: Vec::new(),
             },
         ]
     }
 
-    pub fn to_ffmpeg_args(&self) -> Vec<String> {
+    pub fn container(&self) -> &str {
+        self.container.as_deref().unwrap_or("mp4")
+    }
+
+    /// Copies the streams into a new container instead of encoding them
+    pub fn is_remux(&self) -> bool {
+        self.video_codec == "copy"
+    }
+
+    /// Short name of the codec the preset writes, e.g. `h264` for libx264
+    pub fn codec_name(&self) -> &str {
+        match self.video_codec.as_str() {
+            "libx264" | "h264_videotoolbox" => "h264",
+            "libx265" | "hevc_videotoolbox" => "hevc",
+            "libvpx-vp9" => "vp9",
+            "libaom-av1" | "libsvtav1" => "av1",
+            "libwebp_anim" => "webp",
+            codec => codec,
+        }
+    }
+
+    /// Writes a silent looping animation (GIF or animated WebP)
+    pub fn is_animation(&self) -> bool {
+        matches!(self.video_codec.as_str(), "gif" | "libwebp_anim")
+    }
+
+    pub fn to_ffmpeg_args(&self, speed: EncoderSpeed) -> Vec<String> {
+        if self.is_remux() {
+            // Quality, bitrate and speed settings mean nothing without an encoder
+            return ["-c:v", "copy", "-c:a", &self.audio_codec].map(String::from).to_vec();
+        }
+
+        if self.is_animation() {
+            // Loop forever like people expect from a GIF. Colors are handled
+            // by the palette filters, and WebP gets a fixed lossy quality.
+            let mut args = vec!["-c:v".to_string(), self.video_codec.clone(), "-an".to_string()];
+            if self.video_codec == "libwebp_anim" {
+                args.extend(["-lossless", "0", "-q:v", "75"].map(String::from));
+            }
+            args.extend(["-loop", "0"].map(String::from));
+            return args;
+        }
+
         let mut args = vec![
             "-c:v".to_string(),
             self.video_codec.clone(),
@@ -104,50 +623,136 @@ impl VideoPreset {
             args.push(bitrate.clone());
         }
 
-        if let Some(scale) = &self.scale {
-            args.push("-vf".to_string());
-            args.push(format!("scale={}", scale));
+        let aac_vbr = self.audio_codec == "aac" && self.audio_rate_control == Some(AudioRateControl::Variable);
+        if aac_vbr {
+            if let Some(quality) = self.audio_quality {
+                args.push("-q:a".to_string());
+                args.push(format!("{:.1}", quality));
+            }
+        } else if let Some(audio_bitrate) = &self.audio_bitrate {
+            args.push("-b:a".to_string());
+            args.push(audio_bitrate.clone());
+        }
+
+        if self.audio_codec == "libopus" {
+            match self.audio_rate_control {
+                Some(AudioRateControl::Constant) => args.extend(["-vbr".to_string(), "off".to_string()]),
+                Some(AudioRateControl::Variable) => args.extend(["-vbr".to_string(), "on".to_string()]),
+                None => {}
+            }
         }
 
-        if self.name == "Web" {
-            args.push("-movflags".to_string());
-            args.push("+faststart".to_string());
+        // QuickTime and Apple devices only play HEVC in MP4/MOV tagged as hvc1,
+        // while FFmpeg writes hev1 by default
+        let hevc = matches!(self.video_codec.as_str(), "libx265" | "hevc_videotoolbox");
+        if hevc && matches!(self.container(), "mp4" | "mov" | "m4v") {
+            args.push("-tag:v".to_string());
+            args.push("hvc1".to_string());
         }
 
-        args.push("-preset".to_string());
-        args.push("medium".to_string());
+        // Hardware encoders have their own speed options, or none
+        if matches!(self.video_codec.as_str(), "libx264" | "libx265") {
+            args.push("-preset".to_string());
+            args.push(speed.as_str().to_string());
+        }
 
         args
     }
 }
 
-pub fn get_ffmpeg_binary(app_handle: &AppHandle) -> Result<PathBuf, String> {
-    let arch = if cfg!(target_arch = "x86_64") {
-        "x86_64"
-    } else if cfg!(target_arch = "aarch64") {
-        "aarch64"
-    } else {
-        return Err("Unsupported architecture".to_string());
-    };
+/// Name of the bundled FFmpeg for a platform, following Tauri's target
+/// triple naming. `os` and `arch` are `std::env::consts` values.
+fn ffmpeg_binary_name(os: &str, arch: &str) -> Option<String> {
+    if !matches!(arch, "x86_64" | "aarch64") {
+        return None;
+    }
+    match os {
+        "macos" => Some(format!("ffmpeg-{}-apple-darwin", arch)),
+        "windows" => Some(format!("ffmpeg-{}-pc-windows-msvc.exe", arch)),
+        "linux" => Some(format!("ffmpeg-{}-unknown-linux-gnu", arch)),
+        _ => None,
+    }
+}
 
-    let binary_name = format!("ffmpeg-{}-apple-darwin", arch);
-    
+/// Bundled binaries can lose their executable bit when copied or unpacked
+pub fn ensure_executable(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return;
+        };
+        let mut permissions = metadata.permissions();
+        if permissions.mode() & 0o111 != 0o111 {
+            permissions.set_mode(permissions.mode() | 0o755);
+            if let Err(e) = std::fs::set_permissions(path, permissions) {
+                eprintln!("Failed to make {} executable: {}", path.display(), e);
+            }
+        }
+    }
+    // Windows runs any .exe
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Where the FFmpeg in use comes from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FFmpegSource {
+    Bundled,
+    /// Picked by the user in settings
+    Override,
+    /// Fetched into app data because none was bundled
+    Downloaded,
+    /// Found on the PATH because no bundled or downloaded binary was there
+    System,
+}
+
+pub fn get_ffmpeg_binary(app_handle: &AppHandle) -> Result<PathBuf, Error> {
+    locate_ffmpeg(app_handle).map(|(path, _)| path)
+}
+
+static SYSTEM_FFMPEG_LOGGED: OnceLock<()> = OnceLock::new();
+
+/// Find FFmpeg: the override from settings, else the bundled binary, else a
+/// downloaded one, else one on the PATH. A missing override is an error rather than a fallback,
+/// so a typo doesn't silently convert with a different build.
+pub fn locate_ffmpeg(app_handle: &AppHandle) -> Result<(PathBuf, FFmpegSource), Error> {
+    let override_path = app_handle.state::<AppState>().ffmpeg_override.lock()
+        .ok()
+        .and_then(|path| path.clone());
+    if let Some(path) = override_path {
+        if !path.is_file() {
+            return Err(Error::FfmpegNotFound(path.to_string_lossy().to_string()));
+        }
+        return Ok((path, FFmpegSource::Override));
+    }
+
+    let binary_name = ffmpeg_binary_name(std::env::consts::OS, std::env::consts::ARCH);
+    if let Some(path) = binary_name.as_deref().and_then(|name| bundled_ffmpeg(app_handle, name)) {
+        ensure_executable(&path);
+        return Ok((path, FFmpegSource::Bundled));
+    }
+
+    if let Some(path) = crate::ffmpeg_download::downloaded_ffmpeg_path(app_handle).ok().filter(|path| path.is_file()) {
+        return Ok((path, FFmpegSource::Downloaded));
+    }
+
+    if let Some(path) = system_ffmpeg() {
+        // FFmpeg is located for every job, so only say this once
+        SYSTEM_FFMPEG_LOGGED.get_or_init(|| println!("No bundled FFmpeg, using {}", path.display()));
+        return Ok((path, FFmpegSource::System));
+    }
+
+    Err(Error::FfmpegNotFound(binary_name.unwrap_or_else(|| "ffmpeg".to_string())))
+}
+
+fn bundled_ffmpeg(app_handle: &AppHandle, binary_name: &str) -> Option<PathBuf> {
     // Try production path first
     if let Ok(resource_path) = app_handle.path().resource_dir() {
-        let ffmpeg_path = resource_path.join("binaries").join(&binary_name);
+        let ffmpeg_path = resource_path.join("binaries").join(binary_name);
         if ffmpeg_path.exists() {
-            // Ensure the binary is executable
-            #[cfg(unix)]
-            {
-                use std::fs;
-                use std::os::unix::fs::PermissionsExt;
-                if let Ok(metadata) = fs::metadata(&ffmpeg_path) {
-                    let mut permissions = metadata.permissions();
-                    permissions.set_mode(0o755);
-                    let _ = fs::set_permissions(&ffmpeg_path, permissions);
-                }
-            }
-            return Ok(ffmpeg_path);
+            return Some(ffmpeg_path);
         }
     }
     
@@ -156,60 +761,639 @@ pub fn get_ffmpeg_binary(app_handle: &AppHandle) -> Result<PathBuf, String> {
     {
         let dev_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("binaries")
-            .join(&binary_name);
+            .join(binary_name);
         if dev_path.exists() {
-            // Ensure the binary is executable in dev too
-            #[cfg(unix)]
-            {
-                use std::fs;
-                use std::os::unix::fs::PermissionsExt;
-                if let Ok(metadata) = fs::metadata(&dev_path) {
-                    let mut permissions = metadata.permissions();
-                    permissions.set_mode(0o755);
-                    let _ = fs::set_permissions(&dev_path, permissions);
-                }
-            }
-            return Ok(dev_path);
+            return Some(dev_path);
         }
     }
 
-    Err(format!(
-        "FFmpeg binary '{}' not found in resource directory or development path",
-        binary_name
-    ))
+    None
 }
 
-pub async fn get_video_duration(ffmpeg_path: &Path, input_path: &str) -> Result<f64, String> {
-    let output = Command::new(ffmpeg_path)
-        .args(&[
+/// `ffmpeg` on the PATH. Apps started from the Finder get a minimal PATH,
+/// so Homebrew's folders are searched on macOS too.
+fn system_ffmpeg() -> Option<PathBuf> {
+    let name = if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" };
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    if cfg!(target_os = "macos") {
+        dirs.extend(["/opt/homebrew/bin", "/usr/local/bin"].map(PathBuf::from));
+    }
+    dirs.into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// How far an output's duration may be off, in seconds or as a share of the
+/// expected duration whichever is larger, before verification flags it
+const VERIFY_DURATION_TOLERANCE_SECONDS: f64 = 1.0;
+const VERIFY_DURATION_TOLERANCE_RATIO: f64 = 0.02;
+
+/// Decode a finished output from start to end and compare its duration with
+/// `expected_duration`. Returns the problems found, empty when there are none.
+pub async fn verify_output(ffmpeg_path: &Path, output_path: &str, expected_duration: Option<f64>) -> Result<Vec<String>, Error> {
+    let output = analysis_command(ffmpeg_path)
+        .args(["-v", "error", "-i", output_path, "-f", "null", "-"])
+        .output()
+        .await
+        .map_err(Error::SpawnFailed)?;
+
+    let mut warnings = Vec::new();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors: Vec<&str> = stderr.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    if !output.status.success() || !errors.is_empty() {
+        warnings.push(format!(
+            "Decoding the output reported {} error(s): {}",
+            errors.len().max(1),
+            errors.first().copied().unwrap_or("unknown error")
+        ));
+    }
+
+    if let Some(expected) = expected_duration.filter(|duration| *duration > 0.0) {
+        let actual = probe_video(ffmpeg_path, output_path).await?.duration;
+        let tolerance = VERIFY_DURATION_TOLERANCE_SECONDS.max(expected * VERIFY_DURATION_TOLERANCE_RATIO);
+        if (actual - expected).abs() > tolerance {
+            warnings.push(format!("The output is {:.1}s long, expected {:.1}s", actual, expected));
+        }
+    }
+    Ok(warnings)
+}
+
+/// FFmpeg command for background analysis (probing, thumbnails, crop detection).
+/// It runs at background priority so it doesn't slow down the active encode;
+/// on macOS `taskpolicy -b` also throttles its disk I/O.
+pub fn analysis_command(ffmpeg_path: &Path) -> Command {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("taskpolicy");
+        command.arg("-b").arg(ffmpeg_path);
+        command
+    };
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let mut command = {
+        let mut command = Command::new("nice");
+        command.args(["-n", "10"]).arg(ffmpeg_path);
+        command
+    };
+    #[cfg(not(unix))]
+    let mut command = Command::new(ffmpeg_path);
+
+    command.kill_on_drop(true);
+    command
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoProbe {
+    pub duration: f64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub video_codec: Option<String>,
+    pub fps: Option<f64>,
+    pub rotation: Option<f64>,
+    pub bitrate_kbps: Option<u32>,
+    pub audio_channels: Option<u32>,
+    pub file_size: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+    pub timecode: Option<String>,
+}
+
+impl ConversionJob {
+    /// Fill in what analysis found out about the source
+    pub fn apply_probe(&mut self, probe: VideoProbe) {
+        self.duration = Some(probe.duration);
+        self.width = probe.width;
+        self.height = probe.height;
+        self.video_codec = probe.video_codec;
+        self.fps = probe.fps;
+        self.rotation = probe.rotation;
+        self.bitrate_kbps = probe.bitrate_kbps;
+        self.audio_channels = probe.audio_channels;
+        self.file_size = probe.file_size;
+        self.streams = probe.streams;
+        self.source_timecode = probe.timecode;
+    }
+}
+
+pub async fn probe_video(ffmpeg_path: &Path, input_path: &str) -> Result<VideoProbe, Error> {
+    let output = analysis_command(ffmpeg_path)
+        .args([
             "-i", input_path,
             "-hide_banner",
         ])
         .output()
         .await
-        .map_err(|e| format!("Failed to get video duration: {}", e))?;
+        .map_err(Error::SpawnFailed)?;
 
     let stderr = String::from_utf8_lossy(&output.stderr);
-    log_debug!("Getting duration for: {}", input_path);
-    
-    // Parse duration from FFmpeg stderr output
+    log_debug!("Getting media info for: {}", input_path);
+
+    let info = parse_media_info(&stderr)
+        .ok_or_else(|| Error::Parse(format!("Could not read media info: {}", stderr.lines().last().unwrap_or("unknown error"))))?;
+    let duration = info.duration.ok_or_else(|| Error::Parse("Could not parse video duration".to_string()))?;
+    log_debug!("Parsed duration: {} seconds", duration);
+
+    let video = info.video.into_iter().next();
+    Ok(VideoProbe {
+        duration,
+        width: video.as_ref().and_then(|video| video.width),
+        height: video.as_ref().and_then(|video| video.height),
+        video_codec: video.as_ref().map(|video| video.codec.clone()),
+        fps: video.as_ref().and_then(|video| video.fps),
+        rotation: video.and_then(|video| video.rotation),
+        bitrate_kbps: info.bitrate_kbps,
+        audio_channels: info.audio.first().and_then(|audio| audio.channels),
+        file_size: std::fs::metadata(input_path).ok().map(|metadata| metadata.len()),
+        streams: info.streams,
+        timecode: parse_timecode(&stderr),
+    })
+}
+
+
+/// Run FFmpeg's cropdetect over a short window of the video and return the
+/// detected crop when it actually removes black bars from the frame.
+pub async fn detect_crop(ffmpeg_path: &Path, input_path: &str, duration: f64) -> Result<Option<CropRect>, String> {
+    // Sample from the same point as the thumbnail to skip fade-ins and intros
+    let start = format!("{}", duration * 0.1);
+    let output = analysis_command(ffmpeg_path)
+        .args([
+            "-ss", &start,
+            "-i", input_path,
+            "-t", "10",
+            "-vf", "cropdetect=limit=24:round=2:reset=0",
+            "-an",
+            "-f", "null",
+            "-",
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run crop detection: {}", e))?;
+
+    if !output.status.success() {
+        return Err("Crop detection failed".to_string());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut resolution = None;
+    let mut crop = None;
     for line in stderr.lines() {
-        if let Some(duration) = parse_duration_from_info(line) {
-            log_debug!("Parsed duration: {} seconds from line: {}", duration, line);
-            return Ok(duration);
+        if resolution.is_none() {
+            resolution = parse_video_resolution(line);
+        }
+        // With reset=0 the last reported crop covers every sampled frame
+        if let Some((width, height, x, y)) = parse_cropdetect_line(line) {
+            crop = Some(CropRect { width, height, x, y });
         }
     }
-    
-    Err("Could not parse video duration".to_string())
+
+    log_debug!("Crop detection for {}: source {:?}, detected {:?}", input_path, resolution, crop);
+
+    match (crop, resolution) {
+        // Ignore implausible crops (e.g. a mostly black sample window)
+        (Some(crop), Some((width, height)))
+            if (crop.width < width || crop.height < height)
+                && crop.width * 2 >= width
+                && crop.height * 2 >= height =>
+        {
+            Ok(Some(crop))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Run an FFmpeg step that comes before the encode, like silence detection,
+/// and collect its output. It's killed as soon as `cancel` flips, and tracked
+/// like the encode so quitting kills it too.
+async fn run_conversion_step(app_handle: &AppHandle, mut command: Command, cancel: &mut watch::Receiver<bool>) -> Result<std::process::Output, Error> {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(Error::SpawnFailed)?;
+    let _tracked = child.id().map(|pid| app_handle.state::<AppState>().track_ffmpeg_process(pid));
+
+    // Dropping the child on cancel kills it, as `analysis_command` sets kill_on_drop
+    tokio::select! {
+        output = child.wait_with_output() => output.map_err(|e| Error::io("Failed to run FFmpeg", e)),
+        Ok(_) = cancel.wait_for(|cancelled| *cancelled) => Err(Error::Cancelled),
+    }
+}
+
+/// Run silencedetect over the audio track and return the range between the
+/// leading and trailing silence, if there is any to cut.
+pub async fn detect_silence(app_handle: &AppHandle, ffmpeg_path: &Path, input_path: &str, duration: f64, cancel: &mut watch::Receiver<bool>) -> Result<Option<TimeRange>, Error> {
+    let mut command = analysis_command(ffmpeg_path);
+    command.args([
+        "-i", input_path,
+        "-vn",
+        "-af", "silencedetect=noise=-50dB:d=1",
+        "-f", "null",
+        "-",
+    ]);
+    let output = run_conversion_step(app_handle, command, cancel).await?;
+
+    if !output.status.success() {
+        return Err(Error::Other("Silence detection failed (the file may have no audio)".to_string()));
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut silences: Vec<(f64, Option<f64>)> = Vec::new();
+    for line in stderr.lines() {
+        if let Some(start) = parse_silence_start(line) {
+            silences.push((start.max(0.0), None));
+        } else if let Some(end) = parse_silence_end(line) {
+            if let Some(last) = silences.last_mut() {
+                last.1 = Some(end);
+            }
+        }
+    }
+
+    log_debug!("Silence detection for {}: {:?}", input_path, silences);
+
+    Ok(audible_range(&silences, duration).map(|(start, end)| TimeRange { start, end }))
+}
+
+/// Lowercase extension of an output path, used to pick container-specific args.
+pub fn output_container(output_path: &str) -> String {
+    Path::new(output_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn has_stream_kind(job: &ConversionJob, kind: StreamKind) -> bool {
+    job.streams.iter().any(|stream| stream.kind == kind)
+}
+
+/// Whether every stream gets mapped explicitly. Matroska sources with
+/// attached fonts/covers always are when the output is Matroska too, since
+/// styled subtitles render wrong without their fonts.
+fn maps_all_streams(job: &ConversionJob, container: &str) -> bool {
+    job.options.keep_all_streams
+        || (container == "mkv" && has_stream_kind(job, StreamKind::Attachment))
+}
+
+/// Stream selection for the "keep all streams" mode. `0:V` skips embedded
+/// cover images, which would otherwise be re-encoded as a video track.
+fn stream_mapping_args(job: &ConversionJob, container: &str) -> Vec<String> {
+    let keep_data = job.options.keep_data_streams && has_stream_kind(job, StreamKind::Data);
+    let maps_all = maps_all_streams(job, container);
+    if !maps_all && !keep_data {
+        return Vec::new();
+    }
+
+    let args: &[&str] = match container {
+        // Matroska can hold every subtitle format and attachment, so copy them as-is
+        "mkv" => &["-map", "0", "-map", "-0:d", "-c:s", "copy", "-c:t", "copy"],
+        // MP4/MOV/WebM only take specific subtitle codecs; keep all audio
+        // tracks and leave subtitles out rather than failing the encode
+        _ if maps_all => &["-map", "0:V", "-map", "0:a?"],
+        // Mapping data streams turns off FFmpeg's automatic selection, so
+        // pick the main video and audio explicitly
+        _ => &["-map", "0:V", "-map", "0:a:0?"],
+    };
+    let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+    if keep_data {
+        args.extend(["-map", "0:d", "-c:d", "copy", "-copy_unknown"].map(String::from));
+
+        // The MOV muxer needs the GoPro metadata tag to write telemetry tracks
+        let data_streams = job.streams.iter().filter(|stream| stream.kind == StreamKind::Data);
+        for (n, stream) in data_streams.enumerate() {
+            if stream.details.contains("gpmd") {
+                args.push(format!("-tag:d:{}", n));
+                args.push("gpmd".to_string());
+            }
+        }
+    }
+
+    args
+}
+
+/// Warnings about source streams the chosen output can't carry over.
+pub fn stream_warnings(job: &ConversionJob) -> Vec<String> {
+    let container = output_container(&job.output_path);
+    let mut warnings = Vec::new();
+
+    if container != "mkv" && has_stream_kind(job, StreamKind::Attachment) {
+        warnings.push(format!(
+            "Attached fonts and covers can't be stored in {} and will be dropped",
+            container.to_uppercase()
+        ));
+    }
+
+    if !job.options.keep_data_streams && has_stream_kind(job, StreamKind::Data) {
+        warnings.push(
+            "Data streams (e.g. GPS/telemetry) will be dropped unless you keep them with a MOV output".to_string()
+        );
+    }
+
+    if job.options.audio_passthrough {
+        let unsupported: Vec<&str> = job.streams.iter()
+            .filter(|stream| stream.kind == StreamKind::Audio)
+            .filter(|stream| SURROUND_CODECS.contains(&stream.codec.as_str()))
+            .filter(|stream| !passthrough_supported(&stream.codec, &container))
+            .map(|stream| stream.codec.as_str())
+            .collect();
+        if !unsupported.is_empty() {
+            warnings.push(format!(
+                "{} audio can't be copied into {} and will be converted instead",
+                unsupported.join(", ").to_uppercase(),
+                container.to_uppercase()
+            ));
+        }
+    }
+
+    warnings
+}
+
+const SURROUND_CODECS: &[&str] = &["ac3", "eac3", "dts", "truehd"];
+
+/// Whether a surround codec can be copied into the given container as-is.
+fn passthrough_supported(codec: &str, container: &str) -> bool {
+    match container {
+        "mkv" => SURROUND_CODECS.contains(&codec),
+        "mp4" | "mov" | "m4v" => matches!(codec, "ac3" | "eac3"),
+        _ => false,
+    }
+}
+
+/// Per-output-track codec overrides for surround passthrough. When FFmpeg
+/// picks the audio track itself we can't know which one it takes, so
+/// passthrough only applies if every source track can be copied.
+fn audio_passthrough_args(job: &ConversionJob, container: &str) -> Vec<String> {
+    if !job.options.audio_passthrough {
+        return Vec::new();
+    }
+
+    let audio: Vec<&StreamInfo> = job.streams.iter()
+        .filter(|stream| stream.kind == StreamKind::Audio)
+        .collect();
+
+    if maps_all_streams(job, container) {
+        audio.iter()
+            .enumerate()
+            .filter(|(_, stream)| passthrough_supported(&stream.codec, container))
+            .flat_map(|(n, _)| [format!("-c:a:{}", n), "copy".to_string()])
+            .collect()
+    } else if !audio.is_empty() && audio.iter().all(|stream| passthrough_supported(&stream.codec, container)) {
+        vec!["-c:a".to_string(), "copy".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Carry language tags and track titles over to every mapped audio (and for
+/// Matroska, subtitle) track so players keep usable track pickers.
+fn stream_metadata_args(job: &ConversionJob, container: &str) -> Vec<String> {
+    if !maps_all_streams(job, container) {
+        return Vec::new();
+    }
+
+    let mut kinds = vec![(StreamKind::Audio, "a")];
+    if container == "mkv" {
+        kinds.push((StreamKind::Subtitle, "s"));
+    }
+
+    let mut args = vec!["-map_metadata".to_string(), "0".to_string()];
+    for (kind, specifier) in kinds {
+        let streams = job.streams.iter().filter(|stream| stream.kind == kind);
+        for (n, stream) in streams.enumerate() {
+            if let Some(language) = stream.language.as_deref().filter(|lang| *lang != "und") {
+                args.push(format!("-metadata:s:{}:{}", specifier, n));
+                args.push(format!("language={}", language));
+            }
+            if let Some(title) = &stream.title {
+                args.push(format!("-metadata:s:{}:{}", specifier, n));
+                args.push(format!("title={}", title));
+            }
+        }
+    }
+
+    args
+}
+
+/// Find the last keyframe at or before `time`. Only keyframes are decoded,
+/// and only up to just past the requested time.
+pub async fn find_keyframe_before(app_handle: &AppHandle, ffmpeg_path: &Path, input_path: &str, time: f64, cancel: &mut watch::Receiver<bool>) -> Result<f64, Error> {
+    let limit = format!("{:.3}", time + 1.0);
+    let mut command = analysis_command(ffmpeg_path);
+    command.args([
+        "-skip_frame", "nokey",
+        "-i", input_path,
+        "-t", &limit,
+        "-an",
+        "-vf", "showinfo",
+        "-f", "null",
+        "-",
+    ]);
+    let output = run_conversion_step(app_handle, command, cancel).await?;
+
+    if !output.status.success() {
+        return Err(Error::Other("Keyframe probing failed".to_string()));
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let keyframe = stderr
+        .lines()
+        .filter_map(parse_showinfo_pts_time)
+        .filter(|pts| *pts <= time)
+        .fold(0.0, f64::max);
+
+    log_debug!("Nearest keyframe before {}s in {}: {}s", time, input_path, keyframe);
+    Ok(keyframe)
+}
+
+/// Work out which part of the source to convert: the requested trim,
+/// narrowed by silence detection and, for fast cuts, moved back to the
+/// previous keyframe so stream copy starts on a decodable frame.
+async fn resolve_trim(app_handle: &AppHandle, ffmpeg_path: &Path, job: &ConversionJob, cancel: &mut watch::Receiver<bool>) -> Result<Option<TimeRange>, Error> {
+    let Some(duration) = job.duration else {
+        // Without a known duration only an explicit start/end can be honored
+        return Ok(match (job.options.trim_start, job.options.trim_end) {
+            (None, None) => None,
+            (start, end) => Some(TimeRange {
+                start: start.unwrap_or(0.0),
+                end: end.unwrap_or(f64::INFINITY),
+            }),
+        });
+    };
+
+    let mut range = TimeRange {
+        start: job.options.trim_start.unwrap_or(0.0),
+        end: job.options.trim_end.unwrap_or(duration).min(duration),
+    };
+
+    if job.options.trim_silence {
+        match detect_silence(app_handle, ffmpeg_path, &job.input_path, duration, cancel).await {
+            Ok(Some(audible)) => {
+                range.start = range.start.max(audible.start);
+                range.end = range.end.min(audible.end);
+            }
+            Ok(None) => {}
+            Err(Error::Cancelled) => return Err(Error::Cancelled),
+            Err(e) => log_debug!("Skipping silence trim for job {}: {}", job.id, e),
+        }
+    }
+
+    if job.options.fast_cut && range.start > 0.0 {
+        range.start = find_keyframe_before(app_handle, ffmpeg_path, &job.input_path, range.start, cancel).await?;
+    }
+
+    if range.start >= range.end {
+        return Err(Error::Other("Nothing left to convert after trimming".to_string()));
+    }
+
+    if range.start <= 0.0 && range.end >= duration {
+        Ok(None)
+    } else {
+        Ok(Some(range))
+    }
 }
 
+/// Build the full FFmpeg argument list for a job, including the filtergraph.
+pub fn build_ffmpeg_args(job: &ConversionJob, settings: &AppSettings, output_path: &str) -> Vec<String> {
+    let mut args = Vec::new();
+
+    // Input seeking keeps both streams in sync when trimming
+    if let Some(range) = &job.resolved_trim {
+        args.push("-ss".to_string());
+        args.push(format!("{:.3}", range.start));
+    }
+
+    args.extend([
+        "-i".to_string(),
+        job.input_path.clone(),
+        "-progress".to_string(),
+        "pipe:2".to_string(),
+        "-stats".to_string(),
+        "-y".to_string(),
+    ]);
+
+    if let Some(range) = job.resolved_trim.filter(|range| range.end.is_finite()) {
+        args.push("-t".to_string());
+        args.push(format!("{:.3}", range.duration()));
+    }
+
+    let container = output_container(output_path);
+
+    // Moving the index to the front lets MP4/MOV play while still downloading
+    if settings.faststart && matches!(container.as_str(), "mp4" | "mov" | "m4v") {
+        args.push("-movflags".to_string());
+        args.push("+faststart".to_string());
+    }
+
+    // Keep proxies lined up with their originals in editing software. The
+    // source timecode moves along with a trimmed start; when that can't be
+    // worked out (drop-frame, unknown frame rate) it's left off, not written wrong.
+    let source_timecode = match job.resolved_trim.filter(|range| range.start > 0.0) {
+        Some(range) => job.source_timecode.as_deref()
+            .zip(job.fps)
+            .and_then(|(timecode, fps)| shift_timecode(timecode, range.start, fps)),
+        None => job.source_timecode.clone(),
+    };
+    if let Some(timecode) = job.options.start_timecode.clone().or(source_timecode) {
+        if matches!(container.as_str(), "mov" | "mp4" | "m4v" | "mxf") {
+            args.push("-timecode".to_string());
+            args.push(timecode);
+        }
+    }
+
+    if job.options.fast_cut {
+        // Stream copy skips the preset and filters entirely
+        args.extend(["-map", "0", "-c", "copy", "-avoid_negative_ts", "make_zero"].map(String::from));
+        push_output(&mut args, job, output_path);
+        return args;
+    }
+
+    args.extend(stream_mapping_args(job, &container));
+    args.extend(stream_metadata_args(job, &container));
+    match target_video_kbps(job) {
+        Some(kbps) => {
+            // The bitrate that fits the target size replaces the preset's quality
+            let preset = VideoPreset {
+                crf: None,
+                bitrate: Some(format!("{}k", kbps.floor())),
+                ..job.preset.clone()
+            };
+            args.extend(preset.to_ffmpeg_args(settings.encoder_speed));
+        }
+        None => args.extend(job.preset.to_ffmpeg_args(settings.encoder_speed)),
+    }
+
+    if job.preset.is_remux() {
+        // Copied HEVC needs the hvc1 tag just like encoded HEVC to play on Apple devices
+        if job.video_codec.as_deref() == Some("hevc") && matches!(container.as_str(), "mp4" | "mov" | "m4v") {
+            args.extend(["-tag:v", "hvc1"].map(String::from));
+        }
+        if job.resolved_trim.is_some() {
+            args.extend(["-avoid_negative_ts", "make_zero"].map(String::from));
+        }
+        if job.preset.audio_codec != "copy" {
+            if let Some(filters) = build_audio_filters(job) {
+                args.push("-af".to_string());
+                args.push(filters);
+            }
+        }
+        push_output(&mut args, job, output_path);
+        return args;
+    }
+
+    args.extend(audio_passthrough_args(job, &container));
+
+    if let Some(filters) = build_video_filters(job, settings) {
+        args.push("-vf".to_string());
+        args.push(filters);
+    }
+
+    if let Some(filters) = build_audio_filters(job).filter(|_| !job.preset.is_animation()) {
+        args.push("-af".to_string());
+        args.push(filters);
+    }
+
+    push_output(&mut args, job, output_path);
+    args
+}
+
+/// End the arguments with the user's extra options and the output path
+fn push_output(args: &mut Vec<String>, job: &ConversionJob, output_path: &str) {
+    args.extend(job.preset.extra_args.iter().cloned());
+    args.extend(job.options.extra_args.iter().cloned());
+    args.push(output_path.to_string());
+}
+
+/// The arguments `convert_video` would run for a job, minus the progress
+/// reporting that only the app parses. For copying into a terminal or script.
+pub async fn standalone_ffmpeg_args(app_handle: &AppHandle, ffmpeg_path: &Path, job: &ConversionJob, settings: &AppSettings) -> Result<Vec<String>, String> {
+    validate_job_options(job)?;
+    if job.options.target_size_mb.is_some() {
+        return Err("Target size jobs run FFmpeg twice and can't be written as one command".to_string());
+    }
+    let mut job = job.clone();
+    // Nothing cancels building a command, so the sender can go right away
+    let mut cancel = watch::channel(false).1;
+    job.resolved_trim = resolve_trim(app_handle, ffmpeg_path, &job, &mut cancel).await?;
+    let output_path = job.output_path.replace('\u{00A0}', " ");
+
+    let mut args = build_ffmpeg_args(&job, settings, &output_path);
+    if let Some(index) = args.iter().position(|arg| arg == "-progress") {
+        args.drain(index..index + 2);
+    }
+    Ok(args)
+}
 
 pub async fn convert_video(
     app_handle: AppHandle,
-    job: ConversionJob,
-    on_progress: impl Fn(String, f32) + Send + 'static,
-) -> Result<(), String> {
+    mut job: ConversionJob,
+    settings: AppSettings,
+    on_progress: impl Fn(String, ProgressUpdate) + Send + 'static,
+    on_usage: impl Fn(String, ResourceUsage) + Send + 'static,
+    mut cancel: watch::Receiver<bool>,
+    log: &mut VecDeque<String>,
+) -> Result<EncodeStats, Error> {
     let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+    validate_job_options(&job)?;
     
     // Only normalize output path - input should be used as-is
     let normalized_output = job.output_path.replace('\u{00A0}', " ");
@@ -217,30 +1401,96 @@ pub async fn convert_video(
     // Ensure output directory exists
     if let Some(parent) = Path::new(&normalized_output).parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+            .map_err(|e| Error::io("Failed to create output directory", e))?;
     }
     
-    let mut args = vec![
-        "-i".to_string(),
-        job.input_path.clone(),
-        "-progress".to_string(),
-        "pipe:2".to_string(),
-        "-stats".to_string(),
-        "-y".to_string(),
-    ];
+    job.resolved_trim = resolve_trim(&app_handle, &ffmpeg_path, &job, &mut cancel).await?;
 
-    args.extend(job.preset.to_ffmpeg_args());
-    args.push(normalized_output.clone());
+    // Jobs are validated before probing, when the length may not be known.
+    // Without it the bitrate can't be worked out and the encode would quietly
+    // fall back to the preset's quality settings.
+    if let (Some(target_mb), None) = (job.options.target_size_mb, target_video_kbps(&job)) {
+        return Err(Error::Other(match output_duration(&job) {
+            Some(_) => format!("{} MB is too small for this clip's length", target_mb),
+            None => "Target size needs the clip's length, which couldn't be read".to_string(),
+        }));
+    }
     
+    // FFmpeg writes to a temp file next to the output, so a failed or killed
+    // encode never leaves a broken file under the final name
+    let partial_output = partial_output_path(&normalized_output);
+    let args = build_ffmpeg_args(&job, &settings, &partial_output);
+
+    let duration = match job.resolved_trim.filter(|range| range.end.is_finite()) {
+        Some(range) => range.duration(),
+        None => job.duration.unwrap_or(0.0),
+    } * output_duration_factor(&job);
+    log_debug!("Starting conversion for job {} with duration: {} seconds", job.id, duration);
+
+    let started_at = std::time::Instant::now();
+    let result = if job.options.target_size_mb.is_some() {
+        // Both passes share a rate-control log in the work dir
+        let passlog = crate::work_dir::work_dir(&app_handle, &settings)?.join(&job.id);
+        let mut result = Ok(());
+        for pass in 1..=2 {
+            let pass_args = two_pass_args(&args, pass, &passlog);
+            let report = |job_id: String, update: ProgressUpdate| on_progress(job_id, update.within_pass(pass, 2));
+            result = run_ffmpeg(&app_handle, &ffmpeg_path, &pass_args, &job, duration, &report, &on_usage, &mut cancel, log).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        remove_pass_logs(&passlog);
+        result
+    } else {
+        run_ffmpeg(&app_handle, &ffmpeg_path, &args, &job, duration, &on_progress, &on_usage, &mut cancel, log).await
+    };
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial_output);
+        return Err(e);
+    }
+    std::fs::rename(&partial_output, &normalized_output)
+        .map_err(|e| Error::io("Failed to move the finished output into place", e))?;
+
+    let elapsed_seconds = started_at.elapsed().as_secs_f64();
+    let speed = (duration > 0.0 && elapsed_seconds > 0.0).then(|| duration / elapsed_seconds);
+    log_debug!("Encoded job {} in {:.1}s ({:?}x realtime)", job.id, elapsed_seconds, speed);
+
+    Ok(EncodeStats { elapsed_seconds, speed })
+}
+
+/// Run one FFmpeg process to completion, reporting progress and resource use
+/// along the way. FFmpeg's other output is appended to `stderr_tail`.
+#[allow(clippy::too_many_arguments)]
+async fn run_ffmpeg(
+    app_handle: &AppHandle,
+    ffmpeg_path: &Path,
+    args: &[String],
+    job: &ConversionJob,
+    duration: f64,
+    on_progress: &impl Fn(String, ProgressUpdate),
+    on_usage: &impl Fn(String, ResourceUsage),
+    cancel: &mut watch::Receiver<bool>,
+    stderr_tail: &mut VecDeque<String>,
+) -> Result<(), Error> {
     // Log the full FFmpeg command for debugging
     log_debug!("FFmpeg command: {} {}", ffmpeg_path.display(), args.join(" "));
 
-    let mut child = Command::new(&ffmpeg_path)
-        .args(&args)
+    let started_at = std::time::Instant::now();
+    let mut child = Command::new(ffmpeg_path)
+        .args(args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        // Don't leave FFmpeg running if the conversion task is aborted
+        .kill_on_drop(true)
         .spawn()
-        .map_err(|e| format!("Failed to start FFmpeg: {}", e))?;
+        .map_err(Error::SpawnFailed)?;
+
+    let pid = child.id();
+    let tracked = pid.map(|pid| app_handle.state::<AppState>().track_ffmpeg_process(pid));
+    let hardware_encoder = job.preset.video_codec.ends_with("_videotoolbox");
+    let mut usage_timer = tokio::time::interval(RESOURCE_SAMPLE_INTERVAL);
 
     let stdout = child.stdout.take().unwrap();
     let stderr = child.stderr.take().unwrap();
@@ -252,25 +1502,30 @@ pub async fn convert_video(
     let mut stderr_lines = stderr_reader.lines();
     let mut last_error_line = String::new();
 
-    let duration = job.duration.unwrap_or(0.0);
-    log_debug!("Starting conversion for job {} with duration: {} seconds", job.id, duration);
-
     // Read from both stdout and stderr using tokio::select!
     loop {
         tokio::select! {
+            _ = usage_timer.tick() => {
+                if let Some(pid) = pid {
+                    if let Some(usage) = sample_process_usage(pid, hardware_encoder).await {
+                        on_usage(job.id.clone(), usage);
+                    }
+                }
+            }
+            _ = cancel.changed() => {
+                if *cancel.borrow() {
+                    let _ = child.kill().await;
+                    save_stderr_tail(app_handle, &job.id, stderr_tail);
+                    return Err(Error::Cancelled);
+                }
+            }
             result = stdout_lines.next_line() => {
                 match result {
                     Ok(Some(line)) => {
                         // Try to parse progress from stdout
                         if let Some(progress_info) = parse_progress_line(&line) {
-                            let current_time = progress_info.time_seconds;
-                            let progress = if duration > 0.0 {
-                                (current_time / duration * 100.0).min(100.0)
-                            } else {
-                                0.0
-                            };
-                            
-                            on_progress(job.id.clone(), progress as f32);
+                            let elapsed = started_at.elapsed().as_secs_f64();
+                            on_progress(job.id.clone(), ProgressUpdate::new(progress_info.time_seconds, duration, elapsed, Some(&progress_info)));
                         }
                     }
                     Ok(None) => break,
@@ -288,23 +1543,17 @@ pub async fn convert_video(
                         
                         // Try to parse progress from the line
                         if let Some(progress_info) = parse_progress_line(&line) {
-                            let current_time = progress_info.time_seconds;
-                            let progress = if duration > 0.0 {
-                                (current_time / duration * 100.0).min(100.0)
-                            } else {
-                                0.0
-                            };
-                            
-                            on_progress(job.id.clone(), progress as f32);
+                            let elapsed = started_at.elapsed().as_secs_f64();
+                            on_progress(job.id.clone(), ProgressUpdate::new(progress_info.time_seconds, duration, elapsed, Some(&progress_info)));
                         } else if let Some(current_time) = parse_progress_time(&line) {
                             // Parse -progress format
-                            let progress = if duration > 0.0 {
-                                (current_time / duration * 100.0).min(100.0)
-                            } else {
-                                0.0
-                            };
-                            
-                            on_progress(job.id.clone(), progress as f32);
+                            let elapsed = started_at.elapsed().as_secs_f64();
+                            on_progress(job.id.clone(), ProgressUpdate::new(current_time, duration, elapsed, None));
+                        } else if !is_progress_key_value(&line) {
+                            if stderr_tail.len() == STDERR_TAIL_LINES {
+                                stderr_tail.pop_front();
+                            }
+                            stderr_tail.push_back(line);
                         }
                     }
                     Ok(None) => break,
@@ -314,21 +1563,87 @@ pub async fn convert_video(
         }
     }
 
-    let status = child.wait().await
-        .map_err(|e| format!("Failed to wait for FFmpeg: {}", e))?;
+    let status = child.wait().await;
+    // The process is reaped, so its pid may be reused: stop tracking it
+    // before anything else can run
+    drop(tracked);
+    let status = status.map_err(|e| Error::io("Failed to wait for FFmpeg", e))?;
+    save_stderr_tail(app_handle, &job.id, stderr_tail);
 
     if !status.success() {
-        let error_msg = if !last_error_line.is_empty() {
-            format!("FFmpeg conversion failed: {}", last_error_line)
-        } else {
-            "FFmpeg conversion failed with unknown error".to_string()
-        };
-        return Err(error_msg);
+        let stderr = (!last_error_line.is_empty()).then_some(last_error_line);
+        return Err(Error::EncodeFailed { stderr });
     }
 
     Ok(())
 }
 
+/// Where an output is written while it's being encoded: `clip.mp4` becomes
+/// `clip.tmp.mp4`, keeping the extension FFmpeg picks the muxer from.
+pub fn partial_output_path(output_path: &str) -> String {
+    let path = Path::new(output_path);
+    let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+        return format!("{}.tmp", output_path);
+    };
+    let file_name = format!("{}.tmp.{}", stem.to_string_lossy(), extension.to_string_lossy());
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+/// Turn the arguments of a bitrate encode into one pass of a two-pass encode.
+/// The first pass only writes the rate-control log, so its output goes to the
+/// null muxer and the options meant for the real container are dropped.
+fn two_pass_args(args: &[String], pass: u8, passlog: &Path) -> Vec<String> {
+    let Some((output_path, options)) = args.split_last() else {
+        return Vec::new();
+    };
+
+    let mut pass_args = Vec::with_capacity(args.len() + 6);
+    let mut options = options.iter();
+    while let Some(arg) = options.next() {
+        if pass == 1 && matches!(arg.as_str(), "-movflags" | "-timecode") {
+            options.next();
+            continue;
+        }
+        pass_args.push(arg.clone());
+    }
+
+    pass_args.extend([
+        "-pass".to_string(),
+        pass.to_string(),
+        "-passlogfile".to_string(),
+        passlog.to_string_lossy().to_string(),
+    ]);
+    if pass == 1 {
+        pass_args.extend(["-f", "null", "-"].map(String::from));
+    } else {
+        pass_args.push(output_path.clone());
+    }
+    pass_args
+}
+
+/// x264 writes `<passlog>-0.log` plus a `.mbtree` file next to it
+fn remove_pass_logs(passlog: &Path) {
+    let (Some(dir), Some(prefix)) = (passlog.parent(), passlog.file_name()) else {
+        return;
+    };
+    let prefix = prefix.to_string_lossy();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(prefix.as_ref()) {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                eprintln!("Failed to remove pass log {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+
+/// `-progress` output such as `out_time_ms=1200000`, which isn't worth keeping
+fn is_progress_key_value(line: &str) -> bool {
+    line.split_once('=').is_some_and(|(key, _)| !key.is_empty() && !key.contains(' '))
+}
 
 pub async fn generate_thumbnail(
     ffmpeg_path: &Path,
@@ -341,7 +1656,7 @@ pub async fn generate_thumbnail(
         ffmpeg_path, time_offset, input_path, output_path);
     
     // Put -ss before -i for much faster seeking (input seeking vs output seeking)
-    let output = Command::new(ffmpeg_path)
+    let output = analysis_command(ffmpeg_path)
         .args(&[
             "-ss", time_offset,
             "-i", input_path,
@@ -362,4 +1677,128 @@ pub async fn generate_thumbnail(
 
     println!("Thumbnail generated successfully at: {}", output_path);
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Render the audio track as a single waveform PNG. All channels are mixed
+/// down so stereo files don't get two stacked waveforms.
+pub async fn generate_waveform(
+    ffmpeg_path: &Path,
+    input_path: &str,
+    output_path: &str,
+) -> Result<(), String> {
+    let output = analysis_command(ffmpeg_path)
+        .args([
+            "-i", input_path,
+            "-filter_complex", "aformat=channel_layouts=mono,showwavespic=s=1200x160:colors=0x3b82f6",
+            "-frames:v", "1",
+            "-y",
+            output_path,
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to generate waveform: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("matches no streams") || stderr.contains("Output file #0 does not contain any stream") {
+            return Err("File has no audio track".to_string());
+        }
+        return Err(format!("Failed to generate waveform: {}", stderr));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameFormat {
+    Png,
+    #[serde(alias = "jpg")]
+    Jpeg,
+}
+
+/// Export a single full-resolution frame. Input seeking decodes from the
+/// previous keyframe, so the captured frame is the one at `timestamp`.
+pub async fn capture_frame(
+    ffmpeg_path: &Path,
+    input_path: &str,
+    timestamp: f64,
+    output_path: &str,
+    format: FrameFormat,
+) -> Result<(), String> {
+    if !timestamp.is_finite() || timestamp < 0.0 {
+        return Err("Timestamp must be a positive number of seconds".to_string());
+    }
+
+    let codec_args: &[&str] = match format {
+        FrameFormat::Png => &["-c:v", "png"],
+        FrameFormat::Jpeg => &["-c:v", "mjpeg", "-q:v", "2"],
+    };
+
+    let timestamp = format!("{:.3}", timestamp);
+    let output = Command::new(ffmpeg_path)
+        .args(["-ss", &timestamp, "-i", input_path, "-frames:v", "1"])
+        .args(codec_args)
+        .args(["-f", "image2", "-update", "1", "-y", output_path])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to capture frame: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to capture frame: {}", stderr.lines().last().unwrap_or("unknown error")));
+    }
+
+    if !Path::new(output_path).exists() {
+        return Err(format!("No frame found at {}s", timestamp));
+    }
+
+    Ok(())
+}
+
+/// Rewrite the display rotation of a video without re-encoding. `rotation`
+/// is the clockwise rotation the video should be shown with.
+pub async fn set_rotation_metadata(
+    ffmpeg_path: &Path,
+    input_path: &str,
+    output_path: &str,
+    rotation: u32,
+) -> Result<(), String> {
+    if !matches!(rotation, 0 | 90 | 180 | 270) {
+        return Err("Rotation must be 0, 90, 180 or 270 degrees".to_string());
+    }
+
+    let container = output_container(output_path);
+    if !matches!(container.as_str(), "mp4" | "mov" | "m4v") {
+        return Err("Rotation metadata can only be written to MP4 or MOV files".to_string());
+    }
+
+    if Path::new(input_path) == Path::new(output_path) {
+        return Err("Choose a different output file than the original".to_string());
+    }
+
+    // FFmpeg expects the counter-clockwise angle; it replaces any existing
+    // display matrix on the first video stream
+    let display_rotation = ((360 - rotation) % 360).to_string();
+    let output = Command::new(ffmpeg_path)
+        .args([
+            "-display_rotation:v:0", &display_rotation,
+            "-i", input_path,
+            "-map", "0",
+            "-c", "copy",
+            "-y",
+            output_path,
+        ])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to fix rotation: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to fix rotation: {}", stderr.lines().last().unwrap_or("unknown error")));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::process::Command;
+use crate::ffmpeg::ensure_executable;
+use crate::ffmpeg_version::{is_sha256, pinned_download, sha256_file, verify_ffmpeg_binary, FFmpegDiagnostic};
+use crate::state::AppState;
+
+/// Sent while the archive downloads
+pub const FFMPEG_DOWNLOAD_PROGRESS: &str = "ffmpeg-download-progress";
+/// Sent with the check of the installed binary once the download is done
+pub const FFMPEG_DOWNLOADED: &str = "ffmpeg-downloaded";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub version: String,
+    pub downloaded_bytes: u64,
+    /// Unknown when the server doesn't send a length
+    pub total_bytes: Option<u64>,
+}
+
+/// Where a downloaded FFmpeg is installed, in the app data folder
+pub fn downloaded_ffmpeg_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let name = if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" };
+    app_handle.path().app_data_dir()
+        .map(|dir| dir.join("ffmpeg").join(name))
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+/// Download the FFmpeg build pinned in this app's manifest, check it against
+/// the pinned SHA-256, install it into app data and check that it runs.
+pub async fn download_ffmpeg(app_handle: &AppHandle, state: &AppState) -> Result<FFmpegDiagnostic, String> {
+    let Ok(_downloading) = state.ffmpeg_download.try_lock() else {
+        return Err("FFmpeg is already being downloaded".to_string());
+    };
+
+    let (version, download) = pinned_download()?;
+    let expected_hash = download.sha256
+        .filter(|hash| is_sha256(hash))
+        .ok_or_else(|| format!("FFmpeg {} has no checksum to verify the download with", version))?;
+    let target = downloaded_ffmpeg_path(app_handle)?;
+    let dir = target.parent().ok_or("Invalid FFmpeg install path")?.to_path_buf();
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    println!("Downloading FFmpeg {} from {}", version, download.url);
+    let archive = dir.join(format!("ffmpeg-{}.zip", version));
+    let result = install(app_handle, &version, &download.url, &expected_hash, &archive, &target).await;
+    let _ = fs::remove_file(&archive);
+    result?;
+
+    let diagnostic = verify_ffmpeg_binary(app_handle).await;
+    *state.ffmpeg_diagnostic.lock().await = Some(diagnostic.clone());
+    let _ = app_handle.emit(FFMPEG_DOWNLOADED, &diagnostic);
+    Ok(diagnostic)
+}
+
+async fn install(app_handle: &AppHandle, version: &str, url: &str, expected_hash: &str, archive: &Path, target: &Path) -> Result<(), String> {
+    fetch(app_handle, version, url, archive).await?;
+
+    let hash_path = archive.to_path_buf();
+    let hash = tokio::task::spawn_blocking(move || sha256_file(&hash_path)).await
+        .map_err(|e| e.to_string())??;
+    if !hash.eq_ignore_ascii_case(expected_hash) {
+        return Err(format!("The FFmpeg download doesn't match its checksum (got {})", hash));
+    }
+
+    let (archive, target) = (archive.to_path_buf(), target.to_path_buf());
+    tokio::task::spawn_blocking(move || extract_ffmpeg(&archive, &target)).await
+        .map_err(|e| e.to_string())?
+}
+
+/// Download with curl, like the manifest check, reporting the size on disk
+/// as progress
+async fn fetch(app_handle: &AppHandle, version: &str, url: &str, archive: &Path) -> Result<(), String> {
+    let total_bytes = content_length(url).await;
+    let mut child = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--output"])
+        .arg(archive)
+        .arg(url)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+
+    let mut progress_timer = tokio::time::interval(PROGRESS_INTERVAL);
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status.map_err(|e| format!("Failed to download FFmpeg: {}", e))?,
+            _ = progress_timer.tick() => {
+                let progress = DownloadProgress {
+                    version: version.to_string(),
+                    downloaded_bytes: fs::metadata(archive).map(|metadata| metadata.len()).unwrap_or(0),
+                    total_bytes,
+                };
+                let _ = app_handle.emit(FFMPEG_DOWNLOAD_PROGRESS, progress);
+            }
+        }
+    };
+    if !status.success() {
+        return Err(format!("Failed to download FFmpeg: curl exited with {}", status));
+    }
+    Ok(())
+}
+
+async fn content_length(url: &str) -> Option<u64> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--head", "--location", "--max-time", "15", url])
+        .output()
+        .await
+        .ok()?;
+    parse_content_length(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Length from the last response in `curl --head --location` output, which
+/// lists the headers of every redirect first
+fn parse_content_length(headers: &str) -> Option<u64> {
+    headers.lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if !name.trim().eq_ignore_ascii_case("content-length") {
+                return None;
+            }
+            value.trim().parse().ok()
+        })
+        .last()
+}
+
+fn extract_ffmpeg(archive: &Path, target: &Path) -> Result<(), String> {
+    let file = fs::File::open(archive)
+        .map_err(|e| format!("Failed to open the FFmpeg download: {}", e))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .map_err(|e| format!("The FFmpeg download isn't a valid archive: {}", e))?;
+
+    let name = target.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let index = (0..zip.len())
+        .find(|&index| zip.by_index(index).is_ok_and(|entry| {
+            entry.is_file() && Path::new(entry.name()).file_name().is_some_and(|entry_name| entry_name.to_string_lossy() == name)
+        }))
+        .ok_or_else(|| format!("The FFmpeg download has no {} in it", name))?;
+    let mut entry = zip.by_index(index)
+        .map_err(|e| format!("Failed to read the FFmpeg download: {}", e))?;
+
+    // Extract next to the target and rename, so a failed extraction never
+    // leaves a broken binary where the lookup finds it
+    let partial = target.with_extension("part");
+    let mut output = fs::File::create(&partial)
+        .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    io::copy(&mut entry, &mut output)
+        .map_err(|e| format!("Failed to extract FFmpeg: {}", e))?;
+    drop(output);
+    ensure_executable(&partial);
+    fs::rename(&partial, target)
+        .map_err(|e| format!("Failed to install FFmpeg: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_length_of_final_response() {
+        let headers = "HTTP/2 302\r\nlocation: https://cdn.example.com/ffmpeg.zip\r\ncontent-length: 0\r\n\r\nHTTP/2 200\r\nContent-Length: 27145302\r\n\r\n";
+        assert_eq!(parse_content_length(headers), Some(27145302));
+        assert_eq!(parse_content_length("HTTP/2 200\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn rejects_placeholder_checksums() {
+        assert!(!is_sha256("optional-hash-here"));
+        assert!(is_sha256(&"ab".repeat(32)));
+    }
+}
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FFmpegProgress {
@@ -9,6 +11,26 @@ pub struct FFmpegProgress {
     pub size: Option<String>,
 }
 
+impl FFmpegProgress {
+    /// Output written so far. FFmpeg reports "1024kB" (older) or "1024KiB".
+    pub fn size_bytes(&self) -> Option<u64> {
+        let size = self.size.as_deref()?;
+        let (number, unit) = size.split_at(size.find(|c: char| c.is_ascii_alphabetic())?);
+        let multiplier = match unit {
+            "B" => 1,
+            "kB" | "KiB" => 1024,
+            "mB" | "MiB" => 1024 * 1024,
+            _ => return None,
+        };
+        number.parse::<u64>().ok().map(|value| value * multiplier)
+    }
+
+    /// Output bitrate, from e.g. "100.0kbits/s"; `None` for "N/A"
+    pub fn bitrate_kbps(&self) -> Option<f32> {
+        self.bitrate.as_deref()?.strip_suffix("kbits/s")?.parse().ok()
+    }
+}
+
 /// Parse FFmpeg progress line that contains time= and other stats
 /// Example: "frame=  123 fps= 25 q=28.0 size=    1024kB time=00:00:05.12 bitrate= 100.0kbits/s speed=1.25x"
 pub fn parse_progress_line(line: &str) -> Option<FFmpegProgress> {
@@ -145,6 +167,306 @@ pub fn parse_progress_time(line: &str) -> Option<f64> {
     parse_time_to_seconds(time_str).ok()
 }
 
+/// Parse the frame size from an FFmpeg video stream info line
+/// Example: "  Stream #0:0: Video: vp9 (Profile 0), yuv420p(tv), 1920x1080, SAR 1:1 DAR 16:9, 30 fps"
+pub fn parse_video_resolution(line: &str) -> Option<(u32, u32)> {
+    if !line.contains("Stream #") || !line.contains("Video:") {
+        return None;
+    }
+
+    line.split(|c: char| c.is_whitespace() || c == ',')
+        .find_map(|token| {
+            let (width, height) = token.split_once('x')?;
+            let width = width.parse::<u32>().ok()?;
+            let height = height.parse::<u32>().ok()?;
+            if width > 0 && height > 0 {
+                Some((width, height))
+            } else {
+                None
+            }
+        })
+}
+
+/// Parse the crop suggestion from a cropdetect filter log line
+/// Example: "[Parsed_cropdetect_0 @ 0x7f8] x1:0 x2:1919 y1:138 y2:941 w:1920 h:800 x:0 y:140 pts:15 t:0.5 crop=1920:800:0:140"
+pub fn parse_cropdetect_line(line: &str) -> Option<(u32, u32, u32, u32)> {
+    let crop_str = line.split("crop=").nth(1)?;
+    let values: Vec<u32> = crop_str
+        .split_whitespace()
+        .next()?
+        .split(':')
+        .map(|v| v.parse::<u32>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match values.as_slice() {
+        [width, height, x, y] => Some((*width, *height, *x, *y)),
+        _ => None,
+    }
+}
+
+/// Parse the overall score from the ssim filter's summary
+/// Example: "[Parsed_ssim_2 @ 0x7f8] SSIM Y:0.991 (20.5) U:0.995 (23.0) V:0.994 (22.5) All:0.992 (21.2)"
+pub fn parse_ssim_score(line: &str) -> Option<f64> {
+    if !line.contains("SSIM ") {
+        return None;
+    }
+    let value = line.split("All:").nth(1)?;
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Parse the pooled score from libvmaf's summary
+/// Example: "[Parsed_libvmaf_2 @ 0x7f8] VMAF score: 93.471240"
+pub fn parse_vmaf_score(line: &str) -> Option<f64> {
+    let value = line.split("VMAF score:").nth(1)?;
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Parse the start of a silent interval from silencedetect output
+/// Example: "[silencedetect @ 0x7f8] silence_start: 12.345"
+pub fn parse_silence_start(line: &str) -> Option<f64> {
+    let value = line.split("silence_start:").nth(1)?;
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Parse the end of a silent interval from silencedetect output
+/// Example: "[silencedetect @ 0x7f8] silence_end: 15.2 | silence_duration: 2.855"
+pub fn parse_silence_end(line: &str) -> Option<f64> {
+    let value = line.split("silence_end:").nth(1)?;
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Work out the audible part of a recording from its silent intervals.
+/// Intervals without an end run to the end of the file. Returns None when
+/// there is no leading/trailing silence to trim or everything is silent.
+pub fn audible_range(silences: &[(f64, Option<f64>)], duration: f64) -> Option<(f64, f64)> {
+    const EDGE_TOLERANCE: f64 = 0.1;
+
+    let mut start = 0.0;
+    let mut end = duration;
+
+    if let Some((silence_start, silence_end)) = silences.first() {
+        if *silence_start <= EDGE_TOLERANCE {
+            start = silence_end.unwrap_or(duration);
+        }
+    }
+
+    if let Some((silence_start, silence_end)) = silences.last() {
+        let reaches_end = silence_end.is_none_or(|e| e >= duration - EDGE_TOLERANCE);
+        if reaches_end && *silence_start > EDGE_TOLERANCE {
+            end = *silence_start;
+        }
+    }
+
+    if start >= end || (start == 0.0 && end == duration) {
+        None
+    } else {
+        Some((start, end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+/// A single stream as listed in FFmpeg's input info
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamInfo {
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// Display rotation from the stream's display matrix, in degrees
+    #[serde(default)]
+    pub rotation: Option<f64>,
+    /// Everything after the codec name, e.g. "(LC), 48000 Hz, stereo, fltp"
+    pub details: String,
+}
+
+/// Parse a stream header line from FFmpeg's input info
+/// Example: "  Stream #0:1(eng): Audio: aac (LC) (mp4a / 0x6134706D), 48000 Hz, stereo, fltp, 128 kb/s (default)"
+pub fn parse_stream_line(line: &str) -> Option<StreamInfo> {
+    let rest = line.trim_start().strip_prefix("Stream #")?;
+    let (spec, description) = rest.split_once(": ")?;
+    let (kind, details) = description.split_once(": ").unwrap_or((description, ""));
+
+    // spec looks like "0:1", "0:1(eng)" or "0:0[0x1011](eng)"
+    let index = spec
+        .split(':')
+        .nth(1)?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()?;
+
+    let language = spec
+        .rsplit_once('(')
+        .and_then(|(_, lang)| lang.strip_suffix(')'))
+        .map(|lang| lang.to_string());
+
+    let kind = match kind.trim() {
+        "Video" => StreamKind::Video,
+        "Audio" => StreamKind::Audio,
+        "Subtitle" => StreamKind::Subtitle,
+        "Data" => StreamKind::Data,
+        "Attachment" => StreamKind::Attachment,
+        _ => StreamKind::Unknown,
+    };
+
+    let details = details.trim();
+    let (codec, details) = details
+        .split_once(|c: char| c.is_whitespace() || c == ',')
+        .unwrap_or((details, ""));
+
+    Some(StreamInfo {
+        index,
+        kind,
+        codec: codec.to_string(),
+        language,
+        title: None,
+        rotation: None,
+        details: details.trim_start_matches(',').trim().to_string(),
+    })
+}
+
+/// Parse every stream from FFmpeg's input info, including the metadata
+/// block that follows each stream header
+pub fn parse_streams(output: &str) -> Vec<StreamInfo> {
+    let mut streams: Vec<StreamInfo> = Vec::new();
+
+    for line in output.lines() {
+        if let Some(stream) = parse_stream_line(line) {
+            streams.push(stream);
+            continue;
+        }
+
+        // Metadata and side data lines are indented deeper than stream headers
+        if !line.starts_with("      ") {
+            continue;
+        }
+        let Some(stream) = streams.last_mut() else {
+            continue;
+        };
+        if let Some((key, value)) = line.split_once(" : ") {
+            if key.trim() == "title" {
+                stream.title = Some(value.trim().to_string());
+            }
+        } else if let Some(rotation) = line.split("displaymatrix: rotation of ").nth(1) {
+            stream.rotation = rotation.split_whitespace().next().and_then(|r| r.parse().ok());
+        }
+    }
+
+    streams
+}
+
+/// Parse the presentation time from a showinfo filter log line
+/// Example: "[Parsed_showinfo_0 @ 0x7f8] n:   3 pts: 384000 pts_time:12.5 duration:  512 ... iskey:1 type:I"
+pub fn parse_showinfo_pts_time(line: &str) -> Option<f64> {
+    let value = line.split("pts_time:").nth(1)?;
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// Find the first SMPTE timecode tag in FFmpeg's input info
+/// Example: "      timecode        : 01:00:00:00"
+pub fn parse_timecode(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(" : ")?;
+        let value = value.trim();
+        if key.trim() == "timecode" && is_valid_timecode(value) {
+            Some(value.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Check for an "HH:MM:SS:FF" timecode, or "HH:MM:SS;FF" for drop-frame
+pub fn is_valid_timecode(timecode: &str) -> bool {
+    let bytes = timecode.as_bytes();
+    if bytes.len() != 11 {
+        return false;
+    }
+
+    let separators_ok = bytes[2] == b':' && bytes[5] == b':' && matches!(bytes[8], b':' | b';');
+    let digits_ok = [0, 1, 3, 4, 6, 7, 9, 10].iter().all(|&i| bytes[i].is_ascii_digit());
+    if !separators_ok || !digits_ok {
+        return false;
+    }
+
+    let minutes = &timecode[3..5];
+    let seconds = &timecode[6..8];
+    minutes < "60" && seconds < "60"
+}
+
+/// Move a timecode forward by `seconds` of video at `fps`, wrapping at 24
+/// hours. None for drop-frame timecodes, whose frame numbers skip.
+pub fn shift_timecode(timecode: &str, seconds: f64, fps: f64) -> Option<String> {
+    if !is_valid_timecode(timecode) || timecode.as_bytes()[8] == b';' {
+        return None;
+    }
+    let rate = fps.round() as u64;
+    if rate == 0 {
+        return None;
+    }
+
+    let field = |start: usize| timecode[start..start + 2].parse::<u64>().ok();
+    let (hours, minutes, secs, frames) = (field(0)?, field(3)?, field(6)?, field(9)?);
+    let start = ((hours * 60 + minutes) * 60 + secs) * rate + frames;
+    let total = (start + (seconds.max(0.0) * fps).round() as u64) % (24 * 3600 * rate);
+    Some(format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        total / (3600 * rate),
+        total / (60 * rate) % 60,
+        total / rate % 60,
+        total % rate,
+    ))
+}
+
+/// Parse `ps -o %cpu=,rss=` output into CPU percent and resident memory in bytes
+/// Example: " 312.5  204800"
+pub fn parse_ps_usage(output: &str) -> Option<(f32, u64)> {
+    let mut fields = output.split_whitespace();
+    let cpu = fields.next()?.parse().ok()?;
+    let rss_kb: u64 = fields.next()?.parse().ok()?;
+    Some((cpu, rss_kb * 1024))
+}
+
+/// Suffixes of encoders that run on a GPU or media engine instead of the CPU
+const HARDWARE_ENCODER_SUFFIXES: &[&str] = &["_videotoolbox", "_nvenc", "_qsv", "_vaapi", "_amf", "_mf", "_v4l2m2m"];
+
+/// Names of the hardware video encoders in `ffmpeg -encoders` output
+/// Example line: " V....D h264_videotoolbox    VideoToolbox H.264 Encoder (codec h264)"
+pub fn parse_hardware_encoders(output: &str) -> Vec<String> {
+    output.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let flags = fields.next()?;
+            let name = fields.next()?;
+            (flags.len() == 6 && flags.starts_with('V')).then_some(name)
+        })
+        .filter(|name| HARDWARE_ENCODER_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Version from the first line of `ffmpeg -version`
+/// Example: "ffmpeg version 7.1.1 Copyright (c) 2000-2025 the FFmpeg developers"
+pub fn parse_ffmpeg_version(output: &str) -> Option<String> {
+    let version = output.lines().next()?.strip_prefix("ffmpeg version ")?.split_whitespace().next()?;
+    // Some builds tag releases like "n7.1"
+    Some(version.strip_prefix('n').filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit())).unwrap_or(version).to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,6 +504,8 @@ mod tests {
         assert_eq!(progress.size, Some("1024kB".to_string()));
         assert_eq!(progress.bitrate, Some("100.0kbits/s".to_string()));
         assert_eq!(progress.speed, Some(1.25));
+        assert_eq!(progress.size_bytes(), Some(1024 * 1024));
+        assert_eq!(progress.bitrate_kbps(), Some(100.0));
 
         // Test minimal progress line
         let line2 = "time=00:00:10.00";
@@ -199,6 +523,7 @@ mod tests {
         assert_eq!(progress4.time_seconds, 0.0);
         assert_eq!(progress4.frame, Some(456));
         assert_eq!(progress4.fps, Some(0.0));
+        assert_eq!(progress4.bitrate_kbps(), None);
 
         // Test with extra spaces (common in FFmpeg output)
         let line5 = "frame=   10 fps= 0 q=0.0 size=       0kB time=00:00:00.41 bitrate=   0.0kbits/s speed=0.835x";
@@ -225,4 +550,170 @@ mod tests {
         assert_eq!(parse_progress_time("frame=123"), None);
         assert_eq!(parse_progress_time("speed=1.25x"), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_video_resolution() {
+        let line = "  Stream #0:0: Video: vp9 (Profile 0), yuv420p(tv, bt709), 1920x1080, SAR 1:1 DAR 16:9, 30 fps, 30 tbr, 1k tbn (default)";
+        assert_eq!(parse_video_resolution(line), Some((1920, 1080)));
+
+        // Codec tags like 0x31637661 must not be mistaken for a frame size
+        let line2 = "  Stream #0:0(und): Video: h264 (High) (avc1 / 0x31637661), yuv420p(progressive), 1280x720 [SAR 1:1 DAR 16:9], 2500 kb/s, 30 fps";
+        assert_eq!(parse_video_resolution(line2), Some((1280, 720)));
+
+        let line3 = "  Stream #0:1: Audio: opus, 48000 Hz, stereo, fltp (default)";
+        assert_eq!(parse_video_resolution(line3), None);
+    }
+
+    #[test]
+    fn test_parse_cropdetect_line() {
+        let line = "[Parsed_cropdetect_0 @ 0x7f8] x1:0 x2:1919 y1:138 y2:941 w:1920 h:800 x:0 y:140 pts:15 t:0.500000 limit:0.094118 crop=1920:800:0:140";
+        assert_eq!(parse_cropdetect_line(line), Some((1920, 800, 0, 140)));
+
+        assert_eq!(parse_cropdetect_line("crop=1920:800"), None);
+        assert_eq!(parse_cropdetect_line("frame=  123 fps= 25"), None);
+    }
+
+    #[test]
+    fn test_parse_silence_events() {
+        assert_eq!(parse_silence_start("[silencedetect @ 0x7f8] silence_start: 12.345"), Some(12.345));
+        assert_eq!(parse_silence_start("[silencedetect @ 0x7f8] silence_start: -0.0213"), Some(-0.0213));
+        assert_eq!(parse_silence_end("[silencedetect @ 0x7f8] silence_end: 15.2 | silence_duration: 2.855"), Some(15.2));
+        assert_eq!(parse_silence_end("[silencedetect @ 0x7f8] silence_start: 12.345"), None);
+    }
+
+    #[test]
+    fn test_audible_range() {
+        // Leading and trailing silence
+        assert_eq!(audible_range(&[(0.0, Some(62.5)), (3500.0, None)], 3600.0), Some((62.5, 3500.0)));
+
+        // Silence in the middle is kept
+        assert_eq!(audible_range(&[(0.0, Some(5.0)), (100.0, Some(110.0))], 600.0), Some((5.0, 600.0)));
+
+        // Nothing to trim
+        assert_eq!(audible_range(&[(100.0, Some(110.0))], 600.0), None);
+        assert_eq!(audible_range(&[], 600.0), None);
+
+        // Completely silent
+        assert_eq!(audible_range(&[(0.0, None)], 600.0), None);
+    }
+
+    #[test]
+    fn test_parse_stream_line() {
+        let audio = parse_stream_line("  Stream #0:1(eng): Audio: aac (LC) (mp4a / 0x6134706D), 48000 Hz, stereo, fltp, 128 kb/s (default)").unwrap();
+        assert_eq!(audio.index, 1);
+        assert_eq!(audio.kind, StreamKind::Audio);
+        assert_eq!(audio.codec, "aac");
+        assert_eq!(audio.language, Some("eng".to_string()));
+        assert_eq!(audio.details, "(LC) (mp4a / 0x6134706D), 48000 Hz, stereo, fltp, 128 kb/s (default)");
+
+        let video = parse_stream_line("  Stream #0:0[0x1011](und): Video: h264 (High), yuv420p, 1920x1080").unwrap();
+        assert_eq!(video.index, 0);
+        assert_eq!(video.kind, StreamKind::Video);
+        assert_eq!(video.language, Some("und".to_string()));
+
+        let attachment = parse_stream_line("  Stream #0:3: Attachment: ttf").unwrap();
+        assert_eq!(attachment.kind, StreamKind::Attachment);
+        assert_eq!(attachment.codec, "ttf");
+        assert_eq!(attachment.language, None);
+
+        assert_eq!(parse_stream_line("  Duration: 00:05:23.45, start: 0.000000"), None);
+    }
+
+    #[test]
+    fn test_parse_streams_with_metadata() {
+        let output = "Input #0, matroska,webm, from 'movie.mkv':
+  Metadata:
+    title           : Movie
+  Duration: 01:30:00.00, start: 0.000000, bitrate: 5000 kb/s
+  Stream #0:0: Video: h264 (High), yuv420p(progressive), 1920x1080, 23.98 fps
+    Side data:
+      displaymatrix: rotation of -90.00 degrees
+  Stream #0:1(eng): Audio: ac3, 48000 Hz, 5.1(side), fltp, 448 kb/s (default)
+    Metadata:
+      title           : Surround
+  Stream #0:2(deu): Audio: aac (LC), 48000 Hz, stereo, fltp
+  Stream #0:3(eng): Subtitle: subrip
+    Metadata:
+      title           : Commentary";
+
+        let streams = parse_streams(output);
+        assert_eq!(streams.len(), 4);
+        assert_eq!(streams[0].title, None);
+        assert_eq!(streams[0].rotation, Some(-90.0));
+        assert_eq!(streams[1].title, Some("Surround".to_string()));
+        assert_eq!(streams[2].language, Some("deu".to_string()));
+        assert_eq!(streams[2].title, None);
+        assert_eq!(streams[3].kind, StreamKind::Subtitle);
+        assert_eq!(streams[3].title, Some("Commentary".to_string()));
+    }
+
+    #[test]
+    fn test_parse_showinfo_pts_time() {
+        let line = "[Parsed_showinfo_0 @ 0x7f8] n:   3 pts: 384000 pts_time:12.5    duration:    512 duration_time:0.04 fmt:yuv420p iskey:1 type:I";
+        assert_eq!(parse_showinfo_pts_time(line), Some(12.5));
+        assert_eq!(parse_showinfo_pts_time("frame=  123 fps= 25"), None);
+    }
+
+    #[test]
+    fn test_parse_timecode() {
+        let output = "  Stream #0:0(eng): Video: prores (HQ) (apch / 0x68637061), yuv422p10le, 1920x1080
+    Metadata:
+      handler_name    : Core Media Video
+      timecode        : 01:00:10:05
+  Stream #0:2(eng): Data: none (tmcd / 0x64636D74)";
+        assert_eq!(parse_timecode(output), Some("01:00:10:05".to_string()));
+        assert_eq!(parse_timecode("  Duration: 00:05:23.45, start: 0.000000"), None);
+    }
+
+    #[test]
+    fn test_is_valid_timecode() {
+        assert!(is_valid_timecode("01:00:00:00"));
+        assert!(is_valid_timecode("00:59:59;29"));
+        assert!(!is_valid_timecode("01:60:00:00"));
+        assert!(!is_valid_timecode("1:00:00:00"));
+        assert!(!is_valid_timecode("01:00:00.00"));
+    }
+
+    #[test]
+    fn test_shift_timecode() {
+        assert_eq!(shift_timecode("01:00:00:00", 10.0, 25.0), Some("01:00:10:00".to_string()));
+        assert_eq!(shift_timecode("00:00:00:10", 1.5, 24.0), Some("00:00:01:22".to_string()));
+        assert_eq!(shift_timecode("23:59:59:24", 0.04, 25.0), Some("00:00:00:00".to_string()));
+        assert_eq!(shift_timecode("01:00:00;00", 10.0, 29.97), None);
+        assert_eq!(shift_timecode("01:00:00:00", 10.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_parse_ps_usage() {
+        assert_eq!(parse_ps_usage(" 312.5  204800\n"), Some((312.5, 204800 * 1024)));
+        assert_eq!(parse_ps_usage(""), None);
+    }
+
+    #[test]
+    fn test_parse_hardware_encoders() {
+        let output = "Encoders:
+ V..... = Video
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+ V....D h264_videotoolbox    VideoToolbox H.264 Encoder (codec h264)
+ V....D hevc_videotoolbox    VideoToolbox H.265 Encoder (codec hevc)
+ A....D aac                  AAC (Advanced Audio Coding)";
+        assert_eq!(parse_hardware_encoders(output), vec!["h264_videotoolbox", "hevc_videotoolbox"]);
+    }
+
+    #[test]
+    fn test_parse_ffmpeg_version() {
+        assert_eq!(parse_ffmpeg_version("ffmpeg version 7.1.1 Copyright (c) 2000-2025 the FFmpeg developers\nbuilt with clang").as_deref(), Some("7.1.1"));
+        assert_eq!(parse_ffmpeg_version("ffmpeg version n7.1-static https://johnvansickle.com/ffmpeg/").as_deref(), Some("7.1-static"));
+        assert_eq!(parse_ffmpeg_version("ffmpeg version N-118000-g1234abcd").as_deref(), Some("N-118000-g1234abcd"));
+        assert_eq!(parse_ffmpeg_version("zsh: command not found: ffmpeg"), None);
+    }
+
+    #[test]
+    fn test_parse_quality_scores() {
+        let ssim = "[Parsed_ssim_2 @ 0x600] SSIM Y:0.991289 (20.599) U:0.995 (23.0) V:0.994 (22.5) All:0.992476 (21.236)";
+        assert_eq!(parse_ssim_score(ssim), Some(0.992476));
+        assert_eq!(parse_vmaf_score("[Parsed_libvmaf_2 @ 0x600] VMAF score: 93.471240"), Some(93.47124));
+        assert_eq!(parse_ssim_score("frame=  240 fps= 60 q=-0.0 Lsize=N/A"), None);
+    }
+}
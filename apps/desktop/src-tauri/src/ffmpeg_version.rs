@@ -1,12 +1,30 @@
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fs;
+use sha2::{Digest, Sha256};
 use tauri::{AppHandle, Manager};
+use crate::ffmpeg::{locate_ffmpeg, FFmpegSource};
+use crate::ffmpeg_parser::parse_ffmpeg_version;
+
+/// The same manifest `scripts/download-ffmpeg.sh` reads, as published on main
+const FFMPEG_MANIFEST_URL: &str =
+    "https://raw.githubusercontent.com/dendotai/transpoze-app/main/apps/desktop/scripts/ffmpeg-versions.json";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FFmpegVersionInfo {
     pub version: String,
     pub date: String,
     pub updated: String,
+    /// Expected size and hash of each bundled binary, keyed by file name
+    #[serde(default)]
+    pub binaries: HashMap<String, BinaryChecksum>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryChecksum {
+    pub size: u64,
+    pub sha256: String,
 }
 
 pub fn get_ffmpeg_version(app_handle: &AppHandle) -> Result<FFmpegVersionInfo, String> {
@@ -38,4 +56,303 @@ pub fn get_ffmpeg_version(app_handle: &AppHandle) -> Result<FFmpegVersionInfo, S
 #[tauri::command]
 pub fn get_ffmpeg_version_info(app_handle: AppHandle) -> Result<FFmpegVersionInfo, String> {
     get_ffmpeg_version(&app_handle)
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+struct FFmpegManifest {
+    current: String,
+    versions: HashMap<String, FFmpegRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FFmpegRelease {
+    date: String,
+    intel: Option<FFmpegDownload>,
+    arm64: Option<FFmpegDownload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FFmpegDownload {
+    pub url: String,
+    pub sha256: Option<String>,
+}
+
+/// How the FFmpeg in use compares with the latest published release. Only
+/// informs: updates arrive with a new app build, not through the downloader.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FFmpegUpdateStatus {
+    pub installed: String,
+    pub latest: String,
+    pub latest_date: String,
+    pub update_available: bool,
+}
+
+/// The manifest this build was made with, so downloads are pinned to a
+/// known release and hash rather than whatever is published later
+const PINNED_MANIFEST: &str = include_str!("../../scripts/ffmpeg-versions.json");
+
+impl FFmpegRelease {
+    /// The build for this machine's architecture
+    fn download(&self) -> Option<FFmpegDownload> {
+        if cfg!(target_arch = "aarch64") {
+            self.arm64.clone()
+        } else {
+            self.intel.clone()
+        }
+    }
+}
+
+/// Version and build of the FFmpeg this app downloads when none is bundled
+pub fn pinned_download() -> Result<(String, FFmpegDownload), String> {
+    if !cfg!(target_os = "macos") {
+        return Err("No FFmpeg download is available for this platform. Install FFmpeg and add it to the PATH.".to_string());
+    }
+    let manifest: FFmpegManifest = serde_json::from_str(PINNED_MANIFEST)
+        .map_err(|e| format!("Failed to parse FFmpeg manifest: {}", e))?;
+    let download = manifest.versions.get(&manifest.current)
+        .and_then(FFmpegRelease::download)
+        .ok_or_else(|| format!("FFmpeg manifest has no download for {}", manifest.current))?;
+    Ok((manifest.current, download))
+}
+
+pub fn is_sha256(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Compare dotted version numbers like "7.1.1" and "7.1"
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version.split('.').map(|part| part.trim().parse().unwrap_or(0)).collect()
+    };
+    let (a, b) = (parts(a), parts(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+async fn fetch_manifest() -> Result<FFmpegManifest, String> {
+    // curl ships with macOS and Windows 10+, so no HTTP client is bundled for one request
+    let output = tokio::process::Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "15", FFMPEG_MANIFEST_URL])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run curl: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to fetch FFmpeg manifest: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse FFmpeg manifest: {}", e))
+}
+
+/// Compare the FFmpeg conversions use with the latest published one.
+#[tauri::command]
+pub async fn check_ffmpeg_update(app_handle: AppHandle) -> Result<FFmpegUpdateStatus, String> {
+    let installed = resolved_ffmpeg_version(&app_handle).await?;
+    let manifest = fetch_manifest().await?;
+    let release = manifest.versions.get(&manifest.current)
+        .ok_or_else(|| format!("FFmpeg manifest has no entry for {}", manifest.current))?;
+
+    Ok(FFmpegUpdateStatus {
+        update_available: compare_versions(&manifest.current, &installed) == Ordering::Greater,
+        installed,
+        latest: manifest.current,
+        latest_date: release.date.clone(),
+    })
+}
+
+/// Version of the FFmpeg `locate_ffmpeg` picks. The bundled one is described
+/// by its version file; any other is asked with `-version`.
+async fn resolved_ffmpeg_version(app_handle: &AppHandle) -> Result<String, String> {
+    let (path, source) = locate_ffmpeg(app_handle).map_err(|e| e.to_string())?;
+    if source == FFmpegSource::Bundled {
+        if let Ok(info) = get_ffmpeg_version(app_handle) {
+            return Ok(info.version);
+        }
+    }
+    detect_ffmpeg_version(&path).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FFmpegProblem {
+    Missing,
+    /// Smaller than the bundled binary, usually an interrupted copy or download
+    Truncated,
+    /// Right size but wrong contents
+    Corrupted,
+    /// Blocked by Gatekeeper until the quarantine flag is removed
+    Quarantined,
+    /// A system or user-picked FFmpeg that doesn't run
+    Unusable,
+}
+
+/// Result of checking the bundled FFmpeg binary at startup
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FFmpegDiagnostic {
+    pub problem: Option<FFmpegProblem>,
+    pub path: Option<String>,
+    pub source: Option<FFmpegSource>,
+    /// Version of a working FFmpeg, as the version file or `-version` reports it
+    pub version: Option<String>,
+    pub message: String,
+}
+
+impl FFmpegDiagnostic {
+    fn problem(problem: FFmpegProblem, path: Option<String>, message: String) -> Self {
+        FFmpegDiagnostic { problem: Some(problem), path, source: None, version: None, message }
+    }
+}
+
+/// Run `ffmpeg -version` to check that a binary works and find its version
+pub async fn detect_ffmpeg_version(path: &std::path::Path) -> Result<String, String> {
+    let output = tokio::process::Command::new(path)
+        .arg("-version")
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("FFmpeg at {} can't be started: {}", path.display(), e))?;
+    if !output.status.success() {
+        return Err(format!("FFmpeg at {} exited with {}", path.display(), output.status));
+    }
+    parse_ffmpeg_version(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| format!("{} doesn't look like FFmpeg", path.display()))
+}
+
+/// Check that the FFmpeg binary exists, matches the size and hash recorded
+/// in `ffmpeg-version.json` and isn't quarantined, so a broken install is
+/// reported once at launch rather than as a failure on every job. A system
+/// or user-picked FFmpeg has no recorded hash and is run once instead.
+pub async fn verify_ffmpeg_binary(app_handle: &AppHandle) -> FFmpegDiagnostic {
+    let (path, source) = match locate_ffmpeg(app_handle) {
+        Ok(found) => found,
+        Err(e) => return FFmpegDiagnostic::problem(FFmpegProblem::Missing, None, e.to_string()),
+    };
+    let path_str = Some(path.to_string_lossy().to_string());
+
+    if source != FFmpegSource::Bundled {
+        return match detect_ffmpeg_version(&path).await {
+            Ok(version) => FFmpegDiagnostic {
+                problem: None,
+                path: path_str,
+                source: Some(source),
+                message: format!("FFmpeg {} from {} is ready", version, path.display()),
+                version: Some(version),
+            },
+            Err(e) => FFmpegDiagnostic::problem(FFmpegProblem::Unusable, path_str, e),
+        };
+    }
+
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return FFmpegDiagnostic::problem(FFmpegProblem::Missing, path_str, format!("FFmpeg can't be read: {}", e));
+        }
+    };
+
+    // Older version files have no checksums; then only the checks below apply
+    let version = get_ffmpeg_version(app_handle).ok();
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    if let Some(expected) = version.as_ref().and_then(|version| version.binaries.get(&file_name)) {
+        if metadata.len() != expected.size {
+            let problem = if metadata.len() < expected.size { FFmpegProblem::Truncated } else { FFmpegProblem::Corrupted };
+            return FFmpegDiagnostic::problem(problem, path_str, format!(
+                "FFmpeg is {} bytes but should be {} bytes. Reinstall the app to repair it.",
+                metadata.len(), expected.size
+            ));
+        }
+
+        let hash_path = path.clone();
+        let hash = tokio::task::spawn_blocking(move || sha256_file(&hash_path)).await
+            .map_err(|e| e.to_string())
+            .and_then(|hash| hash);
+        match hash {
+            Ok(hash) if hash.eq_ignore_ascii_case(&expected.sha256) => {}
+            Ok(_) => {
+                return FFmpegDiagnostic::problem(FFmpegProblem::Corrupted, path_str,
+                    "FFmpeg doesn't match the bundled build. Reinstall the app to repair it.".to_string());
+            }
+            Err(e) => {
+                return FFmpegDiagnostic::problem(FFmpegProblem::Missing, path_str, format!("FFmpeg can't be read: {}", e));
+            }
+        }
+    }
+
+    if is_quarantined(&path).await {
+        return FFmpegDiagnostic::problem(FFmpegProblem::Quarantined, path_str,
+            "macOS has quarantined FFmpeg. Move the app to Applications and open it again, or allow it in System Settings > Privacy & Security.".to_string());
+    }
+
+    FFmpegDiagnostic {
+        problem: None,
+        path: path_str,
+        source: Some(source),
+        message: match &version {
+            Some(version) => format!("FFmpeg {} is ready", version.version),
+            None => "FFmpeg is ready".to_string(),
+        },
+        version: version.map(|version| version.version),
+    }
+}
+
+pub fn sha256_file(path: &std::path::Path) -> Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[cfg(target_os = "macos")]
+async fn is_quarantined(path: &std::path::Path) -> bool {
+    tokio::process::Command::new("xattr")
+        .arg("-p")
+        .arg("com.apple.quarantine")
+        .arg(path)
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(not(target_os = "macos"))]
+async fn is_quarantined(_path: &std::path::Path) -> bool {
+    false
+}
+
+/// The startup check result, or a fresh check if it hasn't finished yet.
+#[tauri::command]
+pub async fn get_ffmpeg_diagnostic(
+    app_handle: AppHandle,
+    state: tauri::State<'_, crate::state::AppState>,
+) -> Result<FFmpegDiagnostic, String> {
+    if let Some(diagnostic) = state.ffmpeg_diagnostic.lock().await.clone() {
+        return Ok(diagnostic);
+    }
+    Ok(verify_ffmpeg_binary(&app_handle).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "fails until the hashes are pinned with scripts/download-ffmpeg.sh --pin"]
+    fn pinned_manifest_has_checksums_for_each_mac() {
+        let manifest: FFmpegManifest = serde_json::from_str(PINNED_MANIFEST).unwrap();
+        let release = &manifest.versions[&manifest.current];
+        for download in [&release.intel, &release.arm64] {
+            let hash = download.as_ref().and_then(|download| download.sha256.as_deref());
+            assert!(hash.is_some_and(is_sha256), "{:?} is not a SHA-256", hash);
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use crate::estimate::target_video_kbps;
+use crate::ffmpeg::{output_container, AudioRateControl, ConversionJob, CropRect, EdgeInsets};
+use crate::ffmpeg_parser::is_valid_timecode;
+use crate::state::AppSettings;
+
+/// The detected crop, if the job actually uses it. It's only a suggestion:
+/// explicit per-job edges win over it, and it's applied only when the job or
+/// the `auto_crop` setting asks for it.
+pub fn applied_detected_crop(job: &ConversionJob, auto_crop: bool) -> Option<&CropRect> {
+    if job.options.crop_edges.is_some_and(|edges| !edges.is_empty()) {
+        return None;
+    }
+    job.detected_crop.as_ref().filter(|_| job.options.apply_detected_crop.unwrap_or(auto_crop))
+}
+
+/// Build the `-vf` filtergraph for a job. Cropping and padding run before
+/// scaling so the preset resizes only the final visible picture.
+pub fn build_video_filters(job: &ConversionJob, settings: &AppSettings) -> Option<String> {
+    let mut filters = Vec::new();
+
+    match job.options.crop_edges.filter(|edges| !edges.is_empty()) {
+        Some(edges) => filters.push(crop_edges_filter(&edges)),
+        None => {
+            if let Some(crop) = applied_detected_crop(job, settings.auto_crop) {
+                filters.push(crop.to_filter());
+            }
+        }
+    }
+
+    if let Some(edges) = job.options.pad_edges.filter(|edges| !edges.is_empty()) {
+        filters.push(pad_edges_filter(&edges));
+    }
+
+    let animation = job.preset.is_animation();
+    if animation {
+        // Dropping frames first keeps boomerang and loop buffers small
+        filters.push(format!("fps={}", ANIMATION_FPS));
+    }
+
+    if let Some(scale) = &job.preset.scale {
+        if animation {
+            // Lanczos stays sharp at the small sizes animations use
+            filters.push(format!("scale={}:flags=lanczos", scale));
+        } else {
+            filters.push(format!("scale={}", scale));
+        }
+    }
+
+    filters.extend(post_filters(job, StreamType::Video));
+
+    if job.preset.video_codec == "gif" {
+        // GIF only has 256 colors. A palette built from this clip instead of
+        // the generic one avoids banding and the worst of the dithering noise.
+        filters.push(
+            "split[frames][palette_in];[palette_in]palettegen=stats_mode=diff[palette];\
+             [frames][palette]paletteuse=dither=bayer:bayer_scale=5:diff_mode=rectangle"
+                .to_string(),
+        );
+    }
+
+    if filters.is_empty() {
+        None
+    } else {
+        Some(filters.join(","))
+    }
+}
+
+/// Build the `-af` filtergraph for a job.
+pub fn build_audio_filters(job: &ConversionJob) -> Option<String> {
+    let mut filters = Vec::new();
+
+    let delay = job.options.audio_delay_ms;
+    if delay > 0 {
+        filters.push(format!("adelay={}:all=1", delay));
+    } else if delay < 0 {
+        // Drop the first part of the audio and restart its timestamps at zero
+        filters.push(format!("atrim=start={:.3}", f64::from(delay.unsigned_abs()) / 1000.0));
+        filters.push("asetpts=PTS-STARTPTS".to_string());
+    }
+
+    filters.extend(post_filters(job, StreamType::Audio));
+
+    if filters.is_empty() {
+        None
+    } else {
+        Some(filters.join(","))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum StreamType {
+    Video,
+    Audio,
+}
+
+/// Output-shaping filters (boomerang, loop) appended after all picture and
+/// sync adjustments, mirrored on the audio side so both stay the same length.
+fn post_filters(job: &ConversionJob, stream: StreamType) -> Vec<String> {
+    let prefix = match stream {
+        StreamType::Video => "",
+        StreamType::Audio => "a",
+    };
+    let (video, audio) = match stream {
+        StreamType::Video => (1, 0),
+        StreamType::Audio => (0, 1),
+    };
+    let mut filters = Vec::new();
+
+    if job.options.boomerang {
+        filters.push(format!(
+            "{p}split[fwd][rev];[rev]{p}reverse[back];[fwd][back]concat=n=2:v={}:a={}",
+            video,
+            audio,
+            p = prefix
+        ));
+    }
+
+    if job.options.loop_count > 1 {
+        let repeats = job.options.loop_count - 1;
+        match stream {
+            StreamType::Video => filters.push(format!("loop=loop={}:size=32767:start=0", repeats)),
+            StreamType::Audio => filters.push(format!("aloop=loop={}:size=2147483647:start=0", repeats)),
+        }
+    }
+
+    filters
+}
+
+/// How much longer the output is than the (trimmed) source, used to scale
+/// progress so the bar still ends at 100%.
+pub fn output_duration_factor(job: &ConversionJob) -> f64 {
+    let boomerang = if job.options.boomerang { 2.0 } else { 1.0 };
+    boomerang * f64::from(job.options.loop_count.max(1))
+}
+
+fn crop_edges_filter(edges: &EdgeInsets) -> String {
+    format!(
+        "crop=iw-{}:ih-{}:{}:{}",
+        edges.horizontal(),
+        edges.vertical(),
+        edges.left,
+        edges.top
+    )
+}
+
+fn pad_edges_filter(edges: &EdgeInsets) -> String {
+    format!(
+        "pad=iw+{}:ih+{}:{}:{}:black",
+        edges.horizontal(),
+        edges.vertical(),
+        edges.left,
+        edges.top
+    )
+}
+
+/// Frame rate of GIF and WebP animations
+const ANIMATION_FPS: u32 = 15;
+const MAX_AUDIO_DELAY_MS: u32 = 60_000;
+const MAX_LOOP_COUNT: u32 = 20;
+/// Loop and reverse filters buffer decoded frames in memory
+const MAX_LOOPED_CLIP_SECONDS: f64 = 60.0;
+/// Below this a target size would only produce a smeared mess
+const MIN_TARGET_VIDEO_KBPS: f64 = 100.0;
+
+/// Check the per-job options against the probed source. Odd crop/pad values
+/// are rejected because 4:2:0 chroma subsampling needs even sizes.
+pub fn validate_job_options(job: &ConversionJob) -> Result<(), String> {
+    let container = output_container(&job.output_path);
+
+    if job.preset.audio_codec == "libopus" && !matches!(container.as_str(), "mkv" | "webm") {
+        return Err("Opus audio needs an MKV or WebM output".to_string());
+    }
+
+    if let Some(bitrate) = &job.preset.audio_bitrate {
+        let valid = bitrate
+            .strip_suffix('k')
+            .is_some_and(|kbps| kbps.parse::<u32>().is_ok_and(|kbps| (6..=512).contains(&kbps)));
+        if !valid {
+            return Err(format!("'{}' is not a valid audio bitrate (e.g. 96k)", bitrate));
+        }
+    }
+
+    if job.preset.audio_codec == "aac" && job.preset.audio_rate_control == Some(AudioRateControl::Variable) {
+        match job.preset.audio_quality {
+            Some(quality) if (0.1..=2.0).contains(&quality) => {}
+            _ => return Err("AAC quality mode needs an audio quality between 0.1 and 2.0".to_string()),
+        }
+    }
+
+    if job.options.keep_data_streams && container != "mov" {
+        return Err("Data streams can only be kept in MOV outputs".to_string());
+    }
+
+    if job.options.audio_delay_ms.unsigned_abs() > MAX_AUDIO_DELAY_MS {
+        return Err(format!(
+            "Audio delay must be within ±{} seconds",
+            MAX_AUDIO_DELAY_MS / 1000
+        ));
+    }
+
+    if let Some(timecode) = &job.options.start_timecode {
+        if !is_valid_timecode(timecode) {
+            return Err(format!("'{}' is not a valid timecode (HH:MM:SS:FF)", timecode));
+        }
+    }
+
+    if let (Some(start), Some(end)) = (job.options.trim_start, job.options.trim_end) {
+        if start >= end {
+            return Err("Trim start must be before the trim end".to_string());
+        }
+    }
+
+    if job.options.trim_start.is_some_and(|start| start < 0.0) {
+        return Err("Trim start can't be negative".to_string());
+    }
+
+    if job.preset.is_remux() {
+        let needs_encoding = job.options.crop_edges.is_some_and(|edges| !edges.is_empty())
+            || job.options.pad_edges.is_some_and(|edges| !edges.is_empty())
+            || job.options.loop_count > 1
+            || job.options.boomerang
+            || job.preset.scale.is_some();
+        if needs_encoding {
+            return Err("Remux can't be combined with crop, padding, scaling, loop or boomerang".to_string());
+        }
+        if job.options.audio_delay_ms != 0 && job.preset.audio_codec == "copy" {
+            return Err("Audio delay needs the audio to be re-encoded".to_string());
+        }
+    }
+
+    if job.options.fast_cut {
+        if job.options.trim_start.is_none() && job.options.trim_end.is_none() {
+            return Err("Fast cut needs a trim start or end".to_string());
+        }
+
+        let needs_encoding = job.options.crop_edges.is_some_and(|edges| !edges.is_empty())
+            || job.options.pad_edges.is_some_and(|edges| !edges.is_empty())
+            || job.options.audio_delay_ms != 0
+            || job.options.loop_count > 1
+            || job.options.boomerang;
+        if needs_encoding {
+            return Err("Fast cut can't be combined with crop, padding, audio delay, loop or boomerang".to_string());
+        }
+    }
+
+    if let Some(target_mb) = job.options.target_size_mb {
+        if !target_mb.is_finite() || target_mb <= 0.0 {
+            return Err("Target size must be more than 0 MB".to_string());
+        }
+        if job.preset.video_codec != "libx264" {
+            return Err("Target size needs an H.264 (libx264) preset".to_string());
+        }
+        if job.options.fast_cut {
+            return Err("Fast cut can't be combined with a target size".to_string());
+        }
+        if job.options.audio_passthrough {
+            return Err("Audio passthrough can't be combined with a target size".to_string());
+        }
+        if job.duration.is_some() && target_video_kbps(job).is_none_or(|kbps| kbps < MIN_TARGET_VIDEO_KBPS) {
+            return Err(format!("{} MB is too small for this clip's length", target_mb));
+        }
+    }
+
+    if job.preset.is_animation() {
+        if job.options.fast_cut {
+            return Err("Fast cut copies the video and can't make a GIF or WebP animation".to_string());
+        }
+        if job.options.audio_passthrough {
+            return Err("GIF and WebP animations have no audio to pass through".to_string());
+        }
+    }
+
+    if job.options.audio_passthrough {
+        if container == "webm" {
+            return Err("WebM outputs can't hold surround audio, so it can't be passed through".to_string());
+        }
+
+        let filters_audio = job.options.audio_delay_ms != 0 || job.options.loop_count > 1 || job.options.boomerang;
+        if filters_audio {
+            return Err("Audio passthrough can't be combined with audio delay, loop or boomerang".to_string());
+        }
+    }
+
+    if job.options.loop_count > MAX_LOOP_COUNT {
+        return Err(format!("Loop count can be at most {}", MAX_LOOP_COUNT));
+    }
+
+    if let Some(duration) = job.duration {
+        let shapes_output = job.options.boomerang || job.options.loop_count > 1;
+        if shapes_output && duration > MAX_LOOPED_CLIP_SECONDS {
+            return Err(format!(
+                "Loop and boomerang modes are limited to clips up to {} seconds",
+                MAX_LOOPED_CLIP_SECONDS
+            ));
+        }
+
+        if job.options.trim_start.is_some_and(|start| start >= duration) {
+            return Err("Trim start is past the end of the video".to_string());
+        }
+
+        if job.options.audio_delay_ms < 0 && f64::from(job.options.audio_delay_ms.unsigned_abs()) / 1000.0 >= duration {
+            return Err("Audio delay is longer than the video".to_string());
+        }
+    }
+
+    for (label, edges) in [("Crop", job.options.crop_edges), ("Padding", job.options.pad_edges)] {
+        if let Some(edges) = edges {
+            if [edges.top, edges.bottom, edges.left, edges.right].iter().any(|v| v % 2 != 0) {
+                return Err(format!("{} values must be even numbers of pixels", label));
+            }
+        }
+    }
+
+    if let (Some(edges), Some(width), Some(height)) = (job.options.crop_edges, job.width, job.height) {
+        if edges.horizontal() >= width || edges.vertical() >= height {
+            return Err(format!(
+                "Crop of {}px horizontally and {}px vertically doesn't fit a {}x{} video",
+                edges.horizontal(),
+                edges.vertical(),
+                width,
+                height
+            ));
+        }
+    }
+
+    validate_extra_args(&job.preset.extra_args)?;
+    validate_extra_args(&job.options.extra_args)?;
+
+    Ok(())
+}
+
+/// Options allowed as advanced FFmpeg arguments, with the number of values
+/// each one takes. Anything else is rejected: options that read or write
+/// files (`-vstats_file`, filtergraphs with `movie=`) or that the app sets
+/// itself (`-i`, `-f`, `-pass`) must never get in, and a value without an
+/// option would become a second output file.
+const EXTRA_ARG_OPTIONS: [(&str, usize); 38] = [
+    ("-shortest", 0),
+    ("-an", 0),
+    ("-sn", 0),
+    ("-dn", 0),
+    ("-copyts", 0),
+    ("-start_at_zero", 0),
+    ("-tune", 1),
+    ("-preset", 1),
+    ("-profile:v", 1),
+    ("-level", 1),
+    ("-level:v", 1),
+    ("-x264-params", 1),
+    ("-x265-params", 1),
+    ("-svtav1-params", 1),
+    ("-g", 1),
+    ("-keyint_min", 1),
+    ("-bf", 1),
+    ("-refs", 1),
+    ("-sc_threshold", 1),
+    ("-maxrate", 1),
+    ("-minrate", 1),
+    ("-bufsize", 1),
+    ("-threads", 1),
+    ("-pix_fmt", 1),
+    ("-colorspace", 1),
+    ("-color_primaries", 1),
+    ("-color_trc", 1),
+    ("-color_range", 1),
+    ("-r", 1),
+    ("-fps_mode", 1),
+    ("-ac", 1),
+    ("-ar", 1),
+    ("-movflags", 1),
+    ("-tag:v", 1),
+    ("-map", 1),
+    ("-map_metadata", 1),
+    ("-map_chapters", 1),
+    ("-metadata", 1),
+];
+
+/// Encoder parameters that name files the encoder reads or writes
+const FILE_ENCODER_PARAMS: [&str; 8] = ["stats", "qpfile", "csv", "analysis", "zonefile", "recon", "dump", "rpu"];
+
+/// Check advanced FFmpeg options. They go right before the output path, so
+/// only known options with the right number of values are accepted.
+pub fn validate_extra_args(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(&(option, arity)) = EXTRA_ARG_OPTIONS.iter().find(|(option, _)| *option == arg.as_str()) else {
+            return Err(if arg.starts_with('-') {
+                format!("'{}' isn't supported as an extra option", arg)
+            } else {
+                format!("'{}' doesn't follow an option, so FFmpeg would treat it as an output file", arg)
+            });
+        };
+
+        for _ in 0..arity {
+            let value = args.next()
+                .ok_or_else(|| format!("'{}' needs a value", option))?;
+            if option.ends_with("-params") && encoder_params_name_files(value) {
+                return Err(format!("'{}' can't read or write files", option));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether `key=value:key=value` encoder parameters include a file option
+fn encoder_params_name_files(params: &str) -> bool {
+    params.split(':')
+        .filter_map(|param| param.split('=').next())
+        .any(|key| {
+            let key = key.trim().to_ascii_lowercase();
+            key.contains("file") || FILE_ENCODER_PARAMS.iter().any(|file_key| key.contains(file_key))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn accepts_options_and_values() {
+        assert!(validate_extra_args(&args(&["-tune", "film", "-x264-params", "aq-mode=3", "-shortest"])).is_ok());
+        assert!(validate_extra_args(&args(&["-bf", "-1", "-metadata", "title=Trip.mp4"])).is_ok());
+    }
+
+    #[test]
+    fn rejects_stray_outputs_and_reserved_options() {
+        assert!(validate_extra_args(&args(&["extra.mp4"])).is_err());
+        assert!(validate_extra_args(&args(&["-tune", "film", "extra.mp4"])).is_err());
+        assert!(validate_extra_args(&args(&["-an", "second.mp4"])).is_err());
+        assert!(validate_extra_args(&args(&["-shortest", "x.mkv"])).is_err());
+        assert!(validate_extra_args(&args(&["-i", "other.mov"])).is_err());
+        assert!(validate_extra_args(&args(&["-f", "null", "-"])).is_err());
+        assert!(validate_extra_args(&args(&["-tune"])).is_err());
+    }
+
+    #[test]
+    fn rejects_options_that_touch_files() {
+        assert!(validate_extra_args(&args(&["-vstats_file", "/tmp/stats.log"])).is_err());
+        assert!(validate_extra_args(&args(&["-filter_complex", "movie=/etc/passwd"])).is_err());
+        assert!(validate_extra_args(&args(&["-x264-params", "stats=/tmp/x264.log"])).is_err());
+        assert!(validate_extra_args(&args(&["-x265-params", "csv=/tmp/x265.csv:csv-log-level=2"])).is_err());
+    }
+}
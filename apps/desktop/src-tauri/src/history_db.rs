@@ -0,0 +1,326 @@
+use std::path::Path;
+use chrono::{DateTime, Local, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Params};
+use crate::ffmpeg::JobStatus;
+use crate::query::{HistoryFilter, HistoryPage, HistorySort, HistorySortKey, SortDirection};
+use crate::state::ConversionHistory;
+use crate::usage_stats::UsageLog;
+
+/// Each entry is stored whole as JSON in `data`, next to copies of the
+/// fields the history view filters and sorts on. File names are lowercased
+/// here because SQLite's `lower()` only handles ASCII.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS history (
+        id TEXT PRIMARY KEY,
+        completed_at TEXT NOT NULL,
+        preset_name TEXT NOT NULL,
+        input_name TEXT NOT NULL,
+        output_name TEXT NOT NULL,
+        output_path TEXT NOT NULL,
+        file_size_before INTEGER NOT NULL,
+        file_size_after INTEGER NOT NULL,
+        duration REAL NOT NULL,
+        has_problems INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS history_completed_at ON history (completed_at);
+    CREATE INDEX IF NOT EXISTS history_preset ON history (preset_name, completed_at);
+    CREATE INDEX IF NOT EXISTS history_output_path ON history (output_path);
+";
+
+/// Conversion history in an SQLite database in the app data folder.
+/// Entries keep the order they were added in, by rowid.
+pub struct HistoryDb {
+    connection: Connection,
+}
+
+impl HistoryDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let connection = Connection::open(path)
+            .map_err(|e| format!("Failed to open history database: {}", e))?;
+        Self::init(connection)
+    }
+
+    fn init(connection: Connection) -> Result<Self, String> {
+        connection.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to create history tables: {}", e))?;
+        Ok(HistoryDb { connection })
+    }
+
+    pub fn insert(&mut self, item: &ConversionHistory) -> Result<(), String> {
+        self.insert_all(std::slice::from_ref(item))
+    }
+
+    /// Add entries in one transaction. Entries already stored are skipped,
+    /// so an interrupted migration can run again.
+    pub fn insert_all(&mut self, items: &[ConversionHistory]) -> Result<(), String> {
+        let transaction = self.connection.transaction()
+            .map_err(|e| format!("Failed to write history: {}", e))?;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR IGNORE INTO history (id, completed_at, preset_name, input_name, output_name, output_path,
+                    file_size_before, file_size_after, duration, has_problems, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            ).map_err(|e| format!("Failed to write history: {}", e))?;
+            for item in items {
+                let data = serde_json::to_string(item)
+                    .map_err(|e| format!("Failed to serialize history: {}", e))?;
+                statement.execute(params![
+                    item.id,
+                    normalize_date(&item.completed_at).unwrap_or_else(|| item.completed_at.clone()),
+                    item.preset_name,
+                    lowercase_file_name(&item.input_path),
+                    lowercase_file_name(&item.output_path),
+                    item.output_path,
+                    item.file_size_before as i64,
+                    item.file_size_after as i64,
+                    item.duration,
+                    !item.problems.is_empty(),
+                    data,
+                ]).map_err(|e| format!("Failed to write history: {}", e))?;
+            }
+        }
+        transaction.commit()
+            .map_err(|e| format!("Failed to write history: {}", e))
+    }
+
+    /// Every entry, oldest first
+    pub fn all(&self) -> Result<Vec<ConversionHistory>, String> {
+        self.select("SELECT data FROM history ORDER BY rowid", Vec::new())
+    }
+
+    /// Whether a previous conversion wrote to `output_path`
+    pub fn has_output(&self, output_path: &str) -> Result<bool, String> {
+        self.connection
+            .query_row("SELECT EXISTS (SELECT 1 FROM history WHERE output_path = ?1)", params![output_path], |row| row.get(0))
+            .map_err(|e| format!("Failed to read history: {}", e))
+    }
+
+    /// One page of the entries matching `filter`, and how many match in total.
+    /// Ties keep the order the entries were added in.
+    pub fn query(&self, filter: &HistoryFilter, sort: HistorySort, offset: usize, limit: Option<usize>) -> Result<HistoryPage, String> {
+        let (conditions, mut values) = where_clause(filter)?;
+        let total: i64 = self.connection
+            .query_row(&format!("SELECT COUNT(*) FROM history {}", conditions), params_from_iter(values.iter()), |row| row.get(0))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+
+        let order = match sort.by {
+            HistorySortKey::Date => "completed_at",
+            HistorySortKey::SizeSaved => "file_size_before - file_size_after",
+            HistorySortKey::CompressionRatio => {
+                "CASE WHEN file_size_before = 0 THEN 1.0 ELSE CAST(file_size_after AS REAL) / file_size_before END"
+            }
+            HistorySortKey::Duration => "duration",
+        };
+        let direction = match sort.direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        // SQLite takes a negative limit as no limit
+        values.push(Value::Integer(limit.map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX))));
+        values.push(Value::Integer(i64::try_from(offset).unwrap_or(i64::MAX)));
+        let items = self.select(
+            &format!("SELECT data FROM history {} ORDER BY {} {}, rowid LIMIT ? OFFSET ?", conditions, order, direction),
+            values,
+        )?;
+
+        Ok(HistoryPage { items, total: total as usize })
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        self.connection.execute("DELETE FROM history", [])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to clear history: {}", e))
+    }
+
+    /// Drop entries older than `max_age_days`, then the oldest entries beyond
+    /// `max_entries`. Zero turns a limit off. Returns the ids of the dropped
+    /// entries.
+    pub fn prune(&self, max_entries: u32, max_age_days: u32, now: DateTime<Utc>) -> Result<Vec<String>, String> {
+        let mut removed = Vec::new();
+        if max_age_days > 0 {
+            let cutoff = (now - chrono::Duration::days(i64::from(max_age_days))).to_rfc3339();
+            removed.extend(self.delete_returning_ids("DELETE FROM history WHERE completed_at < ?1 RETURNING id", params![cutoff])?);
+        }
+        if max_entries > 0 {
+            removed.extend(self.delete_returning_ids(
+                "DELETE FROM history WHERE rowid NOT IN (SELECT rowid FROM history ORDER BY rowid DESC LIMIT ?1) RETURNING id",
+                params![i64::from(max_entries)],
+            )?);
+        }
+        Ok(removed)
+    }
+
+    fn delete_returning_ids(&self, sql: &str, values: impl Params) -> Result<Vec<String>, String> {
+        let mut statement = self.connection.prepare(sql)
+            .map_err(|e| format!("Failed to prune history: {}", e))?;
+        let ids = statement.query_map(values, |row| row.get(0))
+            .map_err(|e| format!("Failed to prune history: {}", e))?;
+        ids.collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("Failed to prune history: {}", e))
+    }
+
+    /// Per-day totals of every entry, by the local date it finished on
+    pub fn usage(&self) -> Result<UsageLog, String> {
+        let mut statement = self.connection
+            .prepare("SELECT completed_at, file_size_before, file_size_after, duration FROM history")
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?, row.get::<_, f64>(3)?))
+        }).map_err(|e| format!("Failed to read history: {}", e))?;
+
+        let mut usage = UsageLog::default();
+        for row in rows {
+            let (completed_at, size_before, size_after, duration) = row.map_err(|e| format!("Failed to read history: {}", e))?;
+            if let Ok(completed_at) = DateTime::parse_from_rfc3339(&completed_at) {
+                let day = completed_at.with_timezone(&Local).date_naive();
+                usage.record(day, size_before.max(0) as u64, size_after.max(0) as u64, duration);
+            }
+        }
+        Ok(usage)
+    }
+
+    fn select(&self, sql: &str, values: Vec<Value>) -> Result<Vec<ConversionHistory>, String> {
+        let mut statement = self.connection.prepare(sql)
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        let rows = statement.query_map(params_from_iter(values.iter()), |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+
+        let mut items = Vec::new();
+        for data in rows {
+            let data = data.map_err(|e| format!("Failed to read history: {}", e))?;
+            match serde_json::from_str(&data) {
+                Ok(item) => items.push(item),
+                Err(e) => eprintln!("Skipping unreadable history entry: {}", e),
+            }
+        }
+        Ok(items)
+    }
+}
+
+fn where_clause(filter: &HistoryFilter) -> Result<(String, Vec<Value>), String> {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+
+    if !filter.statuses.is_empty() {
+        let completed = filter.statuses.contains(&JobStatus::Completed);
+        let with_warnings = filter.statuses.contains(&JobStatus::CompletedWithWarnings);
+        match (completed, with_warnings) {
+            (true, true) => {}
+            (true, false) => conditions.push("has_problems = 0"),
+            (false, true) => conditions.push("has_problems = 1"),
+            // History only holds finished conversions
+            (false, false) => conditions.push("0"),
+        }
+    }
+    if let Some(preset) = &filter.preset {
+        conditions.push("preset_name = ?");
+        values.push(Value::Text(preset.clone()));
+    }
+    for (bound, condition) in [(&filter.from, "completed_at >= ?"), (&filter.to, "completed_at <= ?")] {
+        if let Some(date) = bound {
+            let date = normalize_date(date).ok_or_else(|| format!("'{}' is not a valid date", date))?;
+            conditions.push(condition);
+            values.push(Value::Text(date));
+        }
+    }
+    if let Some(search) = filter.search.as_deref().map(str::trim).filter(|search| !search.is_empty()) {
+        conditions.push("(instr(input_name, ?) > 0 OR instr(output_name, ?) > 0)");
+        values.push(Value::Text(search.to_lowercase()));
+        values.push(Value::Text(search.to_lowercase()));
+    }
+
+    let clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    Ok((clause, values))
+}
+
+/// RFC 3339 in UTC, like the dates entries are written with, so dates
+/// compare correctly as text
+fn normalize_date(date: &str) -> Option<String> {
+    DateTime::parse_from_rfc3339(date).ok().map(|date| date.with_timezone(&Utc).to_rfc3339())
+}
+
+fn lowercase_file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage_stats::UsagePeriod;
+
+    fn entry(id: &str, preset: &str, completed_at: &str, problems: &[&str]) -> ConversionHistory {
+        ConversionHistory {
+            id: id.to_string(),
+            input_path: format!("/videos/{}.MOV", id),
+            output_path: format!("/videos/converted/{}.mp4", id),
+            preset_name: preset.to_string(),
+            completed_at: completed_at.to_string(),
+            file_size_before: 1000,
+            file_size_after: 400,
+            duration: 10.0,
+            elapsed_seconds: None,
+            speed: None,
+            larger_than_original: false,
+            quality_score: None,
+            problems: problems.iter().map(|problem| problem.to_string()).collect(),
+        }
+    }
+
+    fn database() -> HistoryDb {
+        let mut db = HistoryDb::init(Connection::open_in_memory().unwrap()).unwrap();
+        db.insert_all(&[
+            entry("beach", "Web", "2025-03-01T10:00:00+00:00", &[]),
+            entry("Birthday", "Web", "2025-03-02T10:00:00+01:00", &["The output is 9.0s long, expected 10.0s"]),
+            entry("concert", "Archive", "2025-03-03T10:00:00+00:00", &[]),
+        ]).unwrap();
+        db
+    }
+
+    #[test]
+    fn filters_and_pages_entries() {
+        let db = database();
+        let filter = HistoryFilter { preset: Some("Web".to_string()), ..Default::default() };
+        let page = db.query(&filter, HistorySort::default(), 0, Some(1)).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.items.iter().map(|item| item.id.as_str()).collect::<Vec<_>>(), ["Birthday"]);
+
+        let filter = HistoryFilter { search: Some("birth".to_string()), ..Default::default() };
+        assert_eq!(db.query(&filter, HistorySort::default(), 0, None).unwrap().total, 1);
+
+        let filter = HistoryFilter { statuses: vec![JobStatus::Completed], from: Some("2025-03-02T00:00:00Z".to_string()), ..Default::default() };
+        let page = db.query(&filter, HistorySort::default(), 0, None).unwrap();
+        assert_eq!(page.items.iter().map(|item| item.id.as_str()).collect::<Vec<_>>(), ["concert"]);
+    }
+
+    #[test]
+    fn totals_usage_over_all_entries() {
+        // Days are local, so the entries can land in different buckets
+        let buckets = database().usage().unwrap().buckets(UsagePeriod::Day);
+        assert_eq!(buckets.iter().map(|bucket| bucket.conversions).sum::<u32>(), 3);
+        assert_eq!(buckets.iter().map(|bucket| bucket.bytes_in).sum::<u64>(), 3000);
+        assert_eq!(buckets.iter().map(|bucket| bucket.bytes_out).sum::<u64>(), 1200);
+        assert_eq!(buckets.iter().map(|bucket| bucket.minutes_encoded).sum::<f64>(), 0.5);
+    }
+
+    #[test]
+    fn prunes_oldest_entries_first() {
+        let mut db = database();
+        // Already stored, so ignored
+        db.insert(&entry("beach", "Web", "2025-03-04T10:00:00+00:00", &[])).unwrap();
+        assert_eq!(db.all().unwrap().len(), 3);
+        let now = DateTime::parse_from_rfc3339("2025-03-05T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(db.prune(2, 0, now).unwrap(), ["beach"]);
+        assert!(db.prune(0, 3, now).unwrap().is_empty());
+        assert_eq!(db.prune(0, 2, now).unwrap(), ["Birthday"]);
+        assert_eq!(db.all().unwrap().iter().map(|item| item.id.as_str()).collect::<Vec<_>>(), ["concert"]);
+    }
+}
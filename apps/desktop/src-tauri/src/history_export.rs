@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use crate::state::ConversionHistory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryExportFormat {
+    Csv,
+    Json,
+}
+
+const CSV_COLUMNS: [&str; 10] = [
+    "completed_at",
+    "input_path",
+    "output_path",
+    "preset",
+    "duration_seconds",
+    "file_size_before",
+    "file_size_after",
+    "size_saved",
+    "compression_ratio",
+    "elapsed_seconds",
+];
+
+/// A history entry with the savings worked out, for spreadsheets
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedEntry<'a> {
+    #[serde(flatten)]
+    entry: &'a ConversionHistory,
+    size_saved: i64,
+    compression_ratio: Option<f64>,
+}
+
+impl<'a> ExportedEntry<'a> {
+    fn new(entry: &'a ConversionHistory) -> Self {
+        ExportedEntry {
+            entry,
+            size_saved: entry.file_size_before as i64 - entry.file_size_after as i64,
+            compression_ratio: compression_ratio(entry),
+        }
+    }
+}
+
+/// Output size relative to the input size, e.g. 0.4 for an output 40% the
+/// size of its source. None for empty sources.
+fn compression_ratio(entry: &ConversionHistory) -> Option<f64> {
+    (entry.file_size_before > 0).then(|| {
+        let ratio = entry.file_size_after as f64 / entry.file_size_before as f64;
+        (ratio * 10_000.0).round() / 10_000.0
+    })
+}
+
+/// The entries in `format`, oldest first like they are passed in
+pub fn export_history(entries: &[ConversionHistory], format: HistoryExportFormat) -> Result<String, String> {
+    match format {
+        HistoryExportFormat::Json => {
+            let exported: Vec<ExportedEntry> = entries.iter().map(ExportedEntry::new).collect();
+            serde_json::to_string_pretty(&exported)
+                .map_err(|e| format!("Failed to serialize history: {}", e))
+        }
+        HistoryExportFormat::Csv => Ok(history_csv(entries)),
+    }
+}
+
+fn history_csv(entries: &[ConversionHistory]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for entry in entries {
+        let exported = ExportedEntry::new(entry);
+        let fields = [
+            entry.completed_at.clone(),
+            entry.input_path.clone(),
+            entry.output_path.clone(),
+            entry.preset_name.clone(),
+            entry.duration.to_string(),
+            entry.file_size_before.to_string(),
+            entry.file_size_after.to_string(),
+            exported.size_saved.to_string(),
+            exported.compression_ratio.map(|ratio| ratio.to_string()).unwrap_or_default(),
+            entry.elapsed_seconds.map(|seconds| format!("{:.1}", seconds)).unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(String::as_str).map(csv_field).collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Quote fields with separators, quotes or line breaks (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> ConversionHistory {
+        ConversionHistory {
+            id: "1".to_string(),
+            input_path: "/videos/Trip, day 1.mov".to_string(),
+            output_path: "/videos/converted/Trip, day 1.mp4".to_string(),
+            preset_name: "Web".to_string(),
+            completed_at: "2025-03-01T10:00:00+00:00".to_string(),
+            file_size_before: 2000,
+            file_size_after: 500,
+            duration: 12.5,
+            elapsed_seconds: Some(4.3),
+            speed: None,
+            larger_than_original: false,
+            quality_score: None,
+            problems: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn writes_quoted_csv_rows() {
+        let csv = export_history(&[entry()], HistoryExportFormat::Csv).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], CSV_COLUMNS.join(","));
+        assert_eq!(
+            rows[1],
+            "2025-03-01T10:00:00+00:00,\"/videos/Trip, day 1.mov\",\"/videos/converted/Trip, day 1.mp4\",Web,12.5,2000,500,1500,0.25,4.3"
+        );
+    }
+
+    #[test]
+    fn adds_savings_to_json() {
+        let json = export_history(&[entry()], HistoryExportFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["presetName"], "Web");
+        assert_eq!(value[0]["sizeSaved"], 1500);
+        assert_eq!(value[0]["compressionRatio"], 0.25);
+    }
+}
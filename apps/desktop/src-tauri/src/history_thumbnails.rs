@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// Thumbnails kept for history entries; the ones shown least recently go first
+const MAX_HISTORY_THUMBNAILS: usize = 500;
+
+/// Copies of job thumbnails, kept after the jobs themselves are cleared
+pub fn history_thumbnail_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle.path().app_cache_dir()
+        .map(|dir| dir.join("history-thumbs"))
+        .map_err(|e| format!("Failed to get cache dir: {}", e))
+}
+
+/// Keep a job's thumbnail for its history entry
+pub fn save_history_thumbnail(app_handle: &AppHandle, history_id: &str, job_thumbnail: &str) -> Result<(), String> {
+    let dir = history_thumbnail_dir(app_handle)?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create history thumbnail directory: {}", e))?;
+    fs::copy(job_thumbnail, dir.join(format!("{}.jpg", history_id)))
+        .map_err(|e| format!("Failed to copy thumbnail: {}", e))?;
+    evict_least_recently_used(&dir, MAX_HISTORY_THUMBNAILS);
+    Ok(())
+}
+
+/// The thumbnail of a history entry, if it has one. Marks it as used so
+/// eviction keeps it longer.
+pub fn history_thumbnail_path(app_handle: &AppHandle, history_id: &str) -> Result<Option<PathBuf>, String> {
+    // The id becomes part of a path, so only accept real history ids
+    Uuid::parse_str(history_id).map_err(|_| "Invalid history id".to_string())?;
+    let path = history_thumbnail_dir(app_handle)?.join(format!("{}.jpg", history_id));
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    if let Err(e) = fs::File::options().write(true).open(&path).and_then(|file| file.set_modified(SystemTime::now())) {
+        eprintln!("Failed to mark thumbnail {} as used: {}", path.display(), e);
+    }
+    Ok(Some(path))
+}
+
+/// Delete every history thumbnail, e.g. when the history is cleared
+pub fn clear_history_thumbnails(app_handle: &AppHandle) -> Result<(), String> {
+    let dir = history_thumbnail_dir(app_handle)?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir)
+            .map_err(|e| format!("Failed to delete history thumbnails: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Delete the thumbnails of history entries that were removed
+pub fn delete_history_thumbnails(app_handle: &AppHandle, history_ids: &[String]) {
+    if history_ids.is_empty() {
+        return;
+    }
+    let Ok(dir) = history_thumbnail_dir(app_handle) else {
+        return;
+    };
+    // Only real history ids, as they become part of a path
+    for history_id in history_ids.iter().filter(|history_id| Uuid::parse_str(history_id).is_ok()) {
+        let path = dir.join(format!("{}.jpg", history_id));
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to delete thumbnail {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Delete the files in `dir` beyond the `keep` most recently modified
+fn evict_least_recently_used(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if files.len() <= keep {
+        return;
+    }
+
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    for (_, path) in files.drain(keep..) {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Failed to evict thumbnail {}: {}", path.display(), e);
+        }
+    }
+}
@@ -1,199 +1,125 @@
+mod batch;
+mod bookmarks;
+mod cache;
+mod environment;
+mod error;
+mod estimate;
+mod events;
 mod ffmpeg;
+mod ffmpeg_download;
 mod ffmpeg_parser;
 mod ffmpeg_version;
+mod filters;
+mod history_db;
+mod history_export;
+mod history_thumbnails;
 mod logger;
+mod media_info;
+mod output_locations;
+mod output_name;
+mod path_scope;
+mod pipeline;
+mod post_queue;
+mod preset_schema;
+mod preset_share;
+mod quality;
+mod query;
+mod queue_file;
+mod script;
+mod self_test;
 mod state;
+mod status_message;
+mod support_bundle;
+mod system_info;
+mod thumbnail_protocol;
+mod updates;
+mod usage_stats;
+mod video_protocol;
+mod watcher;
+mod work_dir;
 
-use ffmpeg::{ConversionJob, VideoPreset, JobStatus, convert_video, generate_thumbnail, get_ffmpeg_binary};
-use state::{AppState, ConversionHistory, AppSettings};
+use error::ErrorCode;
+use events::{emit_conversion_progress, emit_job_state, emit_jobs_removed, JobChange};
+use ffmpeg::{ConversionJob, FrameFormat, JobOptions, VideoPreset, JobStatus, convert_video, generate_thumbnail, get_ffmpeg_binary};
+use quality::{QualityMetric, QualityScore};
+use post_queue::PostQueueAction;
+use queue_file::{QueueFile, QueueImport};
+use script::{CommandPreview, ScriptCommand, Shell};
+use status_message::StatusMessage;
+use watcher::WatchFolder;
+use state::{AppState, ConversionHistory, AppSettings, LargerOutputPolicy, OutputConflictPolicy, ProgressSample, QueueProgress, QueueStatus};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 use tauri::{AppHandle, Manager, Emitter};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_fs::FsExt;
 use uuid::Uuid;
 use chrono::Utc;
 use base64::{Engine as _, engine::general_purpose};
 use std::process::Command;
-use std::sync::atomic::{AtomicBool, Ordering};
-
-static QUEUE_PROCESSOR_RUNNING: AtomicBool = AtomicBool::new(false);
+use tokio::sync::watch;
 
 async fn start_queue_processor_if_needed(app_handle: AppHandle, state: AppState) {
-    if QUEUE_PROCESSOR_RUNNING.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
-        println!("Starting queue processor for subsequent jobs (first job processes immediately)");
-        tauri::async_runtime::spawn(async move {
-            loop {
-                // Check if there's a ready job to convert (has been analyzed)
-                if let Some(job_id) = state.get_next_ready_job().await {
-                    // Check if any job is currently processing conversion
-                    if !state.is_any_job_processing().await {
-                        println!("Converting next job from queue: {}", job_id);
-                        convert_job(app_handle.clone(), state.clone(), job_id).await;
-                    }
-                }
-                
-                // Wait a bit before checking again
-                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                
-                // Check if there are any jobs left to process
-                let has_ready_jobs = state.get_next_ready_job().await.is_some();
-                let has_queued_jobs = state.get_next_queued_job().await.is_some();
-                if !has_ready_jobs && !has_queued_jobs {
-                    println!("No more jobs in queue, stopping processor");
-                    QUEUE_PROCESSOR_RUNNING.store(false, Ordering::SeqCst);
-                    break;
-                }
-            }
-        });
+    let worker_state = state.clone();
+    let started = state.start_queue_worker(move |stop| {
+        tauri::async_runtime::spawn(run_queue_processor(app_handle, worker_state, stop))
+    }).await;
+    if started {
+        println!("Started queue processor");
     }
 }
 
-async fn start_priority_processing(app_handle: AppHandle, state: AppState, job_id: String, input_path: String) {
-    tauri::async_runtime::spawn(async move {
-        println!("Starting priority processing (analyze + convert) for first job: {}", &job_id);
-        
-        // First, do the analysis
-        if let Ok(ffmpeg_path) = get_ffmpeg_binary(&app_handle) {
-            println!("Got FFmpeg path: {:?}", ffmpeg_path);
-            if let Ok(duration) = ffmpeg::get_video_duration(&ffmpeg_path, &input_path).await {
-                println!("Got video duration: {}", duration);
-                if let Some(mut job) = state.get_job(&job_id).await {
-                    job.duration = Some(duration);
-                    
-                    // Generate thumbnail
-                    let thumbnail_dir = app_handle.path().app_cache_dir()
-                        .expect("Failed to get cache dir")
-                        .join("thumbnails");
-                    
-                    if !thumbnail_dir.exists() {
-                        let _ = fs::create_dir_all(&thumbnail_dir);
-                    }
-                    
-                    let thumbnail_path = thumbnail_dir.join(format!("{}.jpg", &job_id));
-                    let thumbnail_path_str = thumbnail_path.to_string_lossy().to_string();
-                    let time_offset = format!("{}", duration * 0.1);
-                    
-                    match generate_thumbnail(&ffmpeg_path, &input_path, &thumbnail_path_str, &time_offset).await {
-                        Ok(()) => {
-                            println!("Thumbnail generated successfully for priority job");
-                            job.thumbnail_path = Some(thumbnail_path_str.clone());
-                        }
-                        Err(e) => {
-                            println!("Failed to generate thumbnail for priority job: {}", e);
-                        }
-                    }
-                    
-                    // Set to Ready first
-                    job.status = JobStatus::Ready;
-                    job.status_message = Some("Ready to convert".to_string());
-                    state.update_job(job.clone()).await;
-                    let _ = app_handle.emit("job-updated", &job_id);
-                    
-                    // Immediately start conversion
-                    println!("Starting immediate conversion for priority job: {}", job_id);
-                    convert_job(app_handle.clone(), state.clone(), job_id.clone()).await;
-                }
-            } else {
-                println!("Failed to get video duration for priority job, converting anyway");
-                if let Some(mut job) = state.get_job(&job_id).await {
-                    job.status = JobStatus::Ready;
-                    job.status_message = Some("Ready to convert".to_string());
-                    state.update_job(job.clone()).await;
-                    let _ = app_handle.emit("job-updated", &job_id);
-                    
-                    // Start conversion even without duration
-                    convert_job(app_handle.clone(), state.clone(), job_id.clone()).await;
-                }
-            }
-        } else {
-            println!("Failed to get FFmpeg binary for priority job, converting anyway");
-            if let Some(mut job) = state.get_job(&job_id).await {
-                job.status = JobStatus::Ready;
-                job.status_message = Some("Ready to convert".to_string());
-                state.update_job(job.clone()).await;
-                let _ = app_handle.emit("job-updated", &job_id);
-                
-                // Start conversion even without analysis
-                convert_job(app_handle.clone(), state.clone(), job_id.clone()).await;
+async fn run_queue_processor(app_handle: AppHandle, state: AppState, mut stop: watch::Receiver<bool>) {
+    // Outputs converted since the queue was last idle
+    let mut run_outputs = Vec::new();
+    while !*stop.borrow() {
+        // Convert ready jobs one at a time, in queue order
+        if let Some(job_id) = state.claim_next_ready_job().await {
+            println!("Converting next job from queue: {}", job_id);
+            convert_job(app_handle.clone(), state.clone(), job_id.clone()).await;
+            let converted = |job: &ConversionJob| matches!(job.status, JobStatus::Completed | JobStatus::CompletedWithWarnings)
+                && job.status_message != Some(StatusMessage::SkippedLargerThanOriginal);
+            if let Some(job) = state.get_job(&job_id).await.filter(converted) {
+                run_outputs.push(job.output_path);
             }
+            archive_finished_jobs(&app_handle, &state).await;
+            continue;
         }
-    });
-}
-
-async fn start_preprocessing(app_handle: AppHandle, state: AppState, job_id: String, input_path: String) {
-    tauri::async_runtime::spawn(async move {
-        println!("Starting preprocessing for job: {}", &job_id);
-        
-        if let Ok(ffmpeg_path) = get_ffmpeg_binary(&app_handle) {
-            println!("Got FFmpeg path: {:?}", ffmpeg_path);
-            if let Ok(duration) = ffmpeg::get_video_duration(&ffmpeg_path, &input_path).await {
-                println!("Got video duration: {}", duration);
-                if let Some(mut job) = state.get_job(&job_id).await {
-                    job.duration = Some(duration);
-                    
-                    // Generate thumbnail
-                    let thumbnail_dir = app_handle.path().app_cache_dir()
-                        .expect("Failed to get cache dir")
-                        .join("thumbnails");
-                    
-                    println!("Thumbnail directory: {:?}", thumbnail_dir);
-                    
-                    // Create thumbnails directory if it doesn't exist
-                    if !thumbnail_dir.exists() {
-                        println!("Creating thumbnail directory");
-                        let _ = fs::create_dir_all(&thumbnail_dir);
-                    }
-                    
-                    let thumbnail_path = thumbnail_dir.join(format!("{}.jpg", &job_id));
-                    let thumbnail_path_str = thumbnail_path.to_string_lossy().to_string();
-                    
-                    // Generate thumbnail at 10% of video duration
-                    let time_offset = format!("{}", duration * 0.1);
-                    
-                    println!("Generating thumbnail at path: {}", &thumbnail_path_str);
-                    println!("Time offset: {}", &time_offset);
-                    
-                    match generate_thumbnail(&ffmpeg_path, &input_path, &thumbnail_path_str, &time_offset).await {
-                        Ok(()) => {
-                            println!("Thumbnail generated successfully");
-                            job.thumbnail_path = Some(thumbnail_path_str.clone());
-                            println!("Job thumbnail_path set to: {:?}", job.thumbnail_path);
-                        }
-                        Err(e) => {
-                            println!("Failed to generate thumbnail: {}", e);
-                        }
-                    }
-                    
-                    // Only update status if job is still queued
-                    if matches!(job.status, JobStatus::Queued) {
-                        job.status = JobStatus::Ready;
-                        job.status_message = Some("Ready to convert".to_string());
-                    }
-                    state.update_job(job.clone()).await;
-                    println!("Job updated with thumbnail_path: {:?}", job.thumbnail_path);
-                    let _ = app_handle.emit("job-updated", &job_id);
-                }
-            } else {
-                println!("Failed to get video duration, setting job to ready anyway");
-                if let Some(mut job) = state.get_job(&job_id).await {
-                    if matches!(job.status, JobStatus::Queued) {
-                        job.status = JobStatus::Ready;
-                        job.status_message = Some("Ready to convert".to_string());
-                        state.update_job(job.clone()).await;
-                        let _ = app_handle.emit("job-updated", &job_id);
-                    }
-                }
-            }
-        } else {
-            println!("Failed to get FFmpeg binary, setting job to ready anyway");
-            if let Some(mut job) = state.get_job(&job_id).await {
-                if matches!(job.status, JobStatus::Queued) {
-                    job.status = JobStatus::Ready;
-                    job.status_message = Some("Ready to convert".to_string());
-                    state.update_job(job.clone()).await;
-                    let _ = app_handle.emit("job-updated", &job_id);
+
+        if !run_outputs.is_empty() && !state.queue_status().await.is_busy() {
+            let outputs = std::mem::take(&mut run_outputs);
+            let action = state.get_settings().await.post_queue_action;
+            println!("Queue finished with {} output(s), running {:?}", outputs.len(), action);
+            // Scripts may run for a while; new jobs shouldn't wait for them
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = post_queue::run(&action, &outputs).await {
+                    eprintln!("Post-queue action failed: {}", e);
                 }
-            }
+            });
+        }
+
+        // Sleep until a job is analyzed or the worker is stopped
+        tokio::select! {
+            _ = state.queue_wakeup.notified() => {}
+            _ = stop.changed() => {}
         }
-    });
+    }
+    println!("Queue processor stopped");
+}
+
+/// Analyze jobs that are waiting in the queue and make sure the processor
+/// picks them up, e.g. after restoring the queue or requeueing failed jobs.
+async fn resume_queue(app_handle: AppHandle, state: AppState) {
+    for job in state.get_all_jobs().await {
+        if matches!(job.status, JobStatus::Queued) {
+            pipeline::start_analysis(app_handle.clone(), state.clone(), job.id, job.input_path).await;
+        }
+    }
+    start_queue_processor_if_needed(app_handle, state.clone()).await;
+    state.wake_queue();
 }
 
 async fn convert_job(app_handle: AppHandle, state: AppState, job_id: String) {
@@ -208,73 +134,360 @@ async fn convert_job(app_handle: AppHandle, state: AppState, job_id: String) {
     
     println!("Starting conversion for job: {}", job_id);
     
-    // Update status to processing
-    state.update_job_status(&job_id, JobStatus::Processing).await;
-    
-    // Update to "Converting video..." 
     println!("Setting status to 'Converting video...' for job {}", &job_id);
-    state.update_job_status_message(&job_id, "Converting video...".to_string()).await;
+    state.update_job_status_message(&job_id, StatusMessage::Converting).await;
     
     // Emit and log the event
-    match app_handle.emit("job-updated", &job_id) {
-        Ok(_) => println!("Successfully emitted job-updated event for 'Converting video...'"),
-        Err(e) => println!("ERROR: Failed to emit job-updated event: {}", e),
-    }
-    
-    // Small delay to ensure UI updates
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
     
     // Get the updated job with the new status message
-    let job_with_duration = state.get_job(&job_id).await.unwrap_or(job.clone());
+    let mut job_with_duration = state.get_job(&job_id).await.unwrap_or(job.clone());
     
+    let settings = state.get_settings().await;
+
+    // Settle a clash with an existing file before FFmpeg gets to overwrite it
+    match resolve_output_conflict(&state, &job_with_duration, settings.on_conflict).await {
+        Ok(output_path) if output_path != job_with_duration.output_path => {
+            println!("Output {} exists, writing {} instead", job_with_duration.output_path, output_path);
+            job_with_duration.output_path = output_path;
+            state.update_job(job_with_duration.clone()).await;
+            emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            job_with_duration.status = JobStatus::Failed;
+            job_with_duration.error = Some(e);
+            job_with_duration.error_code = Some(ErrorCode::Other);
+            state.update_job(job_with_duration).await;
+            emit_job_state(&app_handle, &state, &job_id, JobChange::Failed).await;
+            return;
+        }
+    }
+    let cancel = state.begin_conversion(&job_id).await;
+    // Cancelled after it was claimed but before FFmpeg could be stopped
+    if !state.get_job(&job_id).await.is_some_and(|job| matches!(job.status, JobStatus::Processing)) {
+        state.end_conversion(&job_id).await;
+        println!("Job {} was cancelled before converting", job_id);
+        return;
+    }
     let state_clone = state.clone();
     let app_handle_clone = app_handle.clone();
+    let usage_state = state.clone();
+    let started_at = std::time::Instant::now();
+    let usage_app_handle = app_handle.clone();
+    let mut ffmpeg_log = VecDeque::with_capacity(ffmpeg::STDERR_TAIL_LINES);
+    let larger_output = settings.larger_output;
+    let verify_outputs = settings.verify_outputs;
+    let quality_metric = settings.quality_metric;
+    let auto_crop = settings.auto_crop;
     let result = convert_video(
         app_handle.clone(),
         job_with_duration.clone(),
-        move |id, progress| {
+        settings,
+        move |id, update| {
             let state = state_clone.clone();
             let app = app_handle_clone.clone();
+            let time = started_at.elapsed().as_secs_f64();
+            emit_conversion_progress(&app, &id, update);
             tauri::async_runtime::spawn(async move {
                 // Update progress
-                state.update_job_progress(&id, progress).await;
+                state.update_job_progress(&id, update.percent).await;
+                if update.fps.is_some() || update.speed.is_some() {
+                    let sample = ProgressSample { time, fps: update.fps, speed: update.speed };
+                    state.record_progress_sample(&id, sample).await;
+                }
                 
-                // Only emit progress event, don't override status message
-                let _ = app.emit("conversion-progress", (id.clone(), progress));
+                emit_job_state(&app, &state, &id, JobChange::Progress).await;
+            });
+        },
+        move |id, usage| {
+            let state = usage_state.clone();
+            let app = usage_app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                state.update_job_resource_usage(&id, usage).await;
+                emit_job_state(&app, &state, &id, JobChange::Progress).await;
             });
         },
+        cancel.clone(),
+        &mut ffmpeg_log,
     ).await;
 
     match result {
-        Ok(_) => {
-            state.update_job_status(&job_id, JobStatus::Completed).await;
+        Ok(stats) => {
+            let sizes = fs::metadata(&job_with_duration.input_path)
+                .and_then(|input| Ok((input.len(), fs::metadata(&job_with_duration.output_path)?.len())));
+            let larger_than_original = sizes.as_ref().is_ok_and(|(before, after)| after > before);
+            let skipped = larger_than_original && larger_output == LargerOutputPolicy::DeleteOutput;
+            if skipped {
+                println!("Output of job {} is larger than the original, deleting it", job_id);
+                if let Err(e) = fs::remove_file(&job_with_duration.output_path) {
+                    eprintln!("Failed to delete larger output {}: {}", job_with_duration.output_path, e);
+                }
+            }
+
+            // The job stays the active conversion while its output is checked,
+            // so it can still be cancelled
+            let mut cancel = cancel;
+            let checks = async {
+                let problems = if verify_outputs && !skipped {
+                    verify_conversion(&app_handle, &job_with_duration).await
+                } else {
+                    Vec::new()
+                };
+                let quality_score = if skipped {
+                    None
+                } else {
+                    score_conversion(&app_handle, &job_with_duration, quality_metric, auto_crop).await
+                };
+                (problems, quality_score)
+            };
+            let checked = unless_cancelled(&mut cancel, checks).await;
+            state.end_conversion(&job_id).await;
+
+            let completed = match checked {
+                Some((problems, quality_score)) => state.complete_job(&job_id, |job| {
+                    job.status = if problems.is_empty() {
+                        JobStatus::Completed
+                    } else {
+                        println!("Verifying the output of job {} found problems: {:?}", job_id, problems);
+                        JobStatus::CompletedWithWarnings
+                    };
+                    job.warnings.extend(problems.iter().cloned());
+                    job.quality_score = quality_score;
+                    if skipped {
+                        job.status_message = Some(StatusMessage::SkippedLargerThanOriginal);
+                    }
+                    job.encode_stats = Some(stats);
+                    job.resource_usage = None;
+                    job.ffmpeg_log = ffmpeg_log.into();
+                }).await.then_some((problems, quality_score)),
+                None => None,
+            };
+            let Some((problems, quality_score)) = completed else {
+                println!("Job {} was cancelled while its output was checked", job_id);
+                emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+                return;
+            };
             
             // Add to history
-            if let (Ok(input_metadata), Ok(output_metadata)) = (
-                fs::metadata(&job_with_duration.input_path),
-                fs::metadata(&job_with_duration.output_path)
-            ) {
+            if let (false, Ok((file_size_before, file_size_after))) = (skipped, sizes) {
                 let history_item = ConversionHistory {
                     id: Uuid::new_v4().to_string(),
                     input_path: job_with_duration.input_path.clone(),
                     output_path: job_with_duration.output_path.clone(),
                     preset_name: job_with_duration.preset.name.clone(),
                     completed_at: Utc::now().to_rfc3339(),
-                    file_size_before: input_metadata.len(),
-                    file_size_after: output_metadata.len(),
+                    file_size_before,
+                    file_size_after,
                     duration: job_with_duration.duration.unwrap_or(0.0),
+                    elapsed_seconds: Some(stats.elapsed_seconds),
+                    speed: stats.speed,
+                    larger_than_original: larger_than_original && larger_output == LargerOutputPolicy::FlagInHistory,
+                    quality_score,
+                    problems,
                 };
+                if let Some(thumbnail) = &job_with_duration.thumbnail_path {
+                    if let Err(e) = history_thumbnails::save_history_thumbnail(&app_handle, &history_item.id, thumbnail) {
+                        eprintln!("Failed to keep thumbnail for history: {}", e);
+                    }
+                }
                 let _ = state.add_to_history(&app_handle, history_item).await;
             }
             
-            let _ = app_handle.emit("conversion-complete", &job_id);
+            emit_job_state(&app_handle, &state, &job_id, JobChange::Completed).await;
+            
+            // Refine ETAs of the jobs still waiting with what this one taught us
+            if let Some(speed) = stats.speed {
+                if let Err(e) = state.record_throughput(&app_handle, &job_with_duration.preset.name, job_with_duration.height, speed).await {
+                    eprintln!("Failed to save throughput: {}", e);
+                }
+                refresh_estimates(&app_handle, &state).await;
+            }
         }
         Err(e) => {
-            let mut job = state.get_job(&job_id).await.unwrap();
+            state.end_conversion(&job_id).await;
+            // Removed while converting
+            let Some(mut job) = state.get_job(&job_id).await else {
+                println!("Conversion stopped for removed job {}", job_id);
+                return;
+            };
+            job.resource_usage = None;
+            job.ffmpeg_log = ffmpeg_log.into();
+            if matches!(job.status, JobStatus::Cancelled) {
+                state.update_job(job).await;
+                println!("Conversion cancelled for job {}", job_id);
+                emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+                return;
+            }
             job.status = JobStatus::Failed;
-            job.error = Some(e);
+            job.error_code = Some(e.code());
+            job.error = Some(e.to_string());
+            state.update_job(job).await;
+            emit_job_state(&app_handle, &state, &job_id, JobChange::Failed).await;
+        }
+    }
+}
+
+/// Output path for `input_path` under the current output settings, or under
+/// `pattern` while the user is still editing it. `number` and `total` are the
+/// file's index in a batch and the batch size. The input is only probed when
+/// the pattern needs its resolution or the codec it's copied with.
+async fn output_path_for_input(
+    app_handle: &AppHandle,
+    state: &AppState,
+    input_path: &str,
+    preset: &VideoPreset,
+    number: Option<u32>,
+    total: Option<u32>,
+    pattern: Option<&str>,
+) -> String {
+    let settings = state.get_settings().await;
+    let pattern = pattern.unwrap_or(&settings.file_name_pattern);
+
+    let needs_probe = pattern.contains("{resolution}") || (pattern.contains("{codec}") && preset.is_remux());
+    let probe = match get_ffmpeg_binary(app_handle) {
+        Ok(ffmpeg_path) if needs_probe && Path::new(input_path).is_file() => {
+            ffmpeg::probe_video(&ffmpeg_path, input_path).await.ok()
+        }
+        _ => None,
+    };
+    let resolution = probe.as_ref()
+        .and_then(|probe| Some((probe.width?, probe.height?)))
+        .map(|(width, height)| format!("{}p", estimate::scaled_size(preset.scale.as_deref(), width, height).1));
+    let codec = match probe.as_ref().and_then(|probe| probe.video_codec.as_deref()) {
+        Some(source_codec) if preset.is_remux() => source_codec,
+        _ => preset.codec_name(),
+    };
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let taken: Vec<String> = state.get_all_jobs().await
+        .into_iter()
+        .map(|job| job.output_path)
+        .chain(state.get_history().await.into_iter().map(|item| item.output_path))
+        .collect();
+    let layout = output_name::OutputLayout {
+        output_directory: &settings.output_directory,
+        subdirectory: settings.use_subdirectory.then_some(settings.subdirectory_name.as_str()),
+        pattern,
+        extension: preset.container(),
+        first_number: number.unwrap_or(0),
+        number_width: total.map_or(1, |total| total.saturating_sub(1).to_string().len()),
+    };
+    let values = output_name::PatternValues {
+        preset: &preset.name,
+        date: &date,
+        resolution: resolution.as_deref(),
+        codec,
+    };
+    output_name::output_path_for(Path::new(input_path), &layout, &values, |candidate| {
+        candidate.exists() || taken.iter().any(|path| Path::new(path) == candidate)
+    })
+    .to_string_lossy()
+    .to_string()
+}
+
+/// Output path a file would get with `preset`. The frontend uses it for new
+/// jobs and the pattern preview, so both match what the backend does.
+#[tauri::command]
+async fn resolve_output_path(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    input_path: String,
+    preset: VideoPreset,
+    number: Option<u32>,
+    total: Option<u32>,
+    pattern: Option<String>,
+) -> Result<String, String> {
+    Ok(output_path_for_input(&app_handle, &state, &input_path, &preset, number, total, pattern.as_deref()).await)
+}
+
+/// The path a job should write to under the conflict policy, or an error
+/// when the existing file has to be kept.
+async fn resolve_output_conflict(state: &AppState, job: &ConversionJob, policy: OutputConflictPolicy) -> Result<String, String> {
+    let output_path = job.output_path.replace('\u{00A0}', " ");
+    if !Path::new(&output_path).exists() {
+        return Ok(job.output_path.clone());
+    }
+
+    match policy {
+        OutputConflictPolicy::Overwrite => Ok(job.output_path.clone()),
+        OutputConflictPolicy::Skip => Err(format!("{} already exists", output_path)),
+        OutputConflictPolicy::AutoRename => {
+            // Other jobs may be about to write the next free name
+            let other_outputs: Vec<String> = state.get_all_jobs().await
+                .into_iter()
+                .filter(|other| other.id != job.id)
+                .map(|other| other.output_path)
+                .collect();
+            Ok(output_name::numbered_output_path(&output_path, |candidate| {
+                candidate.exists() || other_outputs.iter().any(|other| Path::new(other) == candidate)
+            }))
+        }
+    }
+}
+
+/// Problems found by decoding a job's finished output again
+async fn verify_conversion(app_handle: &AppHandle, job: &ConversionJob) -> Vec<String> {
+    let ffmpeg_path = match get_ffmpeg_binary(app_handle) {
+        Ok(path) => path,
+        Err(e) => return vec![format!("Failed to verify the output: {}", e)],
+    };
+    // Animated WebP has no duration FFmpeg can read back
+    let expected_duration = if job.preset.is_animation() {
+        None
+    } else {
+        estimate::output_duration(job)
+    };
+    let output_path = job.output_path.replace('\u{00A0}', " ");
+    ffmpeg::verify_output(&ffmpeg_path, &output_path, expected_duration).await
+        .unwrap_or_else(|e| vec![format!("Failed to verify the output: {}", e)])
+}
+
+/// Run a check of a finished output unless the job is cancelled first.
+/// FFmpeg is killed on drop, so a cancelled check stops right away.
+async fn unless_cancelled<T>(cancel: &mut watch::Receiver<bool>, check: impl std::future::Future<Output = T>) -> Option<T> {
+    if *cancel.borrow() {
+        return None;
+    }
+    tokio::select! {
+        result = check => Some(result),
+        Ok(_) = cancel.wait_for(|cancelled| *cancelled) => None,
+    }
+}
+
+/// Quality of a job's finished output compared with its source, when the
+/// setting asks for it and the output can be compared
+async fn score_conversion(app_handle: &AppHandle, job: &ConversionJob, metric: QualityMetric, auto_crop: bool) -> Option<QualityScore> {
+    if metric == QualityMetric::Off {
+        return None;
+    }
+    let output_path = job.output_path.replace('\u{00A0}', " ");
+    let result = match get_ffmpeg_binary(app_handle) {
+        Ok(ffmpeg_path) => quality::score_output(&ffmpeg_path, job, &output_path, metric, auto_crop).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(score) => {
+            if let Some(score) = score {
+                println!("Quality of job {}: {:?} {:.4}", job.id, score.metric, score.score);
+            }
+            score
+        }
+        Err(e) => {
+            eprintln!("Failed to score the quality of job {}: {}", job.id, e);
+            None
+        }
+    }
+}
+
+async fn refresh_estimates(app_handle: &AppHandle, state: &AppState) {
+    let throughput = state.get_throughput().await;
+    for mut job in state.get_all_jobs().await {
+        if matches!(job.status, JobStatus::Queued | JobStatus::Ready) {
+            job.estimate = estimate::estimate_output(&job, &throughput);
+            let job_id = job.id.clone();
             state.update_job(job).await;
-            let _ = app_handle.emit("conversion-failed", &job_id);
+            emit_job_state(app_handle, state, &job_id, JobChange::Updated).await;
         }
     }
 }
@@ -284,6 +497,51 @@ async fn get_video_presets() -> Vec<VideoPreset> {
     VideoPreset::get_presets()
 }
 
+/// Built-in presets followed by the ones the user made.
+#[tauri::command]
+async fn list_presets(state: tauri::State<'_, AppState>) -> Result<Vec<VideoPreset>, String> {
+    Ok(state.list_presets().await)
+}
+
+#[tauri::command]
+async fn create_preset(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    preset: VideoPreset,
+) -> Result<(), String> {
+    state.create_preset(&app_handle, preset).await
+}
+
+/// Replace the user preset called `name`, which may rename it.
+#[tauri::command]
+async fn update_preset(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    name: String,
+    preset: VideoPreset,
+) -> Result<(), String> {
+    state.update_preset(&app_handle, &name, preset).await
+}
+
+#[tauri::command]
+async fn delete_preset(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<(), String> {
+    state.delete_preset(&app_handle, &name).await
+}
+
+#[tauri::command]
+async fn encode_preset_string(preset: VideoPreset) -> Result<String, String> {
+    preset_share::encode_preset(&preset)
+}
+
+#[tauri::command]
+async fn decode_preset_string(value: String) -> Result<VideoPreset, String> {
+    preset_share::decode_preset(&value)
+}
+
 #[tauri::command]
 async fn add_conversion_job(
     app_handle: AppHandle,
@@ -291,47 +549,238 @@ async fn add_conversion_job(
     input_path: String,
     output_path: String,
     preset: VideoPreset,
+    options: Option<JobOptions>,
+    batch_id: Option<String>,
 ) -> Result<String, String> {
     println!("add_conversion_job called with:");
     println!("  input_path: {}", input_path);
     println!("  output_path: {}", output_path);
     println!("  preset: {:?}", preset);
-    
+
+    path_scope::ensure_allowed(&app_handle, &state, &input_path).await?;
+    path_scope::ensure_allowed(&app_handle, &state, &output_path).await?;
+    enqueue_job(&app_handle, &state, input_path, output_path, preset, options.unwrap_or_default(), batch_id).await
+}
+
+/// Add a job to the queue and start analyzing it.
+async fn enqueue_job(
+    app_handle: &AppHandle,
+    state: &AppState,
+    input_path: String,
+    output_path: String,
+    preset: VideoPreset,
+    options: JobOptions,
+    batch_id: Option<String>,
+) -> Result<String, String> {
     let job_id = Uuid::new_v4().to_string();
+    let job = new_job(job_id.clone(), input_path.clone(), output_path, preset, options, batch_id);
+    filters::validate_job_options(&job)?;
+
+    state.add_job(job.clone()).await;
+    emit_job_state(app_handle, state, &job.id, JobChange::Updated).await;
+
+    pipeline::start_analysis(app_handle.clone(), state.clone(), job_id.clone(), input_path).await;
     
-    let job = ConversionJob {
-        id: job_id.clone(),
-        input_path: input_path.clone(),
-        output_path: output_path.clone(),
-        preset: preset.clone(),
+    // Start the queue processor if it's not already running
+    start_queue_processor_if_needed(app_handle.clone(), state.clone()).await;
+
+    Ok(job_id)
+}
+
+/// A new job waiting in the queue, before analysis
+fn new_job(
+    job_id: String,
+    input_path: String,
+    output_path: String,
+    preset: VideoPreset,
+    options: JobOptions,
+    batch_id: Option<String>,
+) -> ConversionJob {
+    let output_problem = output_locations::output_problem(&output_path);
+    ConversionJob {
+        id: job_id,
+        input_path,
+        output_path,
+        preset,
         status: JobStatus::Queued,
         progress: 0.0,
         duration: None,
         error: None,
-        status_message: Some("Waiting in queue...".to_string()),
+        error_code: None,
+        ffmpeg_log: Vec::new(),
+        status_message: Some(StatusMessage::WaitingInQueue),
         thumbnail_path: None,
-    };
+        thumbnail_time: None,
+        width: None,
+        height: None,
+        video_codec: None,
+        fps: None,
+        file_size: None,
+        streams: Vec::new(),
+        source_timecode: None,
+        rotation: None,
+        bitrate_kbps: None,
+        audio_channels: None,
+        detected_crop: None,
+        warnings: Vec::new(),
+        output_problem,
+        estimate: None,
+        encode_stats: None,
+        resource_usage: None,
+        resolved_trim: None,
+        options,
+        batch_id,
+        quality_score: None,
+    }
+}
 
-    state.add_job(job.clone()).await;
+/// Write the conversion history, oldest first, to a CSV or JSON file.
+/// Returns the number of entries.
+#[tauri::command]
+async fn export_history(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+    format: history_export::HistoryExportFormat,
+) -> Result<usize, String> {
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    let history = state.get_history().await;
+    if history.is_empty() {
+        return Err("No conversions in the history".to_string());
+    }
 
-    // Check if this is the first job in the queue
-    let is_first_job = {
-        let queue = state.job_queue.lock().await;
-        queue.len() == 1 // This job was just added, so if length is 1, it's the first
-    };
-    
-    if is_first_job {
-        // For the first job, analyze and start converting immediately
-        start_priority_processing(app_handle.clone(), state.inner().clone(), job_id.clone(), input_path.clone()).await;
-    } else {
-        // For subsequent jobs, just start background analysis
-        start_preprocessing(app_handle.clone(), state.inner().clone(), job_id.clone(), input_path.clone()).await;
+    let content = history_export::export_history(&history, format)?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write history export: {}", e))?;
+
+    Ok(history.len())
+}
+
+/// Save the jobs waiting in the queue to a JSON file. Returns the number of jobs.
+#[tauri::command]
+async fn export_queue(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<usize, String> {
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    let jobs = state.get_all_jobs().await;
+    let pending = jobs.iter().filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Ready));
+    let queue_file = QueueFile::from_jobs(pending);
+    if queue_file.jobs.is_empty() {
+        return Err("No jobs waiting in the queue".to_string());
     }
-    
-    // Start the queue processor if it's not already running
-    start_queue_processor_if_needed(app_handle.clone(), state.inner().clone()).await;
 
-    Ok(job_id)
+    let json = serde_json::to_string_pretty(&queue_file)
+        .map_err(|e| format!("Failed to serialize queue: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to write queue file: {}", e))?;
+
+    Ok(queue_file.jobs.len())
+}
+
+/// Add the jobs from a file written by `export_queue`. Jobs whose input
+/// doesn't exist here or whose folders the user hasn't granted access to
+/// are skipped; the rest become one batch.
+#[tauri::command]
+async fn import_queue(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<QueueImport, String> {
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read queue file: {}", e))?;
+    let queue_file = QueueFile::parse(&json)?;
+
+    let mut result = QueueImport::default();
+    let (present, missing): (Vec<_>, Vec<_>) = queue_file.jobs.into_iter()
+        .partition(|job| Path::new(&job.input_path).exists());
+    result.missing_inputs = missing.into_iter().map(|job| job.input_path).collect();
+
+    // A queue file only names paths, it doesn't grant access to them
+    let mut allowed = Vec::new();
+    for job in present {
+        if path_scope::ensure_granted(&app_handle, &state, &job.input_path).await.is_ok()
+            && path_scope::ensure_granted(&app_handle, &state, &job.output_path).await.is_ok()
+        {
+            allowed.push(job);
+        } else {
+            result.not_allowed_inputs.push(job.input_path);
+        }
+    }
+
+    let batch_id = (allowed.len() > 1).then(|| Uuid::new_v4().to_string());
+    for job in allowed {
+        let job_id = enqueue_job(&app_handle, &state, job.input_path, job.output_path, job.preset, job.options, batch_id.clone()).await?;
+        result.job_ids.push(job_id);
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+async fn update_job_options(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+    options: JobOptions,
+) -> Result<(), String> {
+    let mut job = state.get_job(&job_id).await
+        .ok_or_else(|| "Job not found".to_string())?;
+
+    if !matches!(job.status, JobStatus::Queued | JobStatus::Ready) {
+        return Err("Only jobs waiting in the queue can be changed".to_string());
+    }
+
+    job.options = options;
+    filters::validate_job_options(&job)?;
+    job.warnings = ffmpeg::stream_warnings(&job);
+    job.estimate = estimate::estimate_output(&job, &state.get_throughput().await);
+    state.update_job(job).await;
+    emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+
+    Ok(())
+}
+
+/// Give a waiting job a different output file name in the same directory.
+#[tauri::command]
+async fn rename_job_output(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+    file_name: String,
+) -> Result<ConversionJob, String> {
+    let mut job = state.get_job(&job_id).await
+        .ok_or_else(|| "Job not found".to_string())?;
+
+    if !matches!(job.status, JobStatus::Queued | JobStatus::Ready) {
+        return Err("Only jobs waiting in the queue can be renamed".to_string());
+    }
+
+    let output_path = output_name::rename_output(&job.input_path, &job.output_path, &file_name)?;
+    if output_path == job.output_path {
+        return Ok(job);
+    }
+
+    // Same rules as the output pattern: never overwrite the source, another
+    // job's output, a previous conversion or an existing file
+    if Path::new(&output_path) == Path::new(&job.input_path) {
+        return Err("Output can't replace the source file".to_string());
+    }
+    let taken_by_job = state.get_all_jobs().await.iter()
+        .any(|other| other.id != job_id && other.output_path == output_path);
+    let taken_by_history = state.history_has_output(&output_path).await;
+    if taken_by_job || taken_by_history || Path::new(&output_path).exists() {
+        return Err(format!("{} already exists", output_path));
+    }
+
+    job.output_problem = output_locations::output_problem(&output_path);
+    job.output_path = output_path;
+    state.update_job(job.clone()).await;
+    emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+
+    Ok(job)
 }
 
 #[tauri::command]
@@ -339,9 +788,207 @@ async fn get_conversion_jobs(state: tauri::State<'_, AppState>) -> Result<Vec<Co
     Ok(state.get_all_jobs().await)
 }
 
+/// Recent fps/speed samples of a job's conversion, oldest first.
+#[tauri::command]
+async fn get_progress_samples(
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+) -> Result<Vec<ProgressSample>, String> {
+    Ok(state.get_progress_samples(&job_id).await)
+}
+
+#[tauri::command]
+async fn query_jobs(
+    state: tauri::State<'_, AppState>,
+    filter: query::JobFilter,
+) -> Result<Vec<ConversionJob>, String> {
+    Ok(query::filter_jobs(state.get_all_jobs().await, &filter))
+}
+
+#[tauri::command]
+async fn get_conversion_job(
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+) -> Result<ConversionJob, String> {
+    state.get_job(&job_id).await
+        .ok_or_else(|| "Job not found".to_string())
+}
+
+/// FFmpeg's output from a job's last conversion attempt, oldest line first.
+/// Falls back to the saved log for jobs converted in an earlier session.
+#[tauri::command]
+async fn get_job_log(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+) -> Result<Vec<String>, String> {
+    if let Some(job) = state.get_job(&job_id).await.filter(|job| !job.ffmpeg_log.is_empty()) {
+        return Ok(job.ffmpeg_log);
+    }
+    Ok(ffmpeg::read_stderr_log(&app_handle, &job_id)?.unwrap_or_default())
+}
+
+#[tauri::command]
+async fn get_batch_progress(
+    state: tauri::State<'_, AppState>,
+    batch_id: String,
+) -> Result<batch::BatchProgress, String> {
+    batch::batch_progress(&state.get_all_jobs().await, &batch_id)
+        .ok_or_else(|| "Batch not found".to_string())
+}
+
+#[tauri::command]
+async fn cancel_batch(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    batch_id: String,
+) -> Result<(), String> {
+    let job_ids: Vec<String> = state.get_all_jobs().await
+        .into_iter()
+        .filter(|job| job.batch_id.as_deref() == Some(batch_id.as_str()))
+        .map(|job| job.id)
+        .collect();
+    if job_ids.is_empty() {
+        return Err("Batch not found".to_string());
+    }
+
+    for job_id in state.cancel_jobs(&job_ids).await {
+        state.abort_analysis(&job_id).await;
+        emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_queue_status(state: tauri::State<'_, AppState>) -> Result<QueueStatus, String> {
+    Ok(state.queue_status().await)
+}
+
+/// OS, hardware and FFmpeg details for the diagnostics panel.
 #[tauri::command]
-async fn get_conversion_history(state: tauri::State<'_, AppState>) -> Result<Vec<ConversionHistory>, String> {
-    Ok(state.get_history().await)
+async fn get_system_info(app_handle: AppHandle) -> Result<system_info::SystemInfo, String> {
+    Ok(system_info::get_system_info(&app_handle).await)
+}
+
+/// Convert a generated test clip to confirm FFmpeg, permissions and the
+/// output folder all work.
+#[tauri::command]
+async fn run_self_test(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<self_test::SelfTestReport, String> {
+    let settings = state.get_settings().await;
+    Ok(self_test::run_self_test(&app_handle, &settings).await)
+}
+
+/// Results of the first-launch environment checks, or fresh ones when `rerun`
+/// is set, e.g. after the user fixed a problem.
+#[tauri::command]
+async fn get_environment_checks(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    rerun: Option<bool>,
+) -> Result<environment::EnvironmentReport, String> {
+    let mut report = state.environment_report.lock().await;
+    if let (Some(existing), false) = (report.as_ref(), rerun.unwrap_or(false)) {
+        return Ok(existing.clone());
+    }
+
+    let first_run = report.is_none() && environment::load_report(&app_handle).is_none();
+    let checked = environment::run_checks(&app_handle, &state.get_settings().await, first_run).await;
+    environment::save_report(&app_handle, &checked)?;
+    *report = Some(checked.clone());
+    Ok(checked)
+}
+
+/// Zip logs, versions, redacted settings and recent jobs for a bug report.
+#[tauri::command]
+async fn create_support_bundle(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    let path = path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    support_bundle::create_support_bundle(&app_handle, &state, &path).await
+}
+
+/// Current run progress, for a compact window opened mid-run. Updates follow
+/// as `queue-progress` events.
+#[tauri::command]
+async fn get_queue_progress(state: tauri::State<'_, AppState>) -> Result<QueueProgress, String> {
+    Ok(state.queue_progress().await)
+}
+
+/// Quit after the user confirmed it while jobs were still running.
+#[tauri::command]
+async fn confirm_quit(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    finish_current: bool,
+) -> Result<(), String> {
+    if finish_current {
+        println!("Quit confirmed, finishing the current job first");
+        state.stop_queue_worker().await;
+    } else {
+        println!("Quit confirmed, stopping conversions");
+        state.abort_queue_worker().await;
+    }
+    app_handle.exit(0);
+    Ok(())
+}
+
+/// One page of history entries matching `filter`, newest first unless
+/// `sort` says otherwise. Without a `limit` the page runs to the end.
+#[tauri::command]
+async fn query_history(
+    state: tauri::State<'_, AppState>,
+    filter: Option<query::HistoryFilter>,
+    sort: Option<query::HistorySort>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<query::HistoryPage, String> {
+    let filter = filter.unwrap_or_default();
+    state.query_history(&filter, sort.unwrap_or_default(), offset.unwrap_or(0), limit).await
+}
+
+#[tauri::command]
+async fn get_conversion_history(
+    state: tauri::State<'_, AppState>,
+    sort: Option<query::HistorySort>,
+) -> Result<Vec<ConversionHistory>, String> {
+    match sort {
+        Some(sort) => Ok(state.query_history(&query::HistoryFilter::default(), sort, 0, None).await?.items),
+        None => Ok(state.get_history().await),
+    }
+}
+
+/// Conversions, bytes in and out and minutes encoded per day, week or month,
+/// for the usage dashboard.
+#[tauri::command]
+async fn get_usage_stats(
+    state: tauri::State<'_, AppState>,
+    period: usage_stats::UsagePeriod,
+) -> Result<Vec<usage_stats::UsageBucket>, String> {
+    state.get_usage(period).await
+}
+
+/// Disk space taken by thumbnails, previews, work files and logs.
+#[tauri::command]
+async fn get_cache_usage(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<cache::CacheUsage, String> {
+    cache::get_cache_usage(&app_handle, &state).await
+}
+
+/// Delete cached files in the given categories and return what's left.
+#[tauri::command]
+async fn clear_cache(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    categories: Vec<cache::CacheCategory>,
+) -> Result<cache::CacheUsage, String> {
+    cache::clear_cache(&app_handle, &state, &categories).await
 }
 
 #[tauri::command]
@@ -352,18 +999,50 @@ async fn clear_completed_jobs(
     // Get all completed jobs before clearing
     let jobs = state.get_all_jobs().await;
     let completed_job_ids: Vec<String> = jobs.iter()
-        .filter(|j| matches!(j.status, JobStatus::Completed) || matches!(j.status, JobStatus::Failed))
+        .filter(|j| matches!(j.status, JobStatus::Completed | JobStatus::CompletedWithWarnings | JobStatus::Failed | JobStatus::Cancelled))
         .map(|j| j.id.clone())
         .collect();
     
-    // Clear jobs from state
+    // Clear jobs from state, stopping any analysis still running for them
     state.clear_completed_jobs().await;
+    for job_id in &completed_job_ids {
+        state.abort_analysis(job_id).await;
+    }
     
     // Clean up thumbnails for cleared jobs
+    remove_thumbnails(&app_handle, &completed_job_ids);
+    
+    // Let the frontend drop the cleared cards
+    emit_jobs_removed(&app_handle, &state, completed_job_ids).await?;
+    
+    Ok(())
+}
+
+/// Remove one job from the queue, stopping its conversion or analysis first.
+#[tauri::command]
+async fn remove_job(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+) -> Result<(), String> {
+    if state.get_job(&job_id).await.is_none() {
+        return Err("Job not found".to_string());
+    }
+
+    // Cancelling signals FFmpeg to stop; the conversion finds the job gone afterwards
+    state.cancel_jobs(std::slice::from_ref(&job_id)).await;
+    state.abort_analysis(&job_id).await;
+    state.remove_job(&job_id).await;
+
+    remove_thumbnails(&app_handle, std::slice::from_ref(&job_id));
+    emit_jobs_removed(&app_handle, &state, vec![job_id]).await
+}
+
+fn remove_thumbnails(app_handle: &AppHandle, job_ids: &[String]) {
     if let Ok(thumbnail_dir) = app_handle.path().app_cache_dir() {
         let thumbnail_dir = thumbnail_dir.join("thumbnails");
         if thumbnail_dir.exists() {
-            for job_id in completed_job_ids {
+            for job_id in job_ids {
                 let thumbnail_path = thumbnail_dir.join(format!("{}.jpg", job_id));
                 if thumbnail_path.exists() {
                     let _ = fs::remove_file(&thumbnail_path);
@@ -371,74 +1050,185 @@ async fn clear_completed_jobs(
             }
         }
     }
-    
-    // Emit event to refresh the frontend
-    app_handle.emit("jobs-cleared", ()).map_err(|e| e.to_string())?;
-    
-    Ok(())
+}
+
+/// Move old finished jobs out of the queue once there are too many, so
+/// week-long sessions don't keep every job and thumbnail around.
+async fn archive_finished_jobs(app_handle: &AppHandle, state: &AppState) {
+    match state.archive_finished_jobs(app_handle).await {
+        Ok(job_ids) if !job_ids.is_empty() => {
+            println!("Archived {} finished jobs", job_ids.len());
+            remove_thumbnails(app_handle, &job_ids);
+            let _ = emit_jobs_removed(app_handle, state, job_ids).await;
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to archive finished jobs: {}", e),
+    }
+}
+
+/// Finished jobs that were moved out of the queue, most recent first.
+#[tauri::command]
+async fn get_archived_jobs(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<ConversionJob>, String> {
+    state.get_archived_jobs(&app_handle, limit.unwrap_or(100))
+}
+
+#[tauri::command]
+async fn clear_conversion_history(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<(), String> {
+    state.clear_history().await?;
+    history_thumbnails::clear_history_thumbnails(&app_handle)
+}
+
+#[tauri::command]
+async fn check_file_exists(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<bool, String> {
+    let path = path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    Ok(path.exists())
+}
+
+#[tauri::command]
+async fn generate_video_thumbnail(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    input_path: String,
+    output_path: String,
+    time_offset: String,
+) -> Result<(), String> {
+    path_scope::ensure_allowed(&app_handle, &state, &input_path).await?;
+    path_scope::ensure_allowed(&app_handle, &state, &output_path).await?;
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+    generate_thumbnail(&ffmpeg_path, &input_path, &output_path, &time_offset).await
 }
 
 #[tauri::command]
-async fn clear_conversion_history(
+async fn capture_frame(
     app_handle: AppHandle,
-    state: tauri::State<'_, AppState>
+    state: tauri::State<'_, AppState>,
+    path: String,
+    timestamp: f64,
+    out_path: String,
+    format: FrameFormat,
 ) -> Result<(), String> {
-    state.clear_history(&app_handle).await
-}
-
-#[tauri::command]
-async fn check_file_exists(path: String) -> Result<bool, String> {
-    Ok(std::path::Path::new(&path).exists())
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    path_scope::ensure_allowed(&app_handle, &state, &out_path).await?;
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+    ffmpeg::capture_frame(&ffmpeg_path, &path, timestamp, &out_path, format).await
 }
 
 #[tauri::command]
-async fn generate_video_thumbnail(
+async fn fix_rotation(
     app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
     input_path: String,
     output_path: String,
-    time_offset: String,
+    rotation: u32,
 ) -> Result<(), String> {
+    path_scope::ensure_allowed(&app_handle, &state, &input_path).await?;
+    path_scope::ensure_allowed(&app_handle, &state, &output_path).await?;
     let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
-    generate_thumbnail(&ffmpeg_path, &input_path, &output_path, &time_offset).await
+    ffmpeg::set_rotation_metadata(&ffmpeg_path, &input_path, &output_path, rotation).await
+}
+
+#[tauri::command]
+async fn get_media_info(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<media_info::MediaInfo, String> {
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+    media_info::get_media_info(&ffmpeg_path, &path).await
 }
 
+/// Replace a job's thumbnail with the frame at `timestamp` seconds.
 #[tauri::command]
-async fn get_thumbnail_data(
+async fn regenerate_thumbnail(
     app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
     job_id: String,
-) -> Result<String, String> {
-    println!("get_thumbnail_data called for job_id: {}", &job_id);
-    
+    timestamp: f64,
+) -> Result<(), String> {
+    let job = state.get_job(&job_id).await
+        .ok_or_else(|| "Job not found".to_string())?;
+    if !timestamp.is_finite() || timestamp < 0.0 || job.duration.is_some_and(|duration| timestamp >= duration) {
+        return Err("Timestamp is outside the video".to_string());
+    }
+
     let thumbnail_dir = app_handle.path().app_cache_dir()
         .map_err(|e| format!("Failed to get cache dir: {}", e))?
         .join("thumbnails");
-    
-    let thumbnail_path = thumbnail_dir.join(format!("{}.jpg", job_id));
-    println!("Looking for thumbnail at: {:?}", &thumbnail_path);
-    
-    if thumbnail_path.exists() {
-        println!("Thumbnail found!");
-        // Read the file and convert to base64
-        let image_data = fs::read(&thumbnail_path)
-            .map_err(|e| format!("Failed to read thumbnail: {}", e))?;
-        let base64_data = general_purpose::STANDARD.encode(image_data);
-        Ok(format!("data:image/jpeg;base64,{}", base64_data))
-    } else {
-        println!("Thumbnail not found at path");
-        Err("Thumbnail not found".to_string())
-    }
+    fs::create_dir_all(&thumbnail_dir)
+        .map_err(|e| format!("Failed to create thumbnail directory: {}", e))?;
+    let thumbnail_path = thumbnail_dir.join(format!("{}.jpg", job_id))
+        .to_string_lossy()
+        .to_string();
+
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+    generate_thumbnail(&ffmpeg_path, &job.input_path, &thumbnail_path, &timestamp.to_string()).await?;
+
+    // The job may have changed while FFmpeg was running
+    let mut job = state.get_job(&job_id).await
+        .ok_or_else(|| "Job not found".to_string())?;
+    job.thumbnail_path = Some(thumbnail_path);
+    job.thumbnail_time = Some(timestamp);
+    state.update_job(job).await;
+    emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+
+    Ok(())
 }
 
+/// URL the webview can load a history entry's thumbnail from, or None when
+/// the entry has none, e.g. because it was evicted to keep the cache small.
 #[tauri::command]
-async fn get_video_file_data(file_path: String) -> Result<Vec<u8>, String> {
-    println!("Reading video file for fast thumbnail: {}", &file_path);
-    
-    // Read the video file as bytes
-    let file_data = fs::read(&file_path)
-        .map_err(|e| format!("Failed to read video file: {}", e))?;
-    
-    println!("Video file read successfully, size: {} bytes", file_data.len());
-    Ok(file_data)
+async fn get_history_thumbnail(
+    app_handle: AppHandle,
+    history_id: String,
+) -> Result<Option<String>, String> {
+    let thumbnail_path = history_thumbnails::history_thumbnail_path(&app_handle, &history_id)?;
+    Ok(thumbnail_path.map(|_| thumbnail_protocol::history_thumbnail_url(&history_id)))
+}
+
+/// Waveform preview of a file's audio as a PNG data URL. Cached by path,
+/// size and modification time so edited files get a fresh image.
+#[tauri::command]
+async fn generate_waveform(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<String, String> {
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    let metadata = fs::metadata(&path)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok().hash(&mut hasher);
+
+    let waveform_dir = app_handle.path().app_cache_dir()
+        .map_err(|e| format!("Failed to get cache dir: {}", e))?
+        .join("waveforms");
+    fs::create_dir_all(&waveform_dir)
+        .map_err(|e| format!("Failed to create waveform directory: {}", e))?;
+    let waveform_path = waveform_dir.join(format!("{:016x}.png", hasher.finish()));
+
+    if !waveform_path.exists() {
+        let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+        let _analysis_slot = state.acquire_analysis_slot().await;
+        ffmpeg::generate_waveform(&ffmpeg_path, &path, &waveform_path.to_string_lossy()).await?;
+    }
+
+    let image_data = fs::read(&waveform_path)
+        .map_err(|e| format!("Failed to read waveform: {}", e))?;
+    Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(image_data)))
 }
 
 #[tauri::command]
@@ -514,37 +1304,483 @@ async fn get_app_settings(state: tauri::State<'_, AppState>) -> Result<AppSettin
 async fn update_app_settings(
     app_handle: AppHandle,
     state: tauri::State<'_, AppState>,
-    settings: AppSettings,
+    settings: serde_json::Value,
 ) -> Result<(), String> {
+    let current = state.get_settings().await;
+    let mut settings = current.merged_with(&settings)?;
+    validate_settings_paths(&app_handle, &state, &current, &settings).await?;
+    if settings.temp_directory != current.temp_directory {
+        work_dir::validate_temp_directory(&settings.temp_directory).await?;
+    }
+    if settings.post_queue_action != current.post_queue_action {
+        settings.post_queue_action.validate()?;
+    }
+    let ffmpeg_changed = settings.ffmpeg_path_override != current.ffmpeg_path_override;
+    if ffmpeg_changed && !settings.ffmpeg_path_override.trim().is_empty() {
+        let version = ffmpeg_version::detect_ffmpeg_version(Path::new(settings.ffmpeg_path_override.trim())).await?;
+        println!("Using FFmpeg {} from {}", version, settings.ffmpeg_path_override);
+    }
+    let retention_changed = settings.history_max_entries != current.history_max_entries
+        || settings.history_max_age_days != current.history_max_age_days;
+    let output_directory = settings.output_directory.clone();
+    settings.remember_output_location(&output_directory);
     state.update_settings(&app_handle, |current_settings| {
         *current_settings = settings;
+    }).await?;
+
+    if retention_changed {
+        state.prune_history(&app_handle).await?;
+    }
+
+    // The startup check described the FFmpeg that was in use before
+    if ffmpeg_changed {
+        let diagnostic = ffmpeg_version::verify_ffmpeg_binary(&app_handle).await;
+        *state.ffmpeg_diagnostic.lock().await = Some(diagnostic);
+    }
+    Ok(())
+}
+
+/// Folders and programs in settings widen what the app may read, write or
+/// run, so new ones have to come from a drop or a native dialog
+async fn validate_settings_paths(app_handle: &AppHandle, state: &AppState, current: &AppSettings, settings: &AppSettings) -> Result<(), String> {
+    let mut new_paths: Vec<&str> = std::iter::once(&settings.output_directory)
+        .chain(&settings.output_locations)
+        .filter(|dir| **dir != current.output_directory && !current.output_locations.contains(dir))
+        .map(String::as_str)
+        .collect();
+    new_paths.extend(
+        settings.watch_folders.iter()
+            .filter(|rule| !current.watch_folders.iter().any(|existing| existing.path == rule.path))
+            .map(|rule| rule.path.as_str()),
+    );
+    if settings.temp_directory != current.temp_directory {
+        new_paths.push(&settings.temp_directory);
+    }
+    if settings.ffmpeg_path_override != current.ffmpeg_path_override {
+        new_paths.push(settings.ffmpeg_path_override.trim());
+    }
+    if settings.post_queue_action != current.post_queue_action {
+        if let PostQueueAction::RunScript { script } = &settings.post_queue_action {
+            new_paths.push(script);
+        }
+    }
+
+    for path in new_paths.into_iter().filter(|path| !path.is_empty()) {
+        path_scope::ensure_granted(app_handle, state, path).await?;
+    }
+    Ok(())
+}
+
+/// Whether each remembered output folder still exists and can be written to.
+#[tauri::command]
+async fn get_output_locations(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<output_locations::OutputLocationStatus>, String> {
+    let locations = state.get_settings().await.output_locations;
+    Ok(locations.iter().map(|path| output_locations::location_status(path)).collect())
+}
+
+/// Check the output folders of waiting jobs again, e.g. after a drive was plugged in.
+#[tauri::command]
+async fn revalidate_output_locations(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    for job_id in state.validate_output_locations().await {
+        emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_watch_folders(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<WatchFolder>, String> {
+    Ok(state.get_settings().await.watch_folders)
+}
+
+/// Convert new videos in `path` with `preset` as they appear, and start
+/// watching right away. Replaces the preset if the folder is already watched.
+#[tauri::command]
+async fn add_watch_folder(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+    preset: String,
+) -> Result<(), String> {
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    if !Path::new(&path).is_dir() {
+        return Err(format!("{} is not a folder", path));
+    }
+    if !state.list_presets().await.iter().any(|known| known.name == preset) {
+        return Err(format!("Preset '{}' not found", preset));
+    }
+
+    let rule = WatchFolder { path: path.clone(), preset, enabled: true };
+    state.update_settings(&app_handle, |settings| {
+        settings.watch_folders.retain(|existing| existing.path != rule.path);
+        settings.watch_folders.push(rule);
+    }).await?;
+    watcher::start(&app_handle, &state, &path).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn remove_watch_folder(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    watcher::stop(&state, &path).await;
+    state.update_settings(&app_handle, |settings| {
+        settings.watch_folders.retain(|rule| rule.path != path);
     }).await
 }
 
+/// Resume watching a folder, now and on later launches
 #[tauri::command]
-async fn load_persisted_data(
+async fn start_watch_folder(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    set_watch_folder_enabled(&app_handle, &state, &path, true).await?;
+    watcher::start(&app_handle, &state, &path).await;
+    Ok(())
+}
+
+/// Stop watching a folder but keep its rule
+#[tauri::command]
+async fn stop_watch_folder(
     app_handle: AppHandle,
     state: tauri::State<'_, AppState>,
+    path: String,
 ) -> Result<(), String> {
+    set_watch_folder_enabled(&app_handle, &state, &path, false).await?;
+    watcher::stop(&state, &path).await;
+    Ok(())
+}
+
+async fn set_watch_folder_enabled(app_handle: &AppHandle, state: &AppState, path: &str, enabled: bool) -> Result<(), String> {
+    if !state.get_settings().await.watch_folders.iter().any(|rule| rule.path == path) {
+        return Err(format!("{} is not a watch folder", path));
+    }
+    state.update_settings(app_handle, |settings| {
+        for rule in settings.watch_folders.iter_mut().filter(|rule| rule.path == path) {
+            rule.enabled = enabled;
+        }
+    }).await
+}
+
+/// Restore the previous session. Only the first call per process does
+/// anything: the frontend calls this on every mount, webview reloads
+/// included, while conversions may be running.
+#[tauri::command]
+async fn load_persisted_data(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let mut interrupted_job_ids = Vec::new();
+    let restored = &mut interrupted_job_ids;
+    state.session_restored.get_or_init(|| async move {
+        *restored = restore_session(&app_handle, &state).await;
+    }).await;
+    Ok(interrupted_job_ids)
+}
+
+/// Returns the ids of interrupted jobs the user should be asked about
+async fn restore_session(app_handle: &AppHandle, state: &AppState) -> Vec<String> {
     // Load settings first
-    if let Err(e) = state.load_settings(&app_handle).await {
+    if let Err(e) = state.load_settings(app_handle).await {
         eprintln!("Failed to load settings: {}", e);
     }
+    // Runs once per process, before watchers or the restored queue can start
+    // a worker, so anything in the work dir is left over from a previous session
+    if state.queue_worker.lock().await.is_none() {
+        match work_dir::work_dir(app_handle, &state.get_settings().await) {
+            Ok(dir) => work_dir::clean_stale_work_files(&dir),
+            Err(e) => eprintln!("Failed to clean work directory: {}", e),
+        }
+    }
+    // The startup check ran before settings were loaded and saw the bundled FFmpeg
+    if !state.get_settings().await.ffmpeg_path_override.trim().is_empty() {
+        let diagnostic = ffmpeg_version::verify_ffmpeg_binary(app_handle).await;
+        *state.ffmpeg_diagnostic.lock().await = Some(diagnostic);
+    }
     
     // Load history
-    if let Err(e) = state.load_history(&app_handle).await {
+    if let Err(e) = state.load_history(app_handle).await {
         eprintln!("Failed to load history: {}", e);
     }
     
+    if let Err(e) = state.load_throughput(app_handle).await {
+        eprintln!("Failed to load throughput: {}", e);
+    }
+
+    if let Err(e) = state.load_user_presets(app_handle).await {
+        eprintln!("Failed to load presets: {}", e);
+    }
+
+    watcher::start_enabled(app_handle, state).await;
+
+    // Restore the queue from the previous session
+    if let Err(e) = state.load_jobs(app_handle).await {
+        eprintln!("Failed to load jobs: {}", e);
+    }
+    let policy = state.get_settings().await.interrupted_jobs;
+    let interrupted_job_ids = state.recover_interrupted_jobs(policy).await;
+    // Flag jobs whose output folder went away since the last session
+    state.validate_output_locations().await;
+    archive_finished_jobs(app_handle, state).await;
+    if let Err(e) = state.save_jobs(app_handle).await {
+        eprintln!("Failed to save jobs: {}", e);
+    }
+    resume_queue(app_handle.clone(), state.clone()).await;
+
+    interrupted_job_ids
+}
+
+/// Convert failed or interrupted jobs again.
+#[tauri::command]
+async fn requeue_jobs(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_ids: Vec<String>,
+) -> Result<(), String> {
+    for job_id in state.requeue_jobs(&job_ids).await {
+        emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+    }
+    resume_queue(app_handle.clone(), state.inner().clone()).await;
     Ok(())
 }
 
+/// Copy the FFmpeg command line for a job to the clipboard and return it.
+#[tauri::command]
+async fn copy_ffmpeg_command(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+) -> Result<String, String> {
+    let job = state.get_job(&job_id).await
+        .ok_or_else(|| "Job not found".to_string())?;
+    let settings = state.get_settings().await;
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+
+    let args = ffmpeg::standalone_ffmpeg_args(&app_handle, &ffmpeg_path, &job, &settings).await?;
+    let command = Shell::native().command_line(&ffmpeg_path.to_string_lossy(), &args);
+    copy_to_clipboard(&app_handle, &command)?;
+    Ok(command)
+}
+
+/// Rough output size and time of converting `input_path` with `preset`,
+/// before it's added to the queue. None when the source can't be measured.
+#[tauri::command]
+async fn estimate_output_size(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    input_path: String,
+    preset: VideoPreset,
+    options: Option<JobOptions>,
+) -> Result<Option<estimate::OutputEstimate>, String> {
+    path_scope::ensure_allowed(&app_handle, &state, &input_path).await?;
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+    let probe = ffmpeg::probe_video(&ffmpeg_path, &input_path).await?;
+
+    let mut job = new_job(String::new(), input_path, String::new(), preset, options.unwrap_or_default(), None);
+    job.apply_probe(probe);
+    Ok(estimate::estimate_output(&job, &state.get_throughput().await))
+}
+
+/// The FFmpeg command a conversion would run, without running anything.
+/// Previews a job in the queue by `job_id`, or a file that hasn't been added
+/// yet by `input_path` and `preset`; black bars are only detected for queued
+/// jobs. With `copy` the command line is also put on the clipboard.
+#[tauri::command]
+async fn preview_ffmpeg_command(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_id: Option<String>,
+    input_path: Option<String>,
+    preset: Option<VideoPreset>,
+    options: Option<JobOptions>,
+    copy: bool,
+) -> Result<CommandPreview, String> {
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+    let job = match (job_id, input_path, preset) {
+        (Some(job_id), _, _) => state.get_job(&job_id).await
+            .ok_or_else(|| "Job not found".to_string())?,
+        (None, Some(input_path), Some(preset)) => {
+            path_scope::ensure_allowed(&app_handle, &state, &input_path).await?;
+            let output_path = output_path_for_input(&app_handle, &state, &input_path, &preset, None, None, None).await;
+            let mut job = new_job(String::new(), input_path, output_path, preset, options.unwrap_or_default(), None);
+            let probe = ffmpeg::probe_video(&ffmpeg_path, &job.input_path).await?;
+            job.apply_probe(probe);
+            job
+        }
+        _ => return Err("Choose a job, or a file and a preset, to preview".to_string()),
+    };
+    let settings = state.get_settings().await;
+
+    let args = ffmpeg::standalone_ffmpeg_args(&app_handle, &ffmpeg_path, &job, &settings).await?;
+    let preview = CommandPreview::new(&ffmpeg_path.to_string_lossy(), args);
+    if copy {
+        copy_to_clipboard(&app_handle, &preview.command_line)?;
+    }
+    Ok(preview)
+}
+
+/// Write the FFmpeg commands of all waiting jobs to a bash script, or a
+/// PowerShell script if `path` ends in `.ps1`. Returns the number of jobs.
+#[tauri::command]
+async fn export_queue_as_script(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<usize, String> {
+    path_scope::ensure_allowed(&app_handle, &state, &path).await?;
+    let settings = state.get_settings().await;
+    let ffmpeg_path = get_ffmpeg_binary(&app_handle)?;
+
+    let mut commands = Vec::new();
+    for job in state.get_all_jobs().await {
+        if !matches!(job.status, JobStatus::Queued | JobStatus::Ready) {
+            continue;
+        }
+        let args = ffmpeg::standalone_ffmpeg_args(&app_handle, &ffmpeg_path, &job, &settings).await
+            .map_err(|e| format!("Failed to export {}: {}", job.input_path, e))?;
+        let label = Path::new(&job.input_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| job.input_path.clone());
+        commands.push(ScriptCommand { label, output_path: job.output_path.clone(), args });
+    }
+
+    if commands.is_empty() {
+        return Err("No jobs waiting in the queue".to_string());
+    }
+
+    let shell = Shell::for_script(&path);
+    let script = script::queue_script(shell, &commands);
+    fs::write(&path, script)
+        .map_err(|e| format!("Failed to write script: {}", e))?;
+
+    // PowerShell runs a .ps1 through `powershell -File`, so only shell scripts need the exec bit
+    #[cfg(unix)]
+    if shell == Shell::Posix {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make script executable: {}", e))?;
+    }
+
+    Ok(commands.len())
+}
+
+fn copy_to_clipboard(app_handle: &AppHandle, text: &str) -> Result<(), String> {
+    app_handle.clipboard().write_text(text)
+        .map_err(|e| format!("Failed to copy to clipboard: {}", e))
+}
+
+/// Look for a newer version on the channel chosen in settings.
+#[tauri::command]
+async fn check_for_update(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<updates::AvailableUpdate>, String> {
+    let channel = state.get_settings().await.update_channel;
+    let update = updates::check_for_update(&app_handle, channel).await?;
+    Ok(update.map(|update| updates::describe_update(&update, channel)))
+}
+
+/// Download the latest update and install it as soon as the queue is idle,
+/// which may be right away. Returns the version that will be installed.
+#[tauri::command]
+async fn install_update(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    if state.pending_update.lock().await.is_some() {
+        return Err("An update is already waiting to be installed".to_string());
+    }
+
+    let channel = state.get_settings().await.update_channel;
+    let Some(update) = updates::check_for_update(&app_handle, channel).await? else {
+        return Ok(None);
+    };
+
+    let progress_handle = app_handle.clone();
+    let mut downloaded = 0;
+    let bytes = update.download(
+        |chunk, total| {
+            downloaded += chunk;
+            let _ = progress_handle.emit("update-download-progress", (downloaded, total));
+        },
+        || {},
+    ).await.map_err(|e| format!("Failed to download update: {}", e))?;
+
+    let version = update.version.clone();
+    *state.pending_update.lock().await = Some(updates::PendingUpdate { update, bytes });
+    if state.queue_status().await.is_busy() {
+        println!("Update {} downloaded, installing once the queue is idle", version);
+    }
+    tauri::async_runtime::spawn(updates::install_when_idle(app_handle.clone(), state.inner().clone()));
+
+    Ok(Some(version))
+}
+
+/// Keep access to a folder the user picked after the app restarts.
+#[tauri::command]
+async fn grant_folder_access(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    // Only folders the user just picked or dropped can be kept
+    path_scope::ensure_granted(&app_handle, &state, &path).await?;
+    state.grant_folder_access(&app_handle, &path).await
+}
+
+#[tauri::command]
+async fn revoke_folder_access(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    state.revoke_folder_access(&app_handle, &path).await
+}
+
+#[tauri::command]
+async fn get_granted_folders(state: tauri::State<'_, AppState>) -> Result<Vec<String>, String> {
+    let mut folders: Vec<String> = state.folder_bookmarks.lock().await.keys().cloned().collect();
+    folders.sort();
+    Ok(folders)
+}
+
+/// Fetch the pinned FFmpeg build into app data, e.g. to repair a missing
+/// binary. Progress is sent as `ffmpeg-download-progress` events.
+#[tauri::command]
+async fn download_ffmpeg(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<ffmpeg_version::FFmpegDiagnostic, String> {
+    ffmpeg_download::download_ffmpeg(&app_handle, &state).await
+}
+
 #[tauri::command]
-async fn reveal_in_finder(file_path: String) -> Result<(), String> {
+async fn reveal_in_finder(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    file_path: String,
+) -> Result<(), String> {
+    path_scope::ensure_allowed(&app_handle, &state, &file_path).await?;
+    reveal_path(&file_path)
+}
+
+/// Show a file in the platform's file manager
+fn reveal_path(file_path: &str) -> Result<(), String> {
     #[cfg(target_os = "macos")]
     {
         Command::new("open")
-            .args(["-R", &file_path])
+            .args(["-R", file_path])
             .spawn()
             .map_err(|e| format!("Failed to open Finder: {}", e))?;
     }
@@ -552,7 +1788,7 @@ async fn reveal_in_finder(file_path: String) -> Result<(), String> {
     #[cfg(target_os = "windows")]
     {
         Command::new("explorer")
-            .args(["/select,", &file_path])
+            .args(["/select,", file_path])
             .spawn()
             .map_err(|e| format!("Failed to open Explorer: {}", e))?;
     }
@@ -561,7 +1797,7 @@ async fn reveal_in_finder(file_path: String) -> Result<(), String> {
     {
         // Try different file managers
         let file_managers = ["xdg-open", "nautilus", "dolphin", "thunar"];
-        let parent_dir = std::path::Path::new(&file_path)
+        let parent_dir = std::path::Path::new(file_path)
             .parent()
             .ok_or("No parent directory")?;
             
@@ -586,16 +1822,42 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::new().build())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin({
+            let updater = tauri_plugin_updater::Builder::new();
+            match updates::UPDATER_PUBKEY {
+                Some(pubkey) => updater.pubkey(pubkey),
+                None => updater,
+            }
+            .build()
+        })
         .manage(AppState::new())
+        .register_asynchronous_uri_scheme_protocol(thumbnail_protocol::THUMBNAIL_SCHEME, |ctx, request, responder| {
+            let app_handle = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(thumbnail_protocol::handle(&app_handle, &request));
+            });
+        })
+        .register_asynchronous_uri_scheme_protocol(video_protocol::VIDEO_SCHEME, |ctx, request, responder| {
+            let app_handle = ctx.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(video_protocol::handle(&app_handle, &request).await);
+            });
+        })
         .setup(|app| {
             let window = app.get_webview_window("main").unwrap();
             let window_clone = window.clone();
+            let drop_state = app.state::<AppState>().inner().clone();
             
             // Enable file drop
             window.on_window_event(move |event| {
                 match event {
                     tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, position: _ }) => {
                         println!("Files dropped: {:?}", paths);
+                        for path in paths {
+                            drop_state.allow_path(path);
+                        }
                         // Convert PathBuf to String
                         let path_strings: Vec<String> = paths.iter()
                             .map(|p| p.to_string_lossy().to_string())
@@ -616,29 +1878,159 @@ pub fn run() {
                     _ => {}
                 }
             });
-            
+
+            // Files and folders picked in dialogs are added to the fs scope
+            let scope_state = app.state::<AppState>().inner().clone();
+            app.fs_scope().listen(move |event| {
+                if let tauri::scope::fs::Event::PathAllowed(path) = event {
+                    scope_state.allow_path(path);
+                }
+            });
+
+            // Regain access to granted folders before anything reads or writes them
+            let state = app.state::<AppState>().inner().clone();
+            if let Err(e) = tauri::async_runtime::block_on(state.load_folder_bookmarks(app.handle())) {
+                eprintln!("Failed to load folder bookmarks: {}", e);
+            }
+
+            // Check the environment once on first launch so onboarding can
+            // point out problems before the first job fails
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app_handle.state::<AppState>();
+                let mut report = state.environment_report.lock().await;
+                if let Some(saved) = environment::load_report(&app_handle) {
+                    *report = Some(saved);
+                    return;
+                }
+                let checked = environment::run_checks(&app_handle, &state.get_settings().await, true).await;
+                if let Err(e) = environment::save_report(&app_handle, &checked) {
+                    eprintln!("Failed to save environment checks: {}", e);
+                }
+                *report = Some(checked);
+            });
+
+            // Catch a missing or damaged FFmpeg before the first job fails on it
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let diagnostic = ffmpeg_version::verify_ffmpeg_binary(&app_handle).await;
+                if diagnostic.problem.is_some() {
+                    eprintln!("FFmpeg check failed: {}", diagnostic.message);
+                    let _ = app_handle.emit("ffmpeg-problem", &diagnostic);
+                }
+                // Downloading is left to the user until the manifest pins real checksums
+                *app_handle.state::<AppState>().ffmpeg_diagnostic.lock().await = Some(diagnostic);
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_video_presets,
+            list_presets,
+            create_preset,
+            update_preset,
+            delete_preset,
+            encode_preset_string,
+            decode_preset_string,
             add_conversion_job,
+            update_job_options,
+            export_queue,
+            import_queue,
+            rename_job_output,
             get_conversion_jobs,
+            get_conversion_job,
+            get_job_log,
+            query_jobs,
+            get_progress_samples,
+            get_queue_status,
+            get_queue_progress,
+            create_support_bundle,
+            get_system_info,
+            run_self_test,
+            get_environment_checks,
+            get_batch_progress,
+            cancel_batch,
+            confirm_quit,
             get_conversion_history,
+            query_history,
+            export_history,
             clear_completed_jobs,
+            remove_job,
+            get_usage_stats,
+            get_cache_usage,
+            clear_cache,
+            resolve_output_path,
+            get_watch_folders,
+            add_watch_folder,
+            remove_watch_folder,
+            start_watch_folder,
+            stop_watch_folder,
+            get_archived_jobs,
             clear_conversion_history,
             check_file_exists,
             generate_video_thumbnail,
-            get_thumbnail_data,
-            get_video_file_data,
+            capture_frame,
+            fix_rotation,
+            get_media_info,
+            regenerate_thumbnail,
+            get_history_thumbnail,
+            generate_waveform,
             select_output_directory,
             debug_binary_paths,
             test_file_drop,
             reveal_in_finder,
+            grant_folder_access,
+            revoke_folder_access,
+            get_granted_folders,
+            check_for_update,
+            install_update,
+            copy_ffmpeg_command,
+            preview_ffmpeg_command,
+            estimate_output_size,
+            export_queue_as_script,
             get_app_settings,
             update_app_settings,
+            get_output_locations,
+            revalidate_output_locations,
             load_persisted_data,
+            requeue_jobs,
             ffmpeg_version::get_ffmpeg_version_info,
+            ffmpeg_version::check_ffmpeg_update,
+            ffmpeg_version::get_ffmpeg_diagnostic,
+            download_ffmpeg,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            let state = app_handle.state::<AppState>().inner().clone();
+            match event {
+                // Closing the window or quitting with work left asks the frontend
+                // to confirm first. Exits with an explicit code come from confirm_quit.
+                tauri::RunEvent::WindowEvent { event: tauri::WindowEvent::CloseRequested { api, .. }, .. } => {
+                    let status = tauri::async_runtime::block_on(state.queue_status());
+                    if status.is_busy() {
+                        api.prevent_close();
+                        let _ = app_handle.emit("quit-requested", status);
+                    }
+                }
+                tauri::RunEvent::ExitRequested { code: None, api, .. } => {
+                    let status = tauri::async_runtime::block_on(state.queue_status());
+                    if status.is_busy() {
+                        api.prevent_exit();
+                        let _ = app_handle.emit("quit-requested", status);
+                    }
+                }
+                tauri::RunEvent::Exit => {
+                    tauri::async_runtime::block_on(events::flush_job_changes(app_handle, &state));
+                    // Never wait for an encode here: exits that skip `confirm_quit`,
+                    // like the updater restart, would hang until it finished
+                    tauri::async_runtime::block_on(state.abort_queue_worker());
+                    // Dropping aborted tasks kills their FFmpeg; kill whatever
+                    // is still tracked, so no FFmpeg outlives the app
+                    tauri::async_runtime::block_on(state.abort_all_analysis());
+                    state.kill_ffmpeg_processes();
+                }
+                _ => {}
+            }
+        });
 }
\ No newline at end of file
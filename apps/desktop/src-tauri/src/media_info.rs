@@ -0,0 +1,372 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use crate::ffmpeg_parser::{parse_duration_from_info, parse_streams, StreamInfo, StreamKind};
+
+/// Everything we know about a media file from FFmpeg's input info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub path: String,
+    /// Demuxer names, e.g. "mov,mp4,m4a,3gp,3g2,mj2" or "matroska,webm"
+    pub container: String,
+    pub duration: Option<f64>,
+    pub bitrate_kbps: Option<u32>,
+    pub file_size: u64,
+    pub video: Vec<VideoStream>,
+    pub audio: Vec<AudioStream>,
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoStream {
+    pub index: u32,
+    pub codec: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub bitrate_kbps: Option<u32>,
+    pub pixel_format: Option<String>,
+    pub bit_depth: Option<u8>,
+    /// "pq" (HDR10/Dolby Vision) or "hlg" when the transfer curve is HDR
+    pub hdr: Option<String>,
+    pub rotation: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStream {
+    pub index: u32,
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub bitrate_kbps: Option<u32>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+pub async fn get_media_info(ffmpeg_path: &Path, input_path: &str) -> Result<MediaInfo, String> {
+    let file_size = std::fs::metadata(input_path)
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .len();
+
+    let output = Command::new(ffmpeg_path)
+        .args(["-hide_banner", "-i", input_path])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to probe media: {}", e))?;
+
+    // FFmpeg exits with an error without an output file, so judge by the info itself
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut info = parse_media_info(&stderr)
+        .ok_or_else(|| format!("Could not read media info: {}", stderr.lines().last().unwrap_or("unknown error")))?;
+    info.path = input_path.to_string();
+    info.file_size = file_size;
+    Ok(info)
+}
+
+/// Build a `MediaInfo` from the text FFmpeg prints for `ffmpeg -i`
+pub fn parse_media_info(output: &str) -> Option<MediaInfo> {
+    let container = output.lines().find_map(|line| {
+        let rest = line.strip_prefix("Input #0, ")?;
+        Some(rest.split(", from").next()?.to_string())
+    })?;
+
+    let duration_line = output.lines().find(|line| line.contains("Duration:"));
+    let duration = duration_line.and_then(parse_duration_from_info);
+    let bitrate_kbps = duration_line.and_then(|line| {
+        let value = line.split("bitrate:").nth(1)?;
+        parse_kbps(value.trim())
+    });
+
+    let streams = parse_streams(output);
+    let video = streams.iter()
+        .filter(|stream| stream.kind == StreamKind::Video)
+        .map(parse_video_stream)
+        .collect();
+    let audio = streams.iter()
+        .filter(|stream| stream.kind == StreamKind::Audio)
+        .map(parse_audio_stream)
+        .collect();
+
+    Some(MediaInfo {
+        path: String::new(),
+        container,
+        duration,
+        bitrate_kbps,
+        file_size: 0,
+        video,
+        audio,
+        streams,
+        chapters: parse_chapters(output),
+    })
+}
+
+/// Split stream details on commas that aren't inside parentheses or brackets
+/// Example: "yuv420p(tv, bt709), 1920x1080 [SAR 1:1 DAR 16:9], 30 fps"
+fn split_details(details: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in details.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(details[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(details[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+fn parse_kbps(value: &str) -> Option<u32> {
+    let number = value.strip_suffix("kb/s")?.trim();
+    number.parse::<f64>().ok().map(|kbps| kbps.round() as u32)
+}
+
+fn parse_video_stream(stream: &StreamInfo) -> VideoStream {
+    let parts = split_details(&stream.details);
+
+    let resolution_index = parts.iter().position(|part| {
+        let size = part.split_whitespace().next().unwrap_or("");
+        size.split_once('x')
+            .is_some_and(|(w, h)| w.parse::<u32>().is_ok() && h.parse::<u32>().is_ok())
+    });
+    let (width, height) = resolution_index
+        .and_then(|i| parts[i].split_whitespace().next()?.split_once('x'))
+        .map(|(w, h)| (w.parse().ok(), h.parse().ok()))
+        .unwrap_or((None, None));
+
+    // The pixel format is listed right before the frame size
+    let pixel_format_part = resolution_index
+        .filter(|&i| i > 0)
+        .map(|i| parts[i - 1])
+        .filter(|part| !part.starts_with('('));
+    let pixel_format = pixel_format_part.map(|part| part.split('(').next().unwrap_or(part).to_string());
+    let bit_depth = pixel_format.as_deref().map(pixel_format_bit_depth);
+    let hdr = pixel_format_part.and_then(|part| {
+        if part.contains("smpte2084") {
+            Some("pq".to_string())
+        } else if part.contains("arib-std-b67") {
+            Some("hlg".to_string())
+        } else {
+            None
+        }
+    });
+
+    let fps = parts.iter()
+        .find_map(|part| part.strip_suffix(" fps"))
+        .and_then(|fps| fps.trim().parse().ok());
+    let bitrate_kbps = parts.iter().find_map(|part| parse_kbps(part));
+
+    VideoStream {
+        index: stream.index,
+        codec: stream.codec.clone(),
+        width,
+        height,
+        fps,
+        bitrate_kbps,
+        pixel_format,
+        bit_depth,
+        hdr,
+        rotation: stream.rotation,
+    }
+}
+
+/// Bits per sample from the depth suffix of a pixel format name, like the
+/// `p10` in `yuv420p10le`. Digits elsewhere name the layout (`yuv410p`, `nv12`).
+fn pixel_format_bit_depth(pixel_format: &str) -> u8 {
+    let name = pixel_format.strip_suffix("le")
+        .or_else(|| pixel_format.strip_suffix("be"))
+        .unwrap_or(pixel_format);
+    let depths = [
+        (16, ["p16", "p016", "gray16"]),
+        (12, ["p12", "p012", "gray12"]),
+        (10, ["p10", "p010", "gray10"]),
+    ];
+    depths.iter()
+        .find(|(_, suffixes)| suffixes.iter().any(|suffix| name.ends_with(suffix)))
+        .map_or(8, |(depth, _)| *depth)
+}
+
+fn parse_audio_stream(stream: &StreamInfo) -> AudioStream {
+    let parts = split_details(&stream.details);
+
+    let sample_rate = parts.iter()
+        .find_map(|part| part.strip_suffix(" Hz"))
+        .and_then(|rate| rate.trim().parse().ok());
+
+    // The channel layout follows the sample rate
+    let channel_layout = parts.iter()
+        .position(|part| part.ends_with(" Hz"))
+        .and_then(|i| parts.get(i + 1))
+        .map(|layout| layout.to_string());
+    let channels = channel_layout.as_deref().and_then(layout_channels);
+    let bitrate_kbps = parts.iter().find_map(|part| parse_kbps(part.split(" (").next().unwrap_or(part)));
+
+    AudioStream {
+        index: stream.index,
+        codec: stream.codec.clone(),
+        sample_rate,
+        channels,
+        channel_layout,
+        bitrate_kbps,
+        language: stream.language.clone(),
+        title: stream.title.clone(),
+    }
+}
+
+fn layout_channels(layout: &str) -> Option<u32> {
+    if let Some(count) = layout.strip_suffix(" channels") {
+        return count.trim().parse().ok();
+    }
+
+    let base = layout.split('(').next().unwrap_or(layout);
+    match base {
+        "mono" => Some(1),
+        "stereo" => Some(2),
+        "2.1" | "3.0" => Some(3),
+        "3.1" | "4.0" | "quad" => Some(4),
+        "4.1" | "5.0" => Some(5),
+        "5.1" | "6.0" | "hexagonal" => Some(6),
+        "6.1" | "7.0" => Some(7),
+        "7.1" | "octagonal" => Some(8),
+        _ => None,
+    }
+}
+
+/// Parse chapter markers and their titles
+/// Example: "    Chapter #0:1: start 300.000000, end 600.000000"
+fn parse_chapters(output: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut in_chapters = false;
+
+    for line in output.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("Chapter #") {
+            in_chapters = true;
+            let times = rest.split_once(": ").map(|(_, times)| times).unwrap_or("");
+            let start = times.split("start ").nth(1).and_then(|v| v.split(',').next()?.trim().parse().ok());
+            let end = times.split("end ").nth(1).and_then(|v| v.trim().parse().ok());
+            if let (Some(start), Some(end)) = (start, end) {
+                chapters.push(Chapter { start, end, title: None });
+            }
+        } else if trimmed.starts_with("Stream #") {
+            in_chapters = false;
+        } else if in_chapters {
+            if let (Some(chapter), Some((key, value))) = (chapters.last_mut(), trimmed.split_once(" : ")) {
+                if key.trim() == "title" {
+                    chapter.title = Some(value.trim().to_string());
+                }
+            }
+        }
+    }
+
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "Input #0, matroska,webm, from 'movie.mkv':
+  Metadata:
+    title           : Movie
+  Duration: 01:30:00.00, start: 0.000000, bitrate: 5000 kb/s
+    Chapter #0:0: start 0.000000, end 300.000000
+      Metadata:
+        title           : Intro
+    Chapter #0:1: start 300.000000, end 5400.000000
+  Stream #0:0: Video: hevc (Main 10), yuv420p10le(tv, bt2020nc/bt2020/smpte2084), 3840x2160 [SAR 1:1 DAR 16:9], 23.98 fps, 23.98 tbr, 1k tbn (default)
+  Stream #0:1(eng): Audio: eac3, 48000 Hz, 5.1(side), fltp, 640 kb/s (default)
+    Metadata:
+      title           : Surround
+  Stream #0:2(jpn): Audio: opus, 48000 Hz, stereo, fltp
+  Stream #0:3(eng): Subtitle: ass";
+
+    #[test]
+    fn test_parse_media_info() {
+        let info = parse_media_info(SAMPLE).unwrap();
+        assert_eq!(info.container, "matroska,webm");
+        assert_eq!(info.duration, Some(5400.0));
+        assert_eq!(info.bitrate_kbps, Some(5000));
+        assert_eq!(info.streams.len(), 4);
+
+        let video = &info.video[0];
+        assert_eq!(video.codec, "hevc");
+        assert_eq!((video.width, video.height), (Some(3840), Some(2160)));
+        assert_eq!(video.fps, Some(23.98));
+        assert_eq!(video.pixel_format, Some("yuv420p10le".to_string()));
+        assert_eq!(video.bit_depth, Some(10));
+        assert_eq!(video.hdr, Some("pq".to_string()));
+
+        assert_eq!(info.audio.len(), 2);
+        assert_eq!(info.audio[0].channels, Some(6));
+        assert_eq!(info.audio[0].channel_layout, Some("5.1(side)".to_string()));
+        assert_eq!(info.audio[0].bitrate_kbps, Some(640));
+        assert_eq!(info.audio[0].title, Some("Surround".to_string()));
+        assert_eq!(info.audio[1].channels, Some(2));
+        assert_eq!(info.audio[1].language, Some("jpn".to_string()));
+
+        assert_eq!(info.chapters.len(), 2);
+        assert_eq!(info.chapters[0].title, Some("Intro".to_string()));
+        assert_eq!(info.chapters[1].start, 300.0);
+        assert_eq!(info.chapters[1].title, None);
+    }
+
+    #[test]
+    fn test_parse_sdr_mp4_stream() {
+        let output = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'clip.mp4':
+  Duration: 00:00:10.00, start: 0.000000, bitrate: 2600 kb/s
+  Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p(tv, bt709, progressive), 1920x1080 [SAR 1:1 DAR 16:9], 2500 kb/s, 29.97 fps, 29.97 tbr, 30k tbn (default)";
+        let info = parse_media_info(output).unwrap();
+        let video = &info.video[0];
+        assert_eq!(video.pixel_format, Some("yuv420p".to_string()));
+        assert_eq!(video.bit_depth, Some(8));
+        assert_eq!(video.hdr, None);
+        assert_eq!(video.bitrate_kbps, Some(2500));
+        assert_eq!(video.fps, Some(29.97));
+    }
+
+    #[test]
+    fn test_pixel_format_bit_depth() {
+        assert_eq!(pixel_format_bit_depth("yuv420p10le"), 10);
+        assert_eq!(pixel_format_bit_depth("p010le"), 10);
+        assert_eq!(pixel_format_bit_depth("yuv444p12be"), 12);
+        assert_eq!(pixel_format_bit_depth("gray16le"), 16);
+        assert_eq!(pixel_format_bit_depth("yuv420p"), 8);
+        assert_eq!(pixel_format_bit_depth("yuv410p"), 8);
+        assert_eq!(pixel_format_bit_depth("nv12"), 8);
+        assert_eq!(pixel_format_bit_depth("nv16"), 8);
+    }
+
+    #[test]
+    fn test_split_details() {
+        assert_eq!(
+            split_details("yuv420p(tv, bt709), 1920x1080 [SAR 1:1, DAR 16:9], 30 fps"),
+            vec!["yuv420p(tv, bt709)", "1920x1080 [SAR 1:1, DAR 16:9]", "30 fps"]
+        );
+    }
+
+    #[test]
+    fn test_parse_media_info_rejects_errors() {
+        assert!(parse_media_info("missing.mp4: No such file or directory").is_none());
+    }
+}
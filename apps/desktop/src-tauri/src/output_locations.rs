@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::Path;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// How many explicitly chosen output folders are remembered
+pub const MAX_OUTPUT_LOCATIONS: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputLocationStatus {
+    pub path: String,
+    pub exists: bool,
+    pub writable: bool,
+}
+
+pub fn location_status(path: &str) -> OutputLocationStatus {
+    let dir = Path::new(path);
+    let exists = dir.is_dir();
+    OutputLocationStatus {
+        path: path.to_string(),
+        exists,
+        writable: exists && is_writable(dir),
+    }
+}
+
+/// Why a job's output can't be written, if it can't. The output folder itself
+/// may be missing since conversions create it, but the folder it would be
+/// created in has to exist (an unplugged drive, a deleted folder).
+pub fn output_problem(output_path: &str) -> Option<String> {
+    let output_dir = Path::new(output_path).parent()?;
+    if output_dir.is_dir() {
+        return (!is_writable(output_dir))
+            .then(|| format!("Output folder {} is not writable", output_dir.display()));
+    }
+
+    match output_dir.parent() {
+        Some(parent) if parent.is_dir() => (!is_writable(parent))
+            .then(|| format!("Output folder {} can't be created", output_dir.display())),
+        _ => Some(format!("Output folder {} is missing", output_dir.display())),
+    }
+}
+
+/// Permissions bits don't account for read-only volumes or ACLs, so try it
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".transpoze-write-test-{}", Uuid::new_v4()));
+    match fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
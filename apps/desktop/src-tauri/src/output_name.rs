@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+
+/// Characters that aren't allowed in file names on at least one platform
+const INVALID_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*', '\\', '/'];
+/// Used instead of an output pattern that can't produce a valid name
+const DEFAULT_PATTERN: &str = "{name}_converted";
+
+/// Replace the file name of `output_path` with a user-supplied one, keeping
+/// the directory and extension. Supports the `{name}` placeholder from the
+/// output pattern; `{number}` only makes sense for batches and is rejected.
+pub fn rename_output(input_path: &str, output_path: &str, file_name: &str) -> Result<String, String> {
+    let file_name = file_name.trim();
+    if file_name.is_empty() {
+        return Err("File name can't be empty".to_string());
+    }
+    if file_name.contains("{number}") {
+        return Err("{number} can only be used in the output pattern".to_string());
+    }
+
+    let input_stem = Path::new(input_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "video".to_string());
+    let name = file_name.replace("{name}", &input_stem);
+    if name.contains(['{', '}']) {
+        return Err("Unknown placeholder in file name".to_string());
+    }
+    if name.contains(INVALID_CHARS) || name.chars().any(char::is_control) || name == "." || name == ".." {
+        return Err("File name contains characters that aren't allowed".to_string());
+    }
+
+    let output = Path::new(output_path);
+    let extension = output.extension().map(|ext| ext.to_string_lossy().to_string());
+    // Users often type the extension themselves
+    let name = match &extension {
+        Some(ext) => name.strip_suffix(&format!(".{}", ext)).unwrap_or(&name).to_string(),
+        None => name,
+    };
+    let file_name = match extension {
+        Some(ext) => format!("{}.{}", name, ext),
+        None => name,
+    };
+
+    let renamed = match output.parent() {
+        Some(parent) => parent.join(file_name),
+        None => Path::new(&file_name).to_path_buf(),
+    };
+    Ok(renamed.to_string_lossy().to_string())
+}
+
+/// Placeholders an output pattern may use
+pub const PLACEHOLDERS: &[&str] = &["{name}", "{number}", "{preset}", "{date}", "{resolution}", "{codec}"];
+
+/// Where and under which name outputs are written, from the output settings
+pub struct OutputLayout<'a> {
+    /// Next to the input when empty
+    pub output_directory: &'a str,
+    pub subdirectory: Option<&'a str>,
+    pub pattern: &'a str,
+    pub extension: &'a str,
+    /// First value tried for `{number}`, e.g. the file's index in a batch
+    pub first_number: u32,
+    /// `{number}` is zero-padded to this many digits
+    pub number_width: usize,
+}
+
+/// Values for the placeholders other than `{name}` and `{number}`
+pub struct PatternValues<'a> {
+    pub preset: &'a str,
+    /// e.g. `2024-05-31`
+    pub date: &'a str,
+    /// e.g. `1080p`; left out when the size isn't known
+    pub resolution: Option<&'a str>,
+    pub codec: &'a str,
+}
+
+/// Output path for an input file, expanding the pattern placeholders. Used
+/// for every job and the settings preview, so they always agree. Numbers are
+/// added until `is_taken` rejects the path.
+pub fn output_path_for(
+    input_path: &Path,
+    layout: &OutputLayout,
+    values: &PatternValues,
+    is_taken: impl Fn(&Path) -> bool,
+) -> PathBuf {
+    let input_stem = input_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "video".to_string());
+
+    let mut dir = if layout.output_directory.is_empty() {
+        input_path.parent().map(Path::to_path_buf).unwrap_or_default()
+    } else {
+        PathBuf::from(layout.output_directory)
+    };
+    if let Some(subdirectory) = layout.subdirectory {
+        dir.push(subdirectory);
+    }
+
+    let pattern = if is_valid_pattern(layout.pattern) { layout.pattern } else { DEFAULT_PATTERN };
+    let name = pattern
+        .replace("{name}", &input_stem)
+        .replace("{preset}", &file_name_safe(values.preset))
+        .replace("{date}", values.date)
+        .replace("{resolution}", values.resolution.unwrap_or_default())
+        .replace("{codec}", values.codec);
+
+    let mut number = layout.first_number;
+    loop {
+        let padded = format!("{:0width$}", number, width = layout.number_width);
+        let file_name = if name.contains("{number}") {
+            name.replace("{number}", &padded)
+        } else if number == layout.first_number {
+            name.clone()
+        } else {
+            format!("{}-{}", name, padded)
+        };
+        let path = dir.join(format!("{}.{}", file_name, layout.extension));
+        if !is_taken(&path) {
+            return path;
+        }
+        number += 1;
+    }
+}
+
+/// Preset names are free text, so swap out anything a file name can't hold
+fn file_name_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if INVALID_CHARS.contains(&c) || c.is_control() { '-' } else { c })
+        .collect()
+}
+
+/// First free path of the form `clip (1).mp4`, `clip (2).mp4`, ... for an
+/// output whose name is already taken
+pub fn numbered_output_path(output_path: &str, is_taken: impl Fn(&Path) -> bool) -> String {
+    let path = Path::new(output_path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_string());
+
+    let mut number = 1u32;
+    loop {
+        let file_name = match &extension {
+            Some(ext) => format!("{} ({}).{}", stem, number, ext),
+            None => format!("{} ({})", stem, number),
+        };
+        let candidate = path.with_file_name(file_name);
+        if !is_taken(&candidate) {
+            return candidate.to_string_lossy().to_string();
+        }
+        number += 1;
+    }
+}
+
+/// Only known placeholders, and nothing a file name can't hold
+fn is_valid_pattern(pattern: &str) -> bool {
+    let literal = PLACEHOLDERS.iter().fold(pattern.to_string(), |literal, placeholder| literal.replace(placeholder, ""));
+    !pattern.trim().is_empty()
+        && !literal.contains(['{', '}'])
+        && !literal.contains(INVALID_CHARS)
+        && !literal.chars().any(char::is_control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_directory_and_extension() {
+        let renamed = rename_output("/videos/clip.mov", "/videos/converted/clip_converted.mp4", "holiday").unwrap();
+        assert_eq!(renamed, "/videos/converted/holiday.mp4");
+
+        let renamed = rename_output("/videos/clip.mov", "/videos/converted/clip_converted.mp4", "holiday.mp4").unwrap();
+        assert_eq!(renamed, "/videos/converted/holiday.mp4");
+    }
+
+    #[test]
+    fn expands_name_placeholder() {
+        let renamed = rename_output("/videos/clip.mov", "/out/clip_converted.mp4", "{name}-final").unwrap();
+        assert_eq!(renamed, "/out/clip-final.mp4");
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let output = "/out/clip_converted.mp4";
+        assert!(rename_output("/videos/clip.mov", output, "  ").is_err());
+        assert!(rename_output("/videos/clip.mov", output, "a/b").is_err());
+        assert!(rename_output("/videos/clip.mov", output, "clip-{number}").is_err());
+        assert!(rename_output("/videos/clip.mov", output, "{date}").is_err());
+    }
+
+    fn layout(pattern: &str) -> OutputLayout<'_> {
+        OutputLayout {
+            output_directory: "/out",
+            subdirectory: None,
+            pattern,
+            extension: "mp4",
+            first_number: 0,
+            number_width: 1,
+        }
+    }
+
+    const VALUES: PatternValues = PatternValues {
+        preset: "HEVC/High",
+        date: "2024-05-31",
+        resolution: Some("1080p"),
+        codec: "hevc",
+    };
+
+    #[test]
+    fn builds_output_paths_from_settings() {
+        let input = Path::new("/watch/clip.mov");
+        let free = |_: &Path| false;
+        let next_to_input = OutputLayout { output_directory: "", subdirectory: Some("converted"), ..layout("{name}_converted") };
+        assert_eq!(
+            output_path_for(input, &next_to_input, &VALUES, free),
+            PathBuf::from("/watch/converted/clip_converted.mp4")
+        );
+        let batch = OutputLayout { first_number: 3, number_width: 2, ..layout("{name}-{number}") };
+        assert_eq!(output_path_for(input, &batch, &VALUES, free), PathBuf::from("/out/clip-03.mp4"));
+        assert_eq!(output_path_for(input, &layout("{size}"), &VALUES, free), PathBuf::from("/out/clip_converted.mp4"));
+    }
+
+    #[test]
+    fn expands_all_placeholders() {
+        let path = output_path_for(
+            Path::new("/watch/clip.mov"),
+            &layout("{name}_{preset}_{date}_{resolution}_{codec}"),
+            &VALUES,
+            |_| false,
+        );
+        assert_eq!(path, PathBuf::from("/out/clip_HEVC-High_2024-05-31_1080p_hevc.mp4"));
+    }
+
+    #[test]
+    fn numbers_taken_output_paths() {
+        let taken = |path: &Path| path == Path::new("/out/clip_converted.mp4");
+        assert_eq!(
+            output_path_for(Path::new("/watch/clip.mov"), &layout("{name}_converted"), &VALUES, taken),
+            PathBuf::from("/out/clip_converted-1.mp4")
+        );
+    }
+
+    #[test]
+    fn numbers_conflicting_outputs() {
+        let taken = |path: &Path| path == Path::new("/out/clip (1).mp4");
+        assert_eq!(numbered_output_path("/out/clip.mp4", taken), "/out/clip (2).mp4");
+        assert_eq!(numbered_output_path("/out/clip", |_| false), "/out/clip (1)");
+    }
+}
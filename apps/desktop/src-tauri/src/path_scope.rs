@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_fs::FsExt;
+use crate::state::AppState;
+
+/// Resolve `path` and check that it's somewhere the user pointed the app at:
+/// next to a file they dropped or picked, inside a folder they picked or
+/// chose for output, or in the app's own data and cache directories. Paths
+/// that don't exist yet (outputs) are checked by their nearest existing parent.
+pub async fn ensure_allowed(app_handle: &AppHandle, state: &AppState, path: &str) -> Result<PathBuf, String> {
+    check(app_handle, path, allowed_roots(app_handle, state).await)
+}
+
+/// Stricter `ensure_allowed` for paths that widen the scope or get run:
+/// only folders granted on the Rust side through a drop, a native dialog
+/// or a bookmark, and the app's own directories. Paths the webview only
+/// names, like settings or queue files, never count.
+pub async fn ensure_granted(app_handle: &AppHandle, state: &AppState, path: &str) -> Result<PathBuf, String> {
+    check(app_handle, path, granted_roots(app_handle, state).await)
+}
+
+fn check(app_handle: &AppHandle, path: &str, roots: Vec<PathBuf>) -> Result<PathBuf, String> {
+    let resolved = resolve(Path::new(path))
+        .ok_or_else(|| format!("Invalid path: {}", path))?;
+
+    if app_handle.fs_scope().is_allowed(&resolved) {
+        return Ok(resolved);
+    }
+
+    if roots.iter().any(|root| resolved.starts_with(root)) {
+        return Ok(resolved);
+    }
+
+    eprintln!("Refusing access to {} outside the allowed folders", resolved.display());
+    Err(format!("Access to {} is not allowed", path))
+}
+
+/// Make a path absolute with symlinks and `..` resolved, even if the last
+/// components don't exist yet. `..` after a missing component is rejected.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() {
+        return None;
+    }
+
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+
+    let mut resolved = existing.canonicalize().ok()?;
+    resolved.extend(missing.iter().rev());
+    Some(resolved)
+}
+
+async fn granted_roots(app_handle: &AppHandle, state: &AppState) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = state.allowed_roots.lock()
+        .map(|roots| roots.iter().cloned().collect())
+        .unwrap_or_default();
+
+    let path = app_handle.path();
+    roots.extend([path.app_cache_dir(), path.app_data_dir()].into_iter().flatten());
+    roots.extend(state.folder_bookmarks.lock().await.keys().map(PathBuf::from));
+
+    canonical_roots(roots)
+}
+
+async fn allowed_roots(app_handle: &AppHandle, state: &AppState) -> Vec<PathBuf> {
+    let mut roots = granted_roots(app_handle, state).await;
+
+    // Output folders are checked with `ensure_granted` before they're saved
+    let settings = state.get_settings().await;
+    roots.extend(
+        std::iter::once(&settings.output_directory)
+            .chain(&settings.output_locations)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+    );
+
+    // Folders of files already in the queue or history, so their outputs can
+    // be checked and revealed
+    let parent = |file: &str| Path::new(file).parent().map(Path::to_path_buf);
+    for job in state.get_all_jobs().await {
+        roots.extend(parent(&job.input_path));
+        roots.extend(parent(&job.output_path));
+    }
+    for entry in state.get_history().await {
+        roots.extend(parent(&entry.output_path));
+    }
+
+    canonical_roots(roots)
+}
+
+fn canonical_roots(roots: Vec<PathBuf>) -> Vec<PathBuf> {
+    roots.into_iter()
+        .filter(|root| root.is_absolute() && root.parent().is_some())
+        .filter_map(|root| resolve(&root))
+        .collect()
+}
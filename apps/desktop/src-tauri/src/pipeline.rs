@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use crate::estimate;
+use crate::events::{emit_job_state, JobChange};
+use crate::ffmpeg::{self, generate_thumbnail, get_ffmpeg_binary, ConversionJob, CropRect, JobStatus, VideoProbe};
+use crate::state::AppState;
+use crate::status_message::StatusMessage;
+
+/// Thumbnails are taken this far into the video, past most fade-ins
+const THUMBNAIL_POSITION: f64 = 0.1;
+
+/// What analysis found out about a job's source. Every part is optional:
+/// a job whose analysis failed is still converted, so FFmpeg can report why.
+#[derive(Debug, Default)]
+struct Analysis {
+    probe: Option<VideoProbe>,
+    /// Thumbnail file and the time it was taken at
+    thumbnail: Option<(String, f64)>,
+    crop: Option<CropRect>,
+}
+
+/// Analyze a queued job in the background (probe, thumbnail, black bars),
+/// then hand it to the queue processor. Every job waits for an analysis
+/// slot, so a large drop never runs more probes at once than the setting allows.
+pub async fn start_analysis(app_handle: AppHandle, state: AppState, job_id: String, input_path: String) {
+    let tracker = state.clone();
+    let tracked_job_id = job_id.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let _analysis_slot = state.acquire_analysis_slot().await;
+        println!("Analyzing job {}", job_id);
+
+        let analysis = match get_ffmpeg_binary(&app_handle) {
+            Ok(ffmpeg_path) => analyze(&app_handle, &ffmpeg_path, &job_id, &input_path).await,
+            Err(e) => {
+                println!("Failed to get FFmpeg binary, setting job {} to ready anyway: {}", job_id, e);
+                Analysis::default()
+            }
+        };
+
+        // Apply to the job as it is now, keeping changes made while analyzing
+        let Some(mut job) = state.get_job(&job_id).await else {
+            return;
+        };
+        apply_analysis(&mut job, analysis);
+        job.estimate = estimate::estimate_output(&job, &state.get_throughput().await);
+        state.update_job(job).await;
+        emit_job_state(&app_handle, &state, &job_id, JobChange::Updated).await;
+        state.wake_queue();
+    });
+    tracker.track_analysis(tracked_job_id, task).await;
+}
+
+async fn analyze(app_handle: &AppHandle, ffmpeg_path: &Path, job_id: &str, input_path: &str) -> Analysis {
+    let probe = match ffmpeg::probe_video(ffmpeg_path, input_path).await {
+        Ok(probe) => probe,
+        Err(e) => {
+            println!("Failed to probe {}, setting job {} to ready anyway: {}", input_path, job_id, e);
+            return Analysis::default();
+        }
+    };
+    let duration = probe.duration;
+
+    let thumbnail = match thumbnail_path(app_handle, job_id) {
+        Ok(thumbnail_path) => {
+            let time = duration * THUMBNAIL_POSITION;
+            match generate_thumbnail(ffmpeg_path, input_path, &thumbnail_path, &time.to_string()).await {
+                Ok(()) => Some((thumbnail_path, time)),
+                Err(e) => {
+                    println!("Failed to generate thumbnail for job {}: {}", job_id, e);
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            println!("Failed to generate thumbnail for job {}: {}", job_id, e);
+            None
+        }
+    };
+
+    let crop = match ffmpeg::detect_crop(ffmpeg_path, input_path, duration).await {
+        Ok(crop) => {
+            if let Some(crop) = &crop {
+                println!("Detected black bars for job {}, suggested {}", job_id, crop.to_filter());
+            }
+            crop
+        }
+        Err(e) => {
+            println!("Failed to detect black bars: {}", e);
+            None
+        }
+    };
+
+    Analysis { probe: Some(probe), thumbnail, crop }
+}
+
+fn thumbnail_path(app_handle: &AppHandle, job_id: &str) -> Result<String, String> {
+    let thumbnail_dir = app_handle.path().app_cache_dir()
+        .map_err(|e| format!("Failed to get cache dir: {}", e))?
+        .join("thumbnails");
+    fs::create_dir_all(&thumbnail_dir)
+        .map_err(|e| format!("Failed to create thumbnail directory: {}", e))?;
+    Ok(thumbnail_dir.join(format!("{}.jpg", job_id)).to_string_lossy().to_string())
+}
+
+/// Fill in the analysis results and let the queue processor pick the job up.
+/// Jobs cancelled while they were being analyzed stay cancelled.
+fn apply_analysis(job: &mut ConversionJob, analysis: Analysis) {
+    if let Some(probe) = analysis.probe {
+        job.apply_probe(probe);
+        job.warnings = ffmpeg::stream_warnings(job);
+    }
+    if let Some((path, time)) = analysis.thumbnail {
+        job.thumbnail_path = Some(path);
+        job.thumbnail_time = Some(time);
+    }
+    job.detected_crop = analysis.crop;
+
+    if matches!(job.status, JobStatus::Queued) {
+        job.status = JobStatus::Ready;
+        job.status_message = Some(StatusMessage::ReadyToConvert);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::{JobOptions, VideoPreset};
+
+    fn job(status: JobStatus) -> ConversionJob {
+        let preset = VideoPreset::get_presets().remove(0);
+        let mut job = crate::new_job("job".to_string(), "/in.mov".to_string(), "/out.mp4".to_string(), preset, JobOptions::default(), None);
+        job.status = status;
+        job
+    }
+
+    fn probe() -> VideoProbe {
+        VideoProbe {
+            duration: 12.5,
+            width: Some(1920),
+            height: Some(1080),
+            video_codec: Some("h264".to_string()),
+            fps: Some(30.0),
+            rotation: None,
+            bitrate_kbps: Some(8000),
+            audio_channels: Some(2),
+            file_size: Some(12_500_000),
+            streams: Vec::new(),
+            timecode: None,
+        }
+    }
+
+    #[test]
+    fn fills_in_results_and_marks_ready() {
+        let mut job = job(JobStatus::Queued);
+        let analysis = Analysis {
+            probe: Some(probe()),
+            thumbnail: Some(("/cache/job.jpg".to_string(), 1.25)),
+            crop: Some(CropRect { width: 1920, height: 800, x: 0, y: 140 }),
+        };
+        apply_analysis(&mut job, analysis);
+
+        assert!(matches!(job.status, JobStatus::Ready));
+        assert_eq!(job.duration, Some(12.5));
+        assert_eq!(job.height, Some(1080));
+        assert_eq!(job.thumbnail_path.as_deref(), Some("/cache/job.jpg"));
+        assert_eq!(job.thumbnail_time, Some(1.25));
+        assert_eq!(job.detected_crop.map(|crop| crop.height), Some(800));
+    }
+
+    #[test]
+    fn failed_analysis_still_makes_the_job_ready() {
+        let mut job = job(JobStatus::Queued);
+        apply_analysis(&mut job, Analysis::default());
+        assert!(matches!(job.status, JobStatus::Ready));
+        assert_eq!(job.duration, None);
+    }
+
+    #[test]
+    fn cancelled_jobs_stay_cancelled() {
+        let mut job = job(JobStatus::Cancelled);
+        apply_analysis(&mut job, Analysis { probe: Some(probe()), ..Analysis::default() });
+        assert!(matches!(job.status, JobStatus::Cancelled));
+        assert_eq!(job.duration, Some(12.5));
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use crate::script::Shell;
+
+/// What to do once the last job of a run has finished
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PostQueueAction {
+    #[default]
+    Nothing,
+    /// Show the last output in Finder or Explorer
+    RevealOutputFolder,
+    Sleep,
+    ShutDown,
+    /// Run a shell script with the run's output paths as arguments
+    RunScript { script: String },
+}
+
+impl PostQueueAction {
+    /// Make sure a script action points at a file that can be run
+    pub fn validate(&self) -> Result<(), String> {
+        if let PostQueueAction::RunScript { script } = self {
+            if script.trim().is_empty() {
+                return Err("Choose a script to run after the queue".to_string());
+            }
+            if !Path::new(script).is_file() {
+                return Err(format!("Script {} not found", script));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run the action for a finished run. `outputs` are the files it converted,
+/// in the order they finished.
+pub async fn run(action: &PostQueueAction, outputs: &[String]) -> Result<(), String> {
+    match action {
+        PostQueueAction::Nothing => Ok(()),
+        PostQueueAction::RevealOutputFolder => match outputs.last() {
+            Some(output) => crate::reveal_path(output),
+            None => Ok(()),
+        },
+        PostQueueAction::Sleep => {
+            let (program, args) = sleep_command();
+            spawn(program, args)
+        }
+        PostQueueAction::ShutDown => {
+            let (program, args) = shutdown_command();
+            spawn(program, args)
+        }
+        PostQueueAction::RunScript { script } => {
+            let (program, args) = script_command(Shell::for_script(script), script, outputs);
+            let status = Command::new(&program)
+                .args(&args)
+                .status()
+                .await
+                .map_err(|e| format!("Failed to run {}: {}", script, e))?;
+            if !status.success() {
+                return Err(format!("{} exited with {}", script, status));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn spawn(program: &str, args: &[&str]) -> Result<(), String> {
+    std::process::Command::new(program)
+        .args(args)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run {}: {}", program, e))
+}
+
+fn sleep_command() -> (&'static str, &'static [&'static str]) {
+    if cfg!(target_os = "macos") {
+        ("pmset", &["sleepnow"])
+    } else if cfg!(windows) {
+        ("rundll32.exe", &["powrprof.dll,SetSuspendState", "0,1,0"])
+    } else {
+        ("systemctl", &["suspend"])
+    }
+}
+
+fn shutdown_command() -> (&'static str, &'static [&'static str]) {
+    if cfg!(target_os = "macos") {
+        // Asks running apps to quit like the Apple menu does, unlike `shutdown -h`
+        ("osascript", &["-e", "tell application \"System Events\" to shut down"])
+    } else if cfg!(windows) {
+        ("shutdown", &["/s", "/t", "0"])
+    } else {
+        ("systemctl", &["poweroff"])
+    }
+}
+
+/// Program and arguments that run `script` with `outputs` appended
+fn script_command(shell: Shell, script: &str, outputs: &[String]) -> (String, Vec<String>) {
+    let mut args = match shell {
+        Shell::Posix => vec![script.to_string()],
+        Shell::PowerShell => vec![
+            "-NoProfile".to_string(),
+            "-ExecutionPolicy".to_string(),
+            "Bypass".to_string(),
+            "-File".to_string(),
+            script.to_string(),
+        ],
+    };
+    args.extend(outputs.iter().cloned());
+
+    let program = match shell {
+        Shell::Posix => "bash",
+        Shell::PowerShell => "powershell",
+    };
+    (program.to_string(), args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_outputs_after_the_script() {
+        let outputs = vec!["/out/a.mp4".to_string(), "/out/b c.mp4".to_string()];
+        let (program, args) = script_command(Shell::Posix, "/scripts/done.sh", &outputs);
+        assert_eq!(program, "bash");
+        assert_eq!(args, ["/scripts/done.sh", "/out/a.mp4", "/out/b c.mp4"]);
+
+        let (program, args) = script_command(Shell::PowerShell, r"C:\done.ps1", &outputs);
+        assert_eq!(program, "powershell");
+        assert_eq!(args[3..], [r"C:\done.ps1", "/out/a.mp4", "/out/b c.mp4"]);
+    }
+
+    #[test]
+    fn reads_tagged_actions() {
+        let action: PostQueueAction = serde_json::from_str(r#"{"kind":"runScript","script":"/s.sh"}"#).unwrap();
+        assert_eq!(action, PostQueueAction::RunScript { script: "/s.sh".to_string() });
+        let action: PostQueueAction = serde_json::from_str(r#"{"kind":"shutDown"}"#).unwrap();
+        assert_eq!(action, PostQueueAction::ShutDown);
+    }
+}
@@ -0,0 +1,48 @@
+use serde_json::Value;
+
+/// Version of the preset fields as written to share strings and queue files.
+/// Bump it and add a step to `MIGRATIONS` whenever a preset field is renamed,
+/// removed or changes meaning; purely additive `#[serde(default)]` fields
+/// don't need a new version.
+pub const PRESET_SCHEMA_VERSION: u32 = 1;
+
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// `MIGRATIONS[n]` upgrades a preset from version `n` to `n + 1`
+const MIGRATIONS: [Migration; PRESET_SCHEMA_VERSION as usize] = [from_unversioned];
+
+/// Upgrade a serialized preset written under an older schema to the current
+/// one, one version at a time.
+pub fn migrate_preset(mut preset: Value, from_version: u32) -> Result<Value, String> {
+    if from_version > PRESET_SCHEMA_VERSION {
+        return Err("Preset was made with a newer version of Transpoze".to_string());
+    }
+    if !preset.is_object() {
+        return Err("Preset is not an object".to_string());
+    }
+
+    for migration in &MIGRATIONS[from_version as usize..] {
+        preset = migration(preset)?;
+    }
+    Ok(preset)
+}
+
+/// Presets saved before versioning have the same fields as version 1
+fn from_unversioned(preset: Value) -> Result<Value, String> {
+    Ok(preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_old_presets_and_rejects_newer_ones() {
+        let preset = json!({ "name": "Mine", "videoCodec": "libx264" });
+        assert_eq!(migrate_preset(preset.clone(), 0).unwrap(), preset);
+        assert_eq!(migrate_preset(preset.clone(), PRESET_SCHEMA_VERSION).unwrap(), preset);
+        assert!(migrate_preset(preset, PRESET_SCHEMA_VERSION + 1).is_err());
+        assert!(migrate_preset(json!("High"), 0).is_err());
+    }
+}
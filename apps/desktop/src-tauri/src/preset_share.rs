@@ -0,0 +1,98 @@
+use base64::{Engine as _, engine::general_purpose};
+use serde::Serialize;
+use crate::ffmpeg::VideoPreset;
+use crate::filters::validate_extra_args;
+use crate::preset_schema::{migrate_preset, PRESET_SCHEMA_VERSION};
+
+/// Marks a string as a Transpoze preset so random base64 isn't mistaken for one
+const SHARE_PREFIX: &str = "tpz1:";
+
+#[derive(Serialize)]
+struct SharedPreset {
+    /// Preset schema version the preset was written with
+    #[serde(rename = "v")]
+    version: u32,
+    preset: VideoPreset,
+}
+
+/// Compact, copy-pasteable form of a preset
+pub fn encode_preset(preset: &VideoPreset) -> Result<String, String> {
+    let shared = SharedPreset { version: PRESET_SCHEMA_VERSION, preset: preset.clone() };
+    let json = serde_json::to_vec(&shared)
+        .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+    Ok(format!("{}{}", SHARE_PREFIX, general_purpose::URL_SAFE_NO_PAD.encode(json)))
+}
+
+pub fn decode_preset(shared: &str) -> Result<VideoPreset, String> {
+    let encoded = shared.trim()
+        .strip_prefix(SHARE_PREFIX)
+        .ok_or_else(|| "Not a Transpoze preset".to_string())?;
+    let json = general_purpose::URL_SAFE_NO_PAD.decode(encoded.trim_end_matches('='))
+        .map_err(|_| "Preset is damaged or incomplete".to_string())?;
+
+    let mut value: serde_json::Value = serde_json::from_slice(&json)
+        .map_err(|_| "Preset is damaged or incomplete".to_string())?;
+    let version = value.get("v").and_then(|v| v.as_u64()).unwrap_or(0);
+    let preset = value.get_mut("preset")
+        .map(serde_json::Value::take)
+        .ok_or_else(|| "Preset is damaged or incomplete".to_string())?;
+
+    let preset = migrate_preset(preset, u32::try_from(version).unwrap_or(u32::MAX))?;
+    let preset: VideoPreset = serde_json::from_value(preset)
+        .map_err(|e| format!("Invalid preset: {}", e))?;
+    validate_preset(&preset)?;
+    // Someone else's FFmpeg options shouldn't run just by pasting a preset
+    if !preset.extra_args.is_empty() {
+        return Err("Shared presets can't carry extra FFmpeg options".to_string());
+    }
+    Ok(preset)
+}
+
+/// Presets from other people or hand-edited files end up as FFmpeg
+/// arguments, so only accept values that look like what the preset editor
+/// produces.
+pub fn validate_preset(preset: &VideoPreset) -> Result<(), String> {
+    if preset.name.trim().is_empty() {
+        return Err("Preset has no name".to_string());
+    }
+
+    let is_codec = |codec: &str| !codec.is_empty()
+        && codec.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && !codec.starts_with('-');
+    if !is_codec(&preset.video_codec) || !is_codec(&preset.audio_codec) {
+        return Err("Preset has an invalid codec".to_string());
+    }
+
+    if preset.crf.is_some_and(|crf| crf > 63) {
+        return Err("Preset CRF must be between 0 and 63".to_string());
+    }
+
+    let is_bitrate = |bitrate: &str| {
+        let digits = bitrate.strip_suffix(['k', 'K', 'm', 'M']).unwrap_or(bitrate);
+        !digits.is_empty() && digits.parse::<f64>().is_ok_and(|value| value > 0.0)
+    };
+    if preset.bitrate.as_deref().is_some_and(|bitrate| !is_bitrate(bitrate))
+        || preset.audio_bitrate.as_deref().is_some_and(|bitrate| !is_bitrate(bitrate))
+    {
+        return Err("Preset has an invalid bitrate".to_string());
+    }
+
+    if preset.container.as_deref().is_some_and(|container| {
+        container.is_empty() || !container.chars().all(|c| c.is_ascii_alphanumeric())
+    }) {
+        return Err("Preset has an invalid container".to_string());
+    }
+
+    if let Some(scale) = &preset.scale {
+        let valid = scale.split_once(':').is_some_and(|(width, height)| {
+            width.parse::<i32>().is_ok() && height.parse::<i32>().is_ok()
+        });
+        if !valid {
+            return Err("Preset has an invalid scale".to_string());
+        }
+    }
+
+    validate_extra_args(&preset.extra_args)?;
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::error::Error;
+use crate::ffmpeg::{analysis_command, ConversionJob};
+use crate::ffmpeg_parser::{parse_ssim_score, parse_vmaf_score};
+use crate::filters::{applied_detected_crop, output_duration_factor};
+
+/// How outputs are compared with their source after converting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QualityMetric {
+    #[default]
+    Off,
+    /// Structural similarity from 0 to 1. Every FFmpeg build has it.
+    Ssim,
+    /// Perceptual score from 0 to 100. Needs FFmpeg built with libvmaf.
+    Vmaf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityScore {
+    pub metric: QualityMetric,
+    pub score: f64,
+}
+
+/// Why an output can't be compared with its source frame by frame
+fn not_comparable(job: &ConversionJob, auto_crop: bool) -> Option<&'static str> {
+    if job.preset.video_codec == "copy" || job.options.fast_cut {
+        Some("the video was copied, not re-encoded")
+    } else if job.preset.is_animation() {
+        Some("animations drop frames and colors")
+    } else if output_duration_factor(job) != 1.0 {
+        Some("loop and boomerang change the timeline")
+    } else if job.options.crop_edges.is_some_and(|edges| !edges.is_empty())
+        || job.options.pad_edges.is_some_and(|edges| !edges.is_empty())
+        || applied_detected_crop(job, auto_crop).is_some()
+    {
+        Some("cropping or padding changes the picture")
+    } else {
+        None
+    }
+}
+
+/// Compare a finished output with the part of the source it was made from.
+/// Outputs scaled by the preset are scaled back to the source size first.
+/// None when the job changed the video in a way the metric can't follow.
+/// `auto_crop` is the setting the job was converted with.
+pub async fn score_output(ffmpeg_path: &Path, job: &ConversionJob, output_path: &str, metric: QualityMetric, auto_crop: bool) -> Result<Option<QualityScore>, Error> {
+    let comparison = match metric {
+        QualityMetric::Off => return Ok(None),
+        QualityMetric::Ssim => "ssim",
+        QualityMetric::Vmaf => "libvmaf",
+    };
+    if let Some(reason) = not_comparable(job, auto_crop) {
+        println!("Not scoring the quality of job {}: {}", job.id, reason);
+        return Ok(None);
+    }
+
+    let mut command = analysis_command(ffmpeg_path);
+    command.args(["-hide_banner", "-nostats", "-i", output_path]);
+    let range = job.resolved_trim
+        .map(|range| (Some(range.start), Some(range.end).filter(|end| end.is_finite())))
+        .unwrap_or((job.options.trim_start, job.options.trim_end));
+    if let (Some(start), _) = range {
+        command.args(["-ss", &start.to_string()]);
+    }
+    if let (_, Some(end)) = range {
+        command.args(["-to", &end.to_string()]);
+    }
+    // libvmaf and ssim take the distorted input first and the reference second
+    let graph = format!(
+        "[0:v][1:v]scale2ref=flags=bicubic[distorted][reference];[distorted][reference]{}",
+        comparison
+    );
+    let output = command
+        .args(["-i", &job.input_path, "-lavfi", &graph, "-an", "-f", "null", "-"])
+        .output()
+        .await
+        .map_err(Error::SpawnFailed)?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        let reason = stderr.lines().rev().find(|line| !line.trim().is_empty()).map(str::to_string);
+        return Err(Error::EncodeFailed { stderr: reason });
+    }
+
+    let parse = match metric {
+        QualityMetric::Vmaf => parse_vmaf_score,
+        _ => parse_ssim_score,
+    };
+    let score = stderr.lines().rev().find_map(parse)
+        .ok_or_else(|| Error::Parse(format!("FFmpeg reported no {} score", comparison)))?;
+    Ok(Some(QualityScore { metric, score }))
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::ffmpeg::{ConversionJob, JobStatus};
+use crate::state::ConversionHistory;
+
+/// Criteria for `query_jobs`. Empty fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JobFilter {
+    pub statuses: Vec<JobStatus>,
+    /// Case-insensitive match against the input file name
+    pub search: Option<String>,
+    pub preset: Option<String>,
+    pub batch_id: Option<String>,
+}
+
+impl JobFilter {
+    pub fn matches(&self, job: &ConversionJob) -> bool {
+        if !self.statuses.is_empty() && !self.statuses.contains(&job.status) {
+            return false;
+        }
+        if self.preset.as_ref().is_some_and(|preset| *preset != job.preset.name) {
+            return false;
+        }
+        if self.batch_id.is_some() && self.batch_id != job.batch_id {
+            return false;
+        }
+        if let Some(search) = self.search.as_deref().map(str::trim).filter(|search| !search.is_empty()) {
+            let file_name = Path::new(&job.input_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !file_name.contains(&search.to_lowercase()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Jobs matching the filter, in queue order
+pub fn filter_jobs(jobs: Vec<ConversionJob>, filter: &JobFilter) -> Vec<ConversionJob> {
+    jobs.into_iter().filter(|job| filter.matches(job)).collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HistorySortKey {
+    #[default]
+    Date,
+    SizeSaved,
+    /// Output size relative to the input size
+    CompressionRatio,
+    Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Ties keep the order the entries were added in
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistorySort {
+    pub by: HistorySortKey,
+    pub direction: SortDirection,
+}
+
+/// Criteria for `query_history`. Empty fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryFilter {
+    pub statuses: Vec<JobStatus>,
+    /// Case-insensitive match against the input and output file names
+    pub search: Option<String>,
+    pub preset: Option<String>,
+    /// RFC 3339 timestamps; entries completed at either bound are included
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// A slice of the matching history entries
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub items: Vec<ConversionHistory>,
+    /// Entries matching the filter across all pages
+    pub total: usize,
+}
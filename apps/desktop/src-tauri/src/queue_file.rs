@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::ffmpeg::{ConversionJob, JobOptions, VideoPreset};
+use crate::preset_schema::{migrate_preset, PRESET_SCHEMA_VERSION};
+use crate::preset_share::validate_preset;
+
+/// Bumped whenever the file layout changes incompatibly
+pub const QUEUE_FILE_VERSION: u32 = 1;
+
+/// A saved set of pending jobs that can be imported again later, possibly
+/// on another machine. Only what the user chose is stored; probe results are
+/// redone on import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueFile {
+    pub version: u32,
+    /// Schema version of the presets in `jobs`. Version 1 queue files were
+    /// written before presets were versioned and don't have it.
+    #[serde(default)]
+    pub preset_schema_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub jobs: Vec<QueueFileJob>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueFileJob {
+    pub input_path: String,
+    pub output_path: String,
+    pub preset: VideoPreset,
+    #[serde(default)]
+    pub options: JobOptions,
+}
+
+impl QueueFile {
+    pub fn from_jobs<'a>(jobs: impl IntoIterator<Item = &'a ConversionJob>) -> Self {
+        QueueFile {
+            version: QUEUE_FILE_VERSION,
+            preset_schema_version: PRESET_SCHEMA_VERSION,
+            exported_at: Utc::now(),
+            jobs: jobs.into_iter()
+                .map(|job| QueueFileJob {
+                    input_path: job.input_path.clone(),
+                    output_path: job.output_path.clone(),
+                    preset: job.preset.clone(),
+                    options: job.options.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn parse(json: &str) -> Result<Self, String> {
+        // Check the version first so a newer file gets a clear error instead
+        // of whatever field happens to fail to deserialize
+        let mut value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| format!("Not a queue file: {}", e))?;
+        let version = value.get("version")
+            .and_then(|version| version.as_u64())
+            .ok_or_else(|| "Not a queue file: missing version".to_string())?;
+        if version > u64::from(QUEUE_FILE_VERSION) {
+            return Err(format!("Queue file version {} needs a newer version of Transpoze", version));
+        }
+
+        let preset_version = value.get("presetSchemaVersion")
+            .and_then(|version| version.as_u64())
+            .unwrap_or(0);
+        let preset_version = u32::try_from(preset_version).unwrap_or(u32::MAX);
+        if let Some(jobs) = value.get_mut("jobs").and_then(|jobs| jobs.as_array_mut()) {
+            for job in jobs {
+                if let Some(preset) = job.get_mut("preset") {
+                    *preset = migrate_preset(preset.take(), preset_version)?;
+                }
+            }
+        }
+
+        let mut queue_file: QueueFile = serde_json::from_value(value)
+            .map_err(|e| format!("Invalid queue file: {}", e))?;
+        // Like shared presets, imported jobs can't bring their own FFmpeg options
+        if queue_file.jobs.iter().any(|job| !job.preset.extra_args.is_empty() || !job.options.extra_args.is_empty()) {
+            return Err("Queue files can't carry extra FFmpeg options".to_string());
+        }
+        // The presets turn into FFmpeg arguments, so check them like a saved preset
+        for job in &queue_file.jobs {
+            validate_preset(&job.preset)
+                .map_err(|e| format!("Invalid preset for {}: {}", job.input_path, e))?;
+        }
+        queue_file.preset_schema_version = PRESET_SCHEMA_VERSION;
+        Ok(queue_file)
+    }
+}
+
+/// Outcome of importing a queue file
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueImport {
+    pub job_ids: Vec<String>,
+    /// Inputs that don't exist on this machine and were skipped
+    pub missing_inputs: Vec<String>,
+    /// Inputs skipped because their folders weren't dropped or picked
+    pub not_allowed_inputs: Vec<String>,
+}
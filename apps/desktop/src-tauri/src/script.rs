@@ -0,0 +1,158 @@
+use std::path::Path;
+use chrono::Local;
+use serde::Serialize;
+
+/// Shell a command line or exported queue script is written for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Posix,
+    PowerShell,
+}
+
+impl Shell {
+    /// The shell a user of this platform most likely pastes into
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Shell::PowerShell
+        } else {
+            Shell::Posix
+        }
+    }
+
+    /// PowerShell for `.ps1` files, bash for everything else
+    pub fn for_script(path: &str) -> Self {
+        let is_ps1 = Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ps1"));
+        if is_ps1 {
+            Shell::PowerShell
+        } else {
+            Shell::Posix
+        }
+    }
+
+    pub fn quote(self, arg: &str) -> String {
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=+,@%".contains(c);
+        if !arg.is_empty() && arg.chars().all(is_safe) {
+            return arg.to_string();
+        }
+        match self {
+            Shell::Posix => format!("'{}'", arg.replace('\'', r"'\''")),
+            // Single-quoted PowerShell strings only treat ' specially
+            Shell::PowerShell => format!("'{}'", arg.replace('\'', "''")),
+        }
+    }
+
+    pub fn command_line(self, program: &str, args: &[String]) -> String {
+        let mut line = match self {
+            Shell::Posix => String::new(),
+            // PowerShell needs the call operator to run a quoted program path
+            Shell::PowerShell => "& ".to_string(),
+        };
+        line.push_str(&self.quote(program));
+        for arg in args {
+            line.push(' ');
+            line.push_str(&self.quote(arg));
+        }
+        line
+    }
+}
+
+/// An FFmpeg command shown to the user instead of being run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandPreview {
+    pub program: String,
+    pub args: Vec<String>,
+    /// The command quoted for the native shell, ready to paste
+    pub command_line: String,
+}
+
+impl CommandPreview {
+    pub fn new(program: &str, args: Vec<String>) -> Self {
+        let command_line = Shell::native().command_line(program, &args);
+        CommandPreview { program: program.to_string(), args, command_line }
+    }
+}
+
+/// One conversion in an exported script
+pub struct ScriptCommand {
+    pub label: String,
+    pub output_path: String,
+    pub args: Vec<String>,
+}
+
+/// A standalone script that runs the given conversions one after another
+/// with whatever `ffmpeg` is on the PATH, stopping at the first failure.
+pub fn queue_script(shell: Shell, commands: &[ScriptCommand]) -> String {
+    let mut script = String::new();
+    let header = format!(
+        "# Exported from Transpoze on {} ({} job{})",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        commands.len(),
+        if commands.len() == 1 { "" } else { "s" },
+    );
+
+    match shell {
+        Shell::Posix => {
+            script.push_str("#!/usr/bin/env bash\n");
+            script.push_str(&header);
+            script.push_str("\nset -e\n");
+        }
+        Shell::PowerShell => {
+            script.push_str(&header);
+            script.push_str("\n$ErrorActionPreference = 'Stop'\n");
+        }
+    }
+
+    for (index, command) in commands.iter().enumerate() {
+        let progress = format!("[{}/{}] {}", index + 1, commands.len(), command.label);
+        let output_dir = Path::new(&command.output_path)
+            .parent()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .filter(|dir| !dir.is_empty());
+
+        script.push('\n');
+        match shell {
+            Shell::Posix => {
+                script.push_str(&format!("echo {}\n", shell.quote(&progress)));
+                if let Some(dir) = output_dir {
+                    script.push_str(&format!("mkdir -p {}\n", shell.quote(&dir)));
+                }
+                script.push_str(&shell.command_line("ffmpeg", &command.args));
+                script.push('\n');
+            }
+            Shell::PowerShell => {
+                script.push_str(&format!("Write-Host {}\n", shell.quote(&progress)));
+                if let Some(dir) = output_dir {
+                    script.push_str(&format!("New-Item -ItemType Directory -Force -Path {} | Out-Null\n", shell.quote(&dir)));
+                }
+                script.push_str(&shell.command_line("ffmpeg", &command.args));
+                // Native commands don't trip $ErrorActionPreference
+                script.push_str("\nif ($LASTEXITCODE -ne 0) { exit $LASTEXITCODE }\n");
+            }
+        }
+    }
+
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_only_when_needed() {
+        assert_eq!(Shell::Posix.quote("-c:v"), "-c:v");
+        assert_eq!(Shell::Posix.quote("/Users/me/My Movies/it's.mov"), r"'/Users/me/My Movies/it'\''s.mov'");
+        assert_eq!(Shell::PowerShell.quote(r"C:\Videos\it's.mov"), r"'C:\Videos\it''s.mov'");
+        assert_eq!(Shell::Posix.quote(""), "''");
+    }
+
+    #[test]
+    fn picks_shell_from_extension() {
+        assert_eq!(Shell::for_script("/tmp/queue.PS1"), Shell::PowerShell);
+        assert_eq!(Shell::for_script("/tmp/queue.sh"), Shell::Posix);
+        assert!(Shell::PowerShell.command_line("ffmpeg", &["-y".to_string()]).starts_with("& ffmpeg"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+use serde::Serialize;
+use tauri::AppHandle;
+use tokio::process::Command;
+use crate::ffmpeg::{get_ffmpeg_binary, probe_video, EncoderSpeed, VideoPreset};
+use crate::environment::check_output_directory;
+use crate::state::AppSettings;
+use crate::work_dir::{ensure_free_space, work_dir, MIN_FREE_SPACE};
+
+/// Name of the preset the app selects by default
+const DEFAULT_PRESET: &str = "Balanced";
+const TEST_CLIP_SECONDS: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SelfTestCheck {
+    /// FFmpeg is there and runs
+    Binary,
+    /// The configured output folder can be written to
+    OutputDirectory,
+    /// A generated test clip converts with the default preset
+    Encode,
+    /// The converted clip decodes without errors
+    Decode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestStep {
+    pub check: SelfTestCheck,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub steps: Vec<SelfTestStep>,
+}
+
+impl SelfTestReport {
+    fn push(&mut self, check: SelfTestCheck, result: Result<(), String>) -> bool {
+        let passed = result.is_ok();
+        self.passed &= passed;
+        self.steps.push(SelfTestStep { check, passed, message: result.err() });
+        passed
+    }
+}
+
+/// Convert a generated clip end to end in the work folder. Stops at the
+/// first failing step that later ones depend on.
+pub async fn run_self_test(app_handle: &AppHandle, settings: &AppSettings) -> SelfTestReport {
+    let mut report = SelfTestReport { passed: true, steps: Vec::new() };
+
+    let ffmpeg_path = get_ffmpeg_binary(app_handle);
+    let binary = match &ffmpeg_path {
+        Ok(path) => run_ffmpeg(path, &["-hide_banner", "-version"]).await.map(|_| ()),
+        Err(e) => Err(e.to_string()),
+    };
+    let binary_ok = report.push(SelfTestCheck::Binary, binary);
+    report.push(SelfTestCheck::OutputDirectory, check_output_directory(&settings.output_directory).map(|_| ()));
+    let (true, Ok(ffmpeg_path)) = (binary_ok, ffmpeg_path) else {
+        return report;
+    };
+
+    let work_dir = match work_dir(app_handle, settings) {
+        Ok(dir) => dir,
+        Err(e) => {
+            report.push(SelfTestCheck::Encode, Err(e));
+            return report;
+        }
+    };
+    if let Err(e) = ensure_free_space(&work_dir, MIN_FREE_SPACE).await {
+        report.push(SelfTestCheck::Encode, Err(e));
+        return report;
+    }
+    let test_dir = work_dir.join("self-test");
+    if let Err(e) = std::fs::create_dir_all(&test_dir) {
+        report.push(SelfTestCheck::Encode, Err(format!("Failed to create test folder: {}", e)));
+        return report;
+    }
+
+    let output = test_dir.join("self-test.mp4");
+    if report.push(SelfTestCheck::Encode, encode_test_clip(&ffmpeg_path, &output, settings.encoder_speed).await) {
+        report.push(SelfTestCheck::Decode, verify_decodes(&ffmpeg_path, &output).await);
+    }
+
+    let _ = std::fs::remove_dir_all(&test_dir);
+    report
+}
+
+async fn encode_test_clip(ffmpeg_path: &Path, output: &Path, speed: EncoderSpeed) -> Result<(), String> {
+    let preset = VideoPreset::get_presets()
+        .into_iter()
+        .find(|preset| preset.name == DEFAULT_PRESET)
+        .ok_or_else(|| format!("Preset {} not found", DEFAULT_PRESET))?;
+
+    let source = format!("testsrc=duration={}:size=320x240:rate=25", TEST_CLIP_SECONDS);
+    let tone = format!("sine=frequency=440:duration={}", TEST_CLIP_SECONDS);
+    let mut args: Vec<String> = ["-hide_banner", "-y", "-f", "lavfi", "-i", &source, "-f", "lavfi", "-i", &tone]
+        .map(String::from)
+        .to_vec();
+    args.extend(preset.to_ffmpeg_args(speed));
+    args.extend(["-pix_fmt".to_string(), "yuv420p".to_string()]);
+    args.push(output.to_string_lossy().into_owned());
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_ffmpeg(ffmpeg_path, &args).await.map(|_| ())
+}
+
+async fn verify_decodes(ffmpeg_path: &Path, output: &Path) -> Result<(), String> {
+    let output = output.to_string_lossy();
+    let errors = run_ffmpeg(ffmpeg_path, &["-hide_banner", "-v", "error", "-i", &output, "-f", "null", "-"]).await?;
+    if let Some(line) = errors.lines().find(|line| !line.trim().is_empty()) {
+        return Err(format!("Converted clip doesn't decode cleanly: {}", line.trim()));
+    }
+
+    let probe = probe_video(ffmpeg_path, &output).await?;
+    if (probe.duration - TEST_CLIP_SECONDS).abs() > 0.5 {
+        return Err(format!("Converted clip is {:.2}s long instead of {:.0}s", probe.duration, TEST_CLIP_SECONDS));
+    }
+    Ok(())
+}
+
+/// Run FFmpeg and return what it printed to stderr, or fail with the last
+/// line it printed
+async fn run_ffmpeg(ffmpeg_path: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(ffmpeg_path)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run FFmpeg: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if output.status.success() {
+        return Ok(stderr);
+    }
+    match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(line) => Err(format!("FFmpeg failed: {}", line.trim())),
+        None => Err(format!("FFmpeg failed with {}", output.status)),
+    }
+}
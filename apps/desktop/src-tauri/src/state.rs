@@ -1,21 +1,107 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex, Notify, OnceCell, OwnedSemaphorePermit, Semaphore};
+use tauri::async_runtime::JoinHandle;
 use serde::{Deserialize, Serialize};
-use crate::ffmpeg::{ConversionJob, JobStatus};
+use chrono::Utc;
+use crate::bookmarks::{self, FolderBookmark};
+use crate::environment::EnvironmentReport;
+use crate::estimate::ThroughputModel;
+use crate::history_db::HistoryDb;
+use crate::history_thumbnails::delete_history_thumbnails;
+use crate::usage_stats::{UsageBucket, UsagePeriod};
+use crate::error::Error;
+use crate::events::JobChange;
+use crate::output_locations::{self, MAX_OUTPUT_LOCATIONS};
+use crate::ffmpeg::{partial_output_path, ConversionJob, EncoderSpeed, JobStatus, ResourceUsage, VideoPreset};
+use crate::post_queue::PostQueueAction;
+use crate::preset_schema::{migrate_preset, PRESET_SCHEMA_VERSION};
+use crate::preset_share::validate_preset;
+use crate::quality::{QualityMetric, QualityScore};
+use crate::query::{HistoryFilter, HistoryPage, HistorySort};
+use crate::ffmpeg_version::FFmpegDiagnostic;
+use crate::status_message::StatusMessage;
+use crate::updates::{PendingUpdate, UpdateChannel};
+use crate::watcher::WatchFolder;
 use tauri::{AppHandle, Manager};
 use std::fs;
-use std::path::PathBuf;
+use base64::{Engine as _, engine::general_purpose};
+use std::path::{Path, PathBuf};
 use std::collections::VecDeque;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct AppSettings {
     pub output_directory: String,
     pub use_subdirectory: bool,
     pub subdirectory_name: String,
     pub file_name_pattern: String,
     pub zoomed_thumbnails: bool,
+    pub auto_crop: bool,
+    pub faststart: bool,
+    pub interrupted_jobs: InterruptedJobPolicy,
+    /// How many files are analyzed (probed, thumbnailed) at the same time
+    pub analysis_concurrency: u32,
+    pub update_channel: UpdateChannel,
+    /// Output folders the user picked, most recent first
+    pub output_locations: Vec<String>,
+    /// Scratch space for work files; the app cache dir when empty
+    pub temp_directory: String,
+    /// Applied to every preset that encodes with x264 or x265
+    pub encoder_speed: EncoderSpeed,
+    pub watch_folders: Vec<WatchFolder>,
+    /// What to do when a file already exists at a job's output path
+    pub on_conflict: OutputConflictPolicy,
+    /// Run once the last job of a run has finished
+    pub post_queue_action: PostQueueAction,
+    /// FFmpeg to use instead of the bundled one; the bundled one when empty
+    pub ffmpeg_path_override: String,
+    /// What to do when a conversion comes out bigger than its source
+    pub larger_output: LargerOutputPolicy,
+    /// Decode every output again after converting and check its duration
+    pub verify_outputs: bool,
+    /// Compare outputs with their source after converting
+    pub quality_metric: QualityMetric,
+    /// Most history entries kept, oldest dropped first; no limit when 0
+    pub history_max_entries: u32,
+    /// Days a history entry is kept for; forever when 0
+    pub history_max_age_days: u32,
+}
+
+pub const MAX_ANALYSIS_CONCURRENCY: u32 = 8;
+
+/// What to do at launch with jobs that were converting when the app last quit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InterruptedJobPolicy {
+    Requeue,
+    MarkFailed,
+    /// Mark them failed and let the frontend ask whether to convert them again
+    #[default]
+    Prompt,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputConflictPolicy {
+    Overwrite,
+    /// Write `name (1).mp4`, `name (2).mp4`, ... instead
+    #[default]
+    AutoRename,
+    /// Fail the job and leave the existing file alone
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LargerOutputPolicy {
+    #[default]
+    KeepBoth,
+    /// Delete the output and mark the job as skipped
+    DeleteOutput,
+    /// Keep the output and mark its history entry
+    FlagInHistory,
 }
 
 impl Default for AppSettings {
@@ -26,7 +112,52 @@ impl Default for AppSettings {
             subdirectory_name: "converted".to_string(),
             file_name_pattern: "{name}_converted".to_string(),
             zoomed_thumbnails: false,
+            auto_crop: false,
+            faststart: true,
+            interrupted_jobs: InterruptedJobPolicy::default(),
+            analysis_concurrency: 2,
+            update_channel: UpdateChannel::default(),
+            output_locations: Vec::new(),
+            temp_directory: String::new(),
+            encoder_speed: EncoderSpeed::default(),
+            watch_folders: Vec::new(),
+            on_conflict: OutputConflictPolicy::default(),
+            post_queue_action: PostQueueAction::default(),
+            ffmpeg_path_override: String::new(),
+            larger_output: LargerOutputPolicy::default(),
+            verify_outputs: false,
+            quality_metric: QualityMetric::default(),
+            history_max_entries: 100,
+            history_max_age_days: 0,
+        }
+    }
+}
+
+impl AppSettings {
+    /// Remember an output folder the user picked
+    pub fn remember_output_location(&mut self, path: &str) {
+        if path.is_empty() {
+            return;
+        }
+        self.output_locations.retain(|location| location != path);
+        self.output_locations.insert(0, path.to_string());
+        self.output_locations.truncate(MAX_OUTPUT_LOCATIONS);
+    }
+
+    /// Apply a partial settings object on top of these settings, so callers
+    /// that only know about some fields don't reset the others to defaults.
+    pub fn merged_with(&self, patch: &serde_json::Value) -> Result<AppSettings, String> {
+        let mut merged = serde_json::to_value(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+        if let (Some(target), Some(patch)) = (merged.as_object_mut(), patch.as_object()) {
+            for (key, value) in patch {
+                target.insert(key.clone(), value.clone());
+            }
         }
+
+        serde_json::from_value(merged)
+            .map_err(|e| format!("Invalid settings: {}", e))
     }
 }
 
@@ -34,8 +165,177 @@ impl Default for AppSettings {
 pub struct AppState {
     pub jobs: Arc<Mutex<HashMap<String, ConversionJob>>>,
     pub job_queue: Arc<Mutex<VecDeque<String>>>, // Queue of job IDs in order
-    pub history: Arc<Mutex<Vec<ConversionHistory>>>,
+    /// Opened by `load_history`. A std mutex: SQLite calls are short and blocking.
+    pub history: Arc<std::sync::Mutex<Option<HistoryDb>>>,
+    pub throughput: Arc<Mutex<ThroughputModel>>,
     pub settings: Arc<Mutex<AppSettings>>,
+    /// Wakes the queue processor when a job becomes ready to convert
+    pub queue_wakeup: Arc<Notify>,
+    pub queue_worker: Arc<Mutex<Option<QueueWorker>>>,
+    /// Limits concurrent analysis so large drops don't hammer the disk
+    pub analysis_slots: Arc<Semaphore>,
+    /// How many permits `analysis_slots` is meant to have, to resize it by
+    pub analysis_limit: Arc<Mutex<usize>>,
+    pub active_conversion: Arc<Mutex<Option<ActiveConversion>>>,
+    /// Recent encode speed samples per job, for live sparklines
+    pub progress_samples: Arc<Mutex<HashMap<String, VecDeque<ProgressSample>>>>,
+    /// Running analysis (probe, thumbnail, crop detection) tasks by job id
+    pub analysis_tasks: Arc<Mutex<HashMap<String, JoinHandle<()>>>>,
+    /// Downloaded update waiting for the queue to go idle
+    pub pending_update: Arc<Mutex<Option<PendingUpdate>>>,
+    /// Result of the FFmpeg binary check run at startup
+    pub ffmpeg_diagnostic: Arc<Mutex<Option<FFmpegDiagnostic>>>,
+    /// Folders the user granted access to, by path
+    pub folder_bookmarks: Arc<Mutex<HashMap<String, FolderBookmark>>>,
+    /// Folders of dropped or picked files and picked folders. A std mutex
+    /// because it's filled from synchronous drop and scope callbacks.
+    pub allowed_roots: Arc<std::sync::Mutex<HashSet<PathBuf>>>,
+    /// Job changes waiting for the next batched event
+    pub pending_job_changes: Arc<Mutex<PendingJobChanges>>,
+    /// Jobs that were waiting or converting since the queue was last idle
+    pub queue_run: Arc<Mutex<HashSet<String>>>,
+    /// Latest environment checks, run on first launch or on request
+    pub environment_report: Arc<Mutex<Option<EnvironmentReport>>>,
+    /// Presets the user made, listed after the built-in ones
+    pub user_presets: Arc<Mutex<Vec<VideoPreset>>>,
+    /// Running watch-folder scans by folder path
+    pub watchers: Arc<Mutex<HashMap<String, JoinHandle<()>>>>,
+    /// Process ids of the FFmpeg processes a conversion runs (the encode and
+    /// the trim steps before it), killed on exit. A std mutex because
+    /// `TrackedProcess` removes its pid when dropped.
+    pub ffmpeg_processes: Arc<std::sync::Mutex<HashSet<u32>>>,
+    /// `ffmpeg_path_override` from settings, kept where the synchronous
+    /// binary lookup can read it
+    pub ffmpeg_override: Arc<std::sync::Mutex<Option<PathBuf>>>,
+    /// Held while FFmpeg is being downloaded
+    pub ffmpeg_download: Arc<Mutex<()>>,
+    /// Set once the previous session is restored. Webview reloads call
+    /// `load_persisted_data` again and must not recover live jobs.
+    pub session_restored: Arc<OnceCell<()>>,
+}
+
+/// Job changes collected between two `jobs-changed` events. Only the latest
+/// change per job is kept; the snapshot is taken when the batch is sent.
+#[derive(Debug, Default)]
+pub struct PendingJobChanges {
+    /// Changed job ids in the order they first changed
+    pub order: Vec<String>,
+    pub changes: HashMap<String, JobChange>,
+    pub removed_job_ids: Vec<String>,
+    /// Something other than progress changed, so the queue has to be saved
+    pub needs_save: bool,
+    /// A flush is already scheduled for this batch
+    pub flush_scheduled: bool,
+}
+
+pub const MAX_PROGRESS_SAMPLES: usize = 120;
+
+/// Finished jobs kept in memory and in the queue; older ones are moved to
+/// the archive file so long sessions with thousands of jobs stay lean
+pub const MAX_FINISHED_JOBS: usize = 200;
+/// The archive is rotated once it grows past this, keeping one old file
+const MAX_ARCHIVE_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressSample {
+    /// Seconds since the conversion started
+    pub time: f64,
+    pub fps: Option<f32>,
+    pub speed: Option<f32>,
+}
+
+/// Job currently being converted and the switch that cancels it
+pub struct ActiveConversion {
+    job_id: String,
+    cancel: watch::Sender<bool>,
+}
+
+/// How much work is left in the queue, used to confirm quitting
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStatus {
+    pub processing: usize,
+    /// Jobs still waiting to be analyzed or converted
+    pub waiting: usize,
+    /// Time left for the whole queue, when every remaining job has an estimate
+    pub eta_seconds: Option<f64>,
+}
+
+impl QueueStatus {
+    pub fn is_busy(&self) -> bool {
+        self.processing > 0 || self.waiting > 0
+    }
+}
+
+/// Summary of the current run for compact progress windows, sent as the
+/// `queue-progress` event. A run lasts until the queue goes idle.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueProgress {
+    /// Jobs in the current run, including the ones already finished
+    pub total_jobs: usize,
+    pub finished_jobs: usize,
+    /// Overall progress of the run, 0 to 100
+    pub percent: f32,
+    /// File name of the job being converted
+    pub current_file: Option<String>,
+    pub current_percent: Option<f32>,
+    pub eta_seconds: Option<f64>,
+}
+
+/// Keeps a process in `AppState::ffmpeg_processes` while it runs
+pub struct TrackedProcess {
+    processes: Arc<std::sync::Mutex<HashSet<u32>>>,
+    pid: u32,
+}
+
+impl Drop for TrackedProcess {
+    fn drop(&mut self) {
+        if let Ok(mut processes) = self.processes.lock() {
+            processes.remove(&self.pid);
+        }
+    }
+}
+
+/// Background task that converts ready jobs, owned by `AppState`
+pub struct QueueWorker {
+    stop: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+/// Contents of presets.json. Presets stay raw JSON until they're migrated
+/// from the schema version they were saved with.
+#[derive(Serialize, Deserialize)]
+struct UserPresetsFile {
+    #[serde(default)]
+    version: u32,
+    presets: Vec<serde_json::Value>,
+}
+
+fn same_preset_name(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+fn user_preset_index(presets: &[VideoPreset], name: &str) -> Result<usize, String> {
+    if VideoPreset::get_presets().iter().any(|preset| same_preset_name(&preset.name, name)) {
+        return Err("Built-in presets can't be changed".to_string());
+    }
+    presets.iter()
+        .position(|preset| same_preset_name(&preset.name, name))
+        .ok_or_else(|| format!("Preset {} not found", name))
+}
+
+/// Preset names have to be unique so jobs and history can refer to them.
+/// `except` is the index of the preset being renamed.
+fn ensure_preset_name_free(presets: &[VideoPreset], name: &str, except: Option<usize>) -> Result<(), String> {
+    let taken = VideoPreset::get_presets().iter().any(|preset| same_preset_name(&preset.name, name))
+        || presets.iter().enumerate()
+            .any(|(index, preset)| Some(index) != except && same_preset_name(&preset.name, name));
+    if taken {
+        return Err(format!("A preset called {} already exists", name.trim()));
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +349,20 @@ pub struct ConversionHistory {
     pub file_size_before: u64,
     pub file_size_after: u64,
     pub duration: f64,
+    /// Wall time the conversion took, used to estimate future conversions
+    #[serde(default)]
+    pub elapsed_seconds: Option<f64>,
+    /// Average encoding speed as a multiple of realtime
+    #[serde(default)]
+    pub speed: Option<f64>,
+    /// The output came out bigger than the source
+    #[serde(default)]
+    pub larger_than_original: bool,
+    #[serde(default)]
+    pub quality_score: Option<QualityScore>,
+    /// What verifying the output found wrong with it
+    #[serde(default)]
+    pub problems: Vec<String>,
 }
 
 impl AppState {
@@ -56,8 +370,244 @@ impl AppState {
         Self {
             jobs: Arc::new(Mutex::new(HashMap::new())),
             job_queue: Arc::new(Mutex::new(VecDeque::new())),
-            history: Arc::new(Mutex::new(Vec::new())),
+            history: Arc::new(std::sync::Mutex::new(None)),
+            throughput: Arc::new(Mutex::new(ThroughputModel::default())),
             settings: Arc::new(Mutex::new(AppSettings::default())),
+            queue_wakeup: Arc::new(Notify::new()),
+            queue_worker: Arc::new(Mutex::new(None)),
+            analysis_slots: Arc::new(Semaphore::new(AppSettings::default().analysis_concurrency as usize)),
+            analysis_limit: Arc::new(Mutex::new(AppSettings::default().analysis_concurrency as usize)),
+            active_conversion: Arc::new(Mutex::new(None)),
+            progress_samples: Arc::new(Mutex::new(HashMap::new())),
+            analysis_tasks: Arc::new(Mutex::new(HashMap::new())),
+            pending_update: Arc::new(Mutex::new(None)),
+            ffmpeg_diagnostic: Arc::new(Mutex::new(None)),
+            folder_bookmarks: Arc::new(Mutex::new(HashMap::new())),
+            allowed_roots: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            pending_job_changes: Arc::new(Mutex::new(PendingJobChanges::default())),
+            queue_run: Arc::new(Mutex::new(HashSet::new())),
+            environment_report: Arc::new(Mutex::new(None)),
+            user_presets: Arc::new(Mutex::new(Vec::new())),
+            watchers: Arc::new(Mutex::new(HashMap::new())),
+            ffmpeg_processes: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            ffmpeg_override: Arc::new(std::sync::Mutex::new(None)),
+            ffmpeg_download: Arc::new(Mutex::new(())),
+            session_restored: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Add a job change to the pending batch. Returns true if the caller has
+    /// to schedule a flush because none is scheduled yet.
+    pub async fn record_job_change(&self, job_id: &str, change: JobChange) -> bool {
+        let mut pending = self.pending_job_changes.lock().await;
+        if !matches!(change, JobChange::Progress) {
+            pending.needs_save = true;
+        }
+        match pending.changes.get_mut(job_id) {
+            // Progress never hides a completion or status change in the same batch
+            Some(existing) => {
+                if !matches!(change, JobChange::Progress) {
+                    *existing = change;
+                }
+            }
+            None => {
+                pending.order.push(job_id.to_string());
+                pending.changes.insert(job_id.to_string(), change);
+            }
+        }
+        !std::mem::replace(&mut pending.flush_scheduled, true)
+    }
+
+    /// Add removed jobs to the pending batch, dropping their other changes.
+    /// Returns true if the caller has to schedule a flush.
+    pub async fn record_jobs_removed(&self, job_ids: Vec<String>) -> bool {
+        let mut pending = self.pending_job_changes.lock().await;
+        let removed: HashSet<&String> = job_ids.iter().collect();
+        pending.order.retain(|id| !removed.contains(id));
+        pending.changes.retain(|id, _| !removed.contains(id));
+        pending.removed_job_ids.extend(job_ids);
+        pending.needs_save = true;
+        !std::mem::replace(&mut pending.flush_scheduled, true)
+    }
+
+    pub async fn take_job_changes(&self) -> PendingJobChanges {
+        std::mem::take(&mut *self.pending_job_changes.lock().await)
+    }
+
+    /// Let file commands touch a path the user dropped or picked. For a file
+    /// that's its whole folder, so outputs can be written next to it.
+    pub fn allow_path(&self, path: &Path) {
+        let root = if path.is_dir() { Some(path) } else { path.parent() };
+        let Some(root) = root.and_then(|root| root.canonicalize().ok()) else {
+            return;
+        };
+        if let Ok(mut roots) = self.allowed_roots.lock() {
+            roots.insert(root);
+        }
+    }
+
+    /// Ask the queue processor to look for work. A wakeup sent while the
+    /// processor is busy is kept until it waits again, so none are lost.
+    pub fn wake_queue(&self) {
+        self.queue_wakeup.notify_one();
+    }
+
+    /// Register the conversion that's about to start and return its cancel signal.
+    pub async fn begin_conversion(&self, job_id: &str) -> watch::Receiver<bool> {
+        self.progress_samples.lock().await.remove(job_id);
+        let (cancel, cancel_rx) = watch::channel(false);
+        *self.active_conversion.lock().await = Some(ActiveConversion { job_id: job_id.to_string(), cancel });
+        cancel_rx
+    }
+
+    pub async fn end_conversion(&self, job_id: &str) {
+        let mut active = self.active_conversion.lock().await;
+        if active.as_ref().is_some_and(|active| active.job_id == job_id) {
+            *active = None;
+        }
+    }
+
+    /// Cancel queued, ready or converting jobs. Returns the ids that were cancelled.
+    pub async fn cancel_jobs(&self, job_ids: &[String]) -> Vec<String> {
+        let mut cancelled = Vec::new();
+        {
+            let mut jobs = self.jobs.lock().await;
+            for job_id in job_ids {
+                if let Some(job) = jobs.get_mut(job_id) {
+                    if matches!(job.status, JobStatus::Queued | JobStatus::Ready | JobStatus::Processing) {
+                        job.status = JobStatus::Cancelled;
+                        job.status_message = Some(StatusMessage::Cancelled);
+                        cancelled.push(job_id.clone());
+                    }
+                }
+            }
+        }
+
+        if let Some(active) = self.active_conversion.lock().await.as_ref() {
+            if cancelled.contains(&active.job_id) {
+                let _ = active.cancel.send(true);
+            }
+        }
+
+        cancelled
+    }
+
+    /// Wait for a free analysis slot. The slot is released when the permit is dropped.
+    pub async fn acquire_analysis_slot(&self) -> OwnedSemaphorePermit {
+        self.analysis_slots.clone().acquire_owned().await
+            .expect("analysis semaphore is never closed")
+    }
+
+    pub async fn track_analysis(&self, job_id: String, task: JoinHandle<()>) {
+        let mut tasks = self.analysis_tasks.lock().await;
+        tasks.retain(|_, task| !task.inner().is_finished());
+        tasks.insert(job_id, task);
+    }
+
+    /// Stop a job's analysis. FFmpeg processes it started are killed with it,
+    /// so nothing writes thumbnails for jobs that no longer exist.
+    pub async fn abort_analysis(&self, job_id: &str) {
+        if let Some(task) = self.analysis_tasks.lock().await.remove(job_id) {
+            task.abort();
+        }
+    }
+
+    /// Stop every running analysis and wait until its FFmpeg processes are gone
+    pub async fn abort_all_analysis(&self) {
+        let tasks: Vec<JoinHandle<()>> = self.analysis_tasks.lock().await.drain().map(|(_, task)| task).collect();
+        for task in tasks {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+
+    /// Remember a running FFmpeg process until the returned guard is dropped
+    pub fn track_ffmpeg_process(&self, pid: u32) -> TrackedProcess {
+        if let Ok(mut processes) = self.ffmpeg_processes.lock() {
+            processes.insert(pid);
+        }
+        TrackedProcess { processes: self.ffmpeg_processes.clone(), pid }
+    }
+
+    /// Kill FFmpeg processes that are still tracked as running, e.g. an
+    /// encode started outside the queue worker. Owners untrack a process
+    /// right after it's reaped, so pids of exited processes, which another
+    /// program may reuse, aren't killed.
+    pub fn kill_ffmpeg_processes(&self) {
+        let Ok(mut processes) = self.ffmpeg_processes.lock() else {
+            return;
+        };
+        for pid in processes.drain() {
+            println!("Killing FFmpeg process {}", pid);
+            #[cfg(unix)]
+            let result = std::process::Command::new("kill").args(["-KILL", &pid.to_string()]).status();
+            #[cfg(windows)]
+            let result = std::process::Command::new("taskkill").args(["/F", "/PID", &pid.to_string()]).status();
+            if let Err(e) = result {
+                eprintln!("Failed to kill FFmpeg process {}: {}", pid, e);
+            }
+        }
+    }
+
+    /// Jobs already analyzing keep their slots; when the limit drops, slots
+    /// in use are taken away as those jobs release them.
+    async fn set_analysis_concurrency(&self, limit: u32) {
+        let limit = limit.clamp(1, MAX_ANALYSIS_CONCURRENCY) as usize;
+        let mut current = self.analysis_limit.lock().await;
+        match limit.cmp(&*current) {
+            Ordering::Greater => self.analysis_slots.add_permits(limit - *current),
+            Ordering::Less => {
+                let excess = *current - limit;
+                let still_held = excess - self.analysis_slots.forget_permits(excess);
+                if still_held > 0 {
+                    let slots = self.analysis_slots.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Ok(permits) = slots.acquire_many_owned(still_held as u32).await {
+                            permits.forget();
+                        }
+                    });
+                }
+            }
+            Ordering::Equal => {}
+        }
+        *current = limit;
+    }
+
+    /// Start the queue worker unless one is already running. `spawn` gets a
+    /// receiver that flips to `true` when the worker should stop. A worker that
+    /// exited on its own (e.g. panicked) is replaced rather than blocking the queue.
+    pub async fn start_queue_worker<F>(&self, spawn: F) -> bool
+    where
+        F: FnOnce(watch::Receiver<bool>) -> JoinHandle<()>,
+    {
+        let mut worker = self.queue_worker.lock().await;
+        if worker.as_ref().is_some_and(|worker| !worker.handle.inner().is_finished()) {
+            return false;
+        }
+
+        let (stop, stop_rx) = watch::channel(false);
+        *worker = Some(QueueWorker { stop, handle: spawn(stop_rx) });
+        true
+    }
+
+    /// Stop the queue worker right away, killing the conversion in progress.
+    /// Returns once the worker is dropped, which doesn't wait for FFmpeg.
+    pub async fn abort_queue_worker(&self) {
+        let worker = self.queue_worker.lock().await.take();
+        if let Some(worker) = worker {
+            worker.handle.abort();
+            let _ = worker.handle.await;
+        }
+    }
+
+    /// Stop the queue worker and wait for the job it's converting to finish.
+    pub async fn stop_queue_worker(&self) {
+        let worker = self.queue_worker.lock().await.take();
+        if let Some(worker) = worker {
+            let _ = worker.stop.send(true);
+            if let Err(e) = worker.handle.await {
+                eprintln!("Queue worker ended with an error: {}", e);
+            }
         }
     }
 
@@ -66,51 +616,344 @@ impl AppState {
             .map_err(|e| format!("Failed to get app data directory: {}", e))
     }
 
+    /// Where versions before the history database kept the history
     fn get_history_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
         let data_dir = Self::get_data_dir(app_handle)?;
         Ok(data_dir.join("conversion_history.json"))
     }
 
+    fn get_history_db_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+        Ok(data_dir.join("history.sqlite3"))
+    }
+
+    fn get_throughput_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+        Ok(data_dir.join("throughput.json"))
+    }
+
+    fn get_bookmarks_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+        Ok(data_dir.join("folder_bookmarks.json"))
+    }
+
+    fn get_presets_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+        Ok(data_dir.join("presets.json"))
+    }
+
+    fn get_archive_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+        Ok(data_dir.join("archived_jobs.jsonl"))
+    }
+
+    fn get_jobs_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+        Ok(data_dir.join("jobs.json"))
+    }
+
     fn get_settings_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
         let data_dir = Self::get_data_dir(app_handle)?;
         Ok(data_dir.join("settings.json"))
     }
 
+    /// Open the history database, moving over the entries older versions
+    /// kept in a JSON file, and prune it to the retention settings
     pub async fn load_history(&self, app_handle: &AppHandle) -> Result<(), String> {
-        let history_path = Self::get_history_file_path(app_handle)?;
-        
-        if history_path.exists() {
-            let content = fs::read_to_string(&history_path)
+        let data_dir = Self::get_data_dir(app_handle)?;
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        let mut db = HistoryDb::open(&Self::get_history_db_path(app_handle)?)?;
+
+        let json_path = Self::get_history_file_path(app_handle)?;
+        if json_path.exists() {
+            let content = fs::read_to_string(&json_path)
                 .map_err(|e| format!("Failed to read history file: {}", e))?;
-            
-            let loaded_history: Vec<ConversionHistory> = serde_json::from_str(&content)
+            let entries: Vec<ConversionHistory> = serde_json::from_str(&content)
                 .map_err(|e| format!("Failed to parse history file: {}", e))?;
-            
-            let mut history = self.history.lock().await;
-            *history = loaded_history;
+            db.insert_all(&entries)?;
+            // Kept as a backup in case the database has to be thrown away
+            fs::rename(&json_path, json_path.with_extension("json.migrated"))
+                .map_err(|e| format!("Failed to move old history file: {}", e))?;
+            println!("Moved {} history entries into the database", entries.len());
         }
-        
+
+        *self.history.lock().map_err(|_| "History database is unavailable".to_string())? = Some(db);
+
+        let pruned = self.prune_history(app_handle).await?;
+        if pruned > 0 {
+            println!("Pruned {} history entries past the retention settings", pruned);
+        }
+        Ok(())
+    }
+
+    /// Drop history entries beyond the retention settings, with their
+    /// thumbnails. Returns how many were dropped.
+    pub async fn prune_history(&self, app_handle: &AppHandle) -> Result<usize, String> {
+        let settings = self.get_settings().await;
+        let removed = self.with_history(|db| db.prune(settings.history_max_entries, settings.history_max_age_days, Utc::now()))?;
+        delete_history_thumbnails(app_handle, &removed);
+        Ok(removed.len())
+    }
+
+    /// Run `f` on the history database once `load_history` has opened it
+    fn with_history<T>(&self, f: impl FnOnce(&mut HistoryDb) -> Result<T, String>) -> Result<T, String> {
+        let mut history = self.history.lock()
+            .map_err(|_| "History database is unavailable".to_string())?;
+        let db = history.as_mut().ok_or("History isn't loaded yet")?;
+        f(db)
+    }
+
+    pub async fn load_throughput(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let throughput_path = Self::get_throughput_file_path(app_handle)?;
+
+        if throughput_path.exists() {
+            let content = fs::read_to_string(&throughput_path)
+                .map_err(|e| format!("Failed to read throughput file: {}", e))?;
+
+            let loaded: ThroughputModel = serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse throughput file: {}", e))?;
+
+            *self.throughput.lock().await = loaded;
+        }
+
         Ok(())
     }
 
-    pub async fn save_history(&self, app_handle: &AppHandle) -> Result<(), String> {
+    /// Learn from a finished conversion and save the updated model.
+    pub async fn record_throughput(&self, app_handle: &AppHandle, preset: &str, height: Option<u32>, speed: f64) -> Result<(), String> {
+        let content = {
+            let mut throughput = self.throughput.lock().await;
+            throughput.record(preset, height, speed);
+            serde_json::to_string_pretty(&*throughput)
+                .map_err(|e| format!("Failed to serialize throughput: {}", e))?
+        };
+
         let data_dir = Self::get_data_dir(app_handle)?;
-        
-        // Create data directory if it doesn't exist
         if !data_dir.exists() {
             fs::create_dir_all(&data_dir)
                 .map_err(|e| format!("Failed to create data directory: {}", e))?;
         }
-        
-        let history_path = Self::get_history_file_path(app_handle)?;
-        let history = self.history.lock().await;
-        
-        let content = serde_json::to_string_pretty(&*history)
-            .map_err(|e| format!("Failed to serialize history: {}", e))?;
-        
-        fs::write(&history_path, content)
-            .map_err(|e| format!("Failed to write history file: {}", e))?;
-        
+
+        fs::write(Self::get_throughput_file_path(app_handle)?, content)
+            .map_err(|e| format!("Failed to write throughput file: {}", e))
+    }
+
+    pub async fn get_throughput(&self) -> ThroughputModel {
+        self.throughput.lock().await.clone()
+    }
+
+    /// Totals per period over the history database
+    pub async fn get_usage(&self, period: UsagePeriod) -> Result<Vec<UsageBucket>, String> {
+        self.with_history(|db| db.usage()).map(|usage| usage.buckets(period))
+    }
+
+    /// Load the user's presets, upgrading ones saved by older versions.
+    /// Presets that no longer validate are skipped rather than failing the lot.
+    pub async fn load_user_presets(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let presets_path = Self::get_presets_file_path(app_handle)?;
+        if !presets_path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&presets_path)
+            .map_err(|e| format!("Failed to read presets file: {}", e))?;
+        let saved: UserPresetsFile = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse presets file: {}", e))?;
+
+        let mut presets = Vec::new();
+        for preset in saved.presets {
+            let preset = migrate_preset(preset, saved.version)
+                .and_then(|preset| serde_json::from_value::<VideoPreset>(preset).map_err(|e| e.to_string()))
+                .and_then(|preset| validate_preset(&preset).map(|_| preset));
+            match preset {
+                Ok(preset) => presets.push(preset),
+                Err(e) => eprintln!("Skipping saved preset: {}", e),
+            }
+        }
+
+        *self.user_presets.lock().await = presets;
+        Ok(())
+    }
+
+    async fn save_user_presets(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let presets = self.user_presets.lock().await.iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to serialize presets: {}", e))?;
+        let content = serde_json::to_string_pretty(&UserPresetsFile { version: PRESET_SCHEMA_VERSION, presets })
+            .map_err(|e| format!("Failed to serialize presets: {}", e))?;
+
+        let data_dir = Self::get_data_dir(app_handle)?;
+        if !data_dir.exists() {
+            fs::create_dir_all(&data_dir)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        fs::write(Self::get_presets_file_path(app_handle)?, content)
+            .map_err(|e| format!("Failed to write presets file: {}", e))
+    }
+
+    /// Built-in presets followed by the user's own
+    pub async fn list_presets(&self) -> Vec<VideoPreset> {
+        let mut presets = VideoPreset::get_presets();
+        presets.extend(self.user_presets.lock().await.iter().cloned());
+        presets
+    }
+
+    pub async fn create_preset(&self, app_handle: &AppHandle, preset: VideoPreset) -> Result<(), String> {
+        validate_preset(&preset)?;
+        {
+            let mut presets = self.user_presets.lock().await;
+            ensure_preset_name_free(&presets, &preset.name, None)?;
+            presets.push(preset);
+        }
+        self.save_user_presets(app_handle).await
+    }
+
+    /// Replace the user preset called `name`. The preset may be renamed.
+    pub async fn update_preset(&self, app_handle: &AppHandle, name: &str, preset: VideoPreset) -> Result<(), String> {
+        validate_preset(&preset)?;
+        {
+            let mut presets = self.user_presets.lock().await;
+            let index = user_preset_index(&presets, name)?;
+            ensure_preset_name_free(&presets, &preset.name, Some(index))?;
+            presets[index] = preset;
+        }
+        self.save_user_presets(app_handle).await
+    }
+
+    pub async fn delete_preset(&self, app_handle: &AppHandle, name: &str) -> Result<(), String> {
+        {
+            let mut presets = self.user_presets.lock().await;
+            let index = user_preset_index(&presets, name)?;
+            presets.remove(index);
+        }
+        self.save_user_presets(app_handle).await
+    }
+
+    /// Load saved folder bookmarks and start accessing them, renewing stale ones.
+    pub async fn load_folder_bookmarks(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let bookmarks_path = Self::get_bookmarks_file_path(app_handle)?;
+        if !bookmarks_path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&bookmarks_path)
+            .map_err(|e| format!("Failed to read bookmarks file: {}", e))?;
+        let saved: Vec<FolderBookmark> = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse bookmarks file: {}", e))?;
+
+        let mut resolved = HashMap::new();
+        let mut changed = false;
+        for bookmark in saved {
+            let data = general_purpose::STANDARD.decode(&bookmark.bookmark).unwrap_or_default();
+            match bookmarks::resolve_bookmark(&data, &bookmark.path) {
+                Ok(access) if access.stale => {
+                    let bookmark = FolderBookmark {
+                        bookmark: general_purpose::STANDARD.encode(bookmarks::create_bookmark(&access.path)?),
+                        path: access.path,
+                    };
+                    resolved.insert(bookmark.path.clone(), bookmark);
+                    changed = true;
+                }
+                Ok(_) => {
+                    resolved.insert(bookmark.path.clone(), bookmark);
+                }
+                Err(e) => {
+                    eprintln!("Dropping bookmark for {}: {}", bookmark.path, e);
+                    changed = true;
+                }
+            }
+        }
+
+        *self.folder_bookmarks.lock().await = resolved;
+        if changed {
+            self.save_folder_bookmarks(app_handle).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn save_folder_bookmarks(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+
+        if !data_dir.exists() {
+            fs::create_dir_all(&data_dir)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let bookmarks: Vec<FolderBookmark> = self.folder_bookmarks.lock().await.values().cloned().collect();
+        let content = serde_json::to_string_pretty(&bookmarks)
+            .map_err(|e| format!("Failed to serialize bookmarks: {}", e))?;
+
+        fs::write(Self::get_bookmarks_file_path(app_handle)?, content)
+            .map_err(|e| format!("Failed to write bookmarks file: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Remember access to a folder the user picked, across restarts.
+    pub async fn grant_folder_access(&self, app_handle: &AppHandle, path: &str) -> Result<(), String> {
+        let data = bookmarks::create_bookmark(path)?;
+        let access = bookmarks::resolve_bookmark(&data, path)?;
+        let bookmark = FolderBookmark {
+            path: access.path,
+            bookmark: general_purpose::STANDARD.encode(data),
+        };
+
+        let previous = self.folder_bookmarks.lock().await.insert(bookmark.path.clone(), bookmark);
+        if let Some(previous) = previous {
+            // Keep one access per folder, not one per grant
+            bookmarks::stop_access(&general_purpose::STANDARD.decode(&previous.bookmark).unwrap_or_default());
+        }
+        self.save_folder_bookmarks(app_handle).await
+    }
+
+    pub async fn revoke_folder_access(&self, app_handle: &AppHandle, path: &str) -> Result<(), String> {
+        let Some(bookmark) = self.folder_bookmarks.lock().await.remove(path) else {
+            return Ok(());
+        };
+        bookmarks::stop_access(&general_purpose::STANDARD.decode(&bookmark.bookmark).unwrap_or_default());
+        self.save_folder_bookmarks(app_handle).await
+    }
+
+    /// Load the queue saved by the previous session. Does nothing when jobs are
+    /// already in memory, so calling it again from a reloaded frontend is safe.
+    pub async fn load_jobs(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let jobs_path = Self::get_jobs_file_path(app_handle)?;
+
+        if jobs_path.exists() && self.jobs.lock().await.is_empty() {
+            let content = fs::read_to_string(&jobs_path)
+                .map_err(|e| format!("Failed to read jobs file: {}", e))?;
+
+            let loaded_jobs: Vec<ConversionJob> = serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse jobs file: {}", e))?;
+
+            for job in loaded_jobs {
+                self.add_job(job).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn save_jobs(&self, app_handle: &AppHandle) -> Result<(), String> {
+        let data_dir = Self::get_data_dir(app_handle)?;
+
+        if !data_dir.exists() {
+            fs::create_dir_all(&data_dir)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let jobs_path = Self::get_jobs_file_path(app_handle)?;
+        let jobs = self.get_all_jobs().await;
+
+        let content = serde_json::to_string_pretty(&jobs)
+            .map_err(|e| format!("Failed to serialize jobs: {}", e))?;
+
+        fs::write(&jobs_path, content)
+            .map_err(|e| format!("Failed to write jobs file: {}", e))?;
+
         Ok(())
     }
 
@@ -124,6 +967,8 @@ impl AppState {
             let loaded_settings: AppSettings = serde_json::from_str(&content)
                 .map_err(|e| format!("Failed to parse settings file: {}", e))?;
             
+            self.set_analysis_concurrency(loaded_settings.analysis_concurrency).await;
+            self.set_ffmpeg_override(&loaded_settings.ffmpeg_path_override);
             let mut settings = self.settings.lock().await;
             *settings = loaded_settings;
         }
@@ -161,13 +1006,25 @@ impl AppState {
     where
         F: FnOnce(&mut AppSettings),
     {
-        {
+        let (previous, current) = {
             let mut settings = self.settings.lock().await;
+            let previous = settings.analysis_concurrency;
             update_fn(&mut *settings);
+            self.set_ffmpeg_override(&settings.ffmpeg_path_override);
+            (previous, settings.analysis_concurrency)
+        };
+        if previous != current {
+            self.set_analysis_concurrency(current).await;
         }
         self.save_settings(app_handle).await
     }
 
+    fn set_ffmpeg_override(&self, path: &str) {
+        if let Ok(mut ffmpeg_override) = self.ffmpeg_override.lock() {
+            *ffmpeg_override = (!path.trim().is_empty()).then(|| PathBuf::from(path.trim()));
+        }
+    }
+
     pub async fn add_job(&self, job: ConversionJob) {
         let job_id = job.id.clone();
         
@@ -198,34 +1055,164 @@ impl AppState {
         jobs.insert(job_id, job);
     }
 
-    pub async fn get_next_queued_job(&self) -> Option<String> {
+    /// Apply the outcome of a finished conversion, unless the job was
+    /// cancelled or removed in the meantime. Returns whether it was applied.
+    pub async fn complete_job(&self, id: &str, complete: impl FnOnce(&mut ConversionJob)) -> bool {
+        let mut jobs = self.jobs.lock().await;
+        match jobs.get_mut(id) {
+            Some(job) if !matches!(job.status, JobStatus::Cancelled) => {
+                complete(job);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Mark the first ready job in queue order as processing and return it,
+    /// unless a conversion is already running. Checking and claiming under
+    /// one lock keeps a job cancelled in between from being converted.
+    pub async fn claim_next_ready_job(&self) -> Option<String> {
         let queue = self.job_queue.lock().await;
-        let jobs = self.jobs.lock().await;
-        
-        // Find the first queued job in the queue
-        for job_id in queue.iter() {
-            if let Some(job) = jobs.get(job_id) {
-                if matches!(job.status, JobStatus::Queued | JobStatus::Ready) {
-                    return Some(job_id.clone());
+        let mut jobs = self.jobs.lock().await;
+        if jobs.values().any(|job| matches!(job.status, JobStatus::Processing)) {
+            return None;
+        }
+
+        let job_id = queue.iter()
+            .find(|job_id| jobs.get(*job_id).is_some_and(|job| matches!(job.status, JobStatus::Ready)))?
+            .clone();
+        if let Some(job) = jobs.get_mut(&job_id) {
+            job.status = JobStatus::Processing;
+        }
+        Some(job_id)
+    }
+
+    /// Check that waiting jobs can still write their output. Returns the ids
+    /// of jobs whose problem appeared or went away.
+    pub async fn validate_output_locations(&self) -> Vec<String> {
+        let mut jobs = self.jobs.lock().await;
+        let mut changed = Vec::new();
+
+        for job in jobs.values_mut().filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Ready)) {
+            let problem = output_locations::output_problem(&job.output_path);
+            if problem != job.output_problem {
+                if let Some(problem) = &problem {
+                    println!("Job {}: {}", job.id, problem);
                 }
+                job.output_problem = problem;
+                changed.push(job.id.clone());
             }
         }
-        None
+
+        changed
     }
 
-    pub async fn get_next_ready_job(&self) -> Option<String> {
-        let queue = self.job_queue.lock().await;
-        let jobs = self.jobs.lock().await;
-        
-        // Find the first ready job in the queue (in order)
-        for job_id in queue.iter() {
-            if let Some(job) = jobs.get(job_id) {
-                if matches!(job.status, JobStatus::Ready) {
-                    return Some(job_id.clone());
+    /// Recovery pass for jobs left in Processing by a previous session.
+    /// Their partial outputs are deleted either way.
+    /// Returns the ids of interrupted jobs the user should be asked about.
+    pub async fn recover_interrupted_jobs(&self, policy: InterruptedJobPolicy) -> Vec<String> {
+        // A job a worker is converting right now wasn't interrupted
+        let active_job_id = self.active_conversion.lock().await.as_ref().map(|active| active.job_id.clone());
+        let mut jobs = self.jobs.lock().await;
+        let mut needs_decision = Vec::new();
+
+        for job in jobs.values_mut().filter(|job| {
+            matches!(job.status, JobStatus::Processing) && active_job_id.as_deref() != Some(job.id.as_str())
+        }) {
+            println!("Recovering interrupted job {} with policy {:?}", job.id, policy);
+            remove_partial_output(&partial_output_path(&job.output_path));
+            match policy {
+                InterruptedJobPolicy::Requeue => reset_for_requeue(job),
+                InterruptedJobPolicy::MarkFailed | InterruptedJobPolicy::Prompt => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(Error::Interrupted.to_string());
+                    job.error_code = Some(Error::Interrupted.code());
+                    if policy == InterruptedJobPolicy::Prompt {
+                        needs_decision.push(job.id.clone());
+                    }
                 }
             }
         }
-        None
+
+        needs_decision
+    }
+
+    /// Put failed or cancelled jobs back in the queue. Returns the ids that were requeued.
+    pub async fn requeue_jobs(&self, job_ids: &[String]) -> Vec<String> {
+        let mut jobs = self.jobs.lock().await;
+        let mut requeued = Vec::new();
+
+        for job_id in job_ids {
+            if let Some(job) = jobs.get_mut(job_id) {
+                if matches!(job.status, JobStatus::Failed | JobStatus::Cancelled) {
+                    reset_for_requeue(job);
+                    requeued.push(job_id.clone());
+                }
+            }
+        }
+
+        requeued
+    }
+
+    pub async fn queue_status(&self) -> QueueStatus {
+        let jobs = self.jobs.lock().await;
+        let remaining: Vec<&ConversionJob> = jobs.values()
+            .filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Ready | JobStatus::Processing))
+            .collect();
+        let eta_seconds = remaining.iter()
+            .map(|job| {
+                let seconds = job.estimate?.seconds?;
+                Some(seconds * (1.0 - f64::from(job.progress) / 100.0))
+            })
+            .sum();
+
+        QueueStatus {
+            processing: remaining.iter().filter(|job| matches!(job.status, JobStatus::Processing)).count(),
+            waiting: remaining.iter().filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Ready)).count(),
+            eta_seconds,
+        }
+    }
+
+    /// Progress of the current run. Once nothing is left the run ends and the
+    /// next job starts a new one.
+    pub async fn queue_progress(&self) -> QueueProgress {
+        let status = self.queue_status().await;
+        let jobs = self.jobs.lock().await;
+        let mut run = self.queue_run.lock().await;
+        run.extend(jobs.values()
+            .filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Ready | JobStatus::Processing))
+            .map(|job| job.id.clone()));
+        // Removed and cancelled jobs no longer count towards the run
+        run.retain(|id| jobs.get(id).is_some_and(|job| !matches!(job.status, JobStatus::Cancelled)));
+
+        let run_jobs: Vec<&ConversionJob> = run.iter().filter_map(|id| jobs.get(id)).collect();
+        let finished_jobs = run_jobs.iter()
+            .filter(|job| matches!(job.status, JobStatus::Completed | JobStatus::CompletedWithWarnings | JobStatus::Failed))
+            .count();
+        let done: f32 = run_jobs.iter()
+            .map(|job| match job.status {
+                JobStatus::Completed | JobStatus::CompletedWithWarnings | JobStatus::Failed => 100.0,
+                JobStatus::Processing => job.progress.clamp(0.0, 100.0),
+                _ => 0.0,
+            })
+            .sum();
+        let current = run_jobs.iter().find(|job| matches!(job.status, JobStatus::Processing));
+
+        let progress = QueueProgress {
+            total_jobs: run_jobs.len(),
+            finished_jobs,
+            percent: if run_jobs.is_empty() { 100.0 } else { done / run_jobs.len() as f32 },
+            current_file: current.and_then(|job| {
+                Path::new(&job.input_path).file_name().map(|name| name.to_string_lossy().into_owned())
+            }),
+            current_percent: current.map(|job| job.progress),
+            eta_seconds: status.eta_seconds,
+        };
+
+        if !status.is_busy() {
+            run.clear();
+        }
+        progress
     }
 
     pub async fn is_any_job_processing(&self) -> bool {
@@ -233,21 +1220,36 @@ impl AppState {
         jobs.values().any(|job| matches!(job.status, JobStatus::Processing))
     }
 
-    pub async fn update_job_status(&self, id: &str, status: JobStatus) {
+    pub async fn update_job_progress(&self, id: &str, progress: f32) {
         let mut jobs = self.jobs.lock().await;
         if let Some(job) = jobs.get_mut(id) {
-            job.status = status;
+            job.progress = progress;
         }
     }
 
-    pub async fn update_job_progress(&self, id: &str, progress: f32) {
+    /// Add a sample to the job's rolling buffer, dropping the oldest once full.
+    pub async fn record_progress_sample(&self, id: &str, sample: ProgressSample) {
+        let mut samples = self.progress_samples.lock().await;
+        let buffer = samples.entry(id.to_string()).or_default();
+        if buffer.len() == MAX_PROGRESS_SAMPLES {
+            buffer.pop_front();
+        }
+        buffer.push_back(sample);
+    }
+
+    pub async fn get_progress_samples(&self, id: &str) -> Vec<ProgressSample> {
+        let samples = self.progress_samples.lock().await;
+        samples.get(id).map(|buffer| buffer.iter().copied().collect()).unwrap_or_default()
+    }
+
+    pub async fn update_job_resource_usage(&self, id: &str, usage: ResourceUsage) {
         let mut jobs = self.jobs.lock().await;
         if let Some(job) = jobs.get_mut(id) {
-            job.progress = progress;
+            job.resource_usage = Some(usage);
         }
     }
 
-    pub async fn update_job_status_message(&self, id: &str, message: String) {
+    pub async fn update_job_status_message(&self, id: &str, message: StatusMessage) {
         let mut jobs = self.jobs.lock().await;
         if let Some(job) = jobs.get_mut(id) {
             println!("Updating job {} status message from {:?} to {}", id, job.status_message, message);
@@ -284,7 +1286,7 @@ impl AppState {
             let jobs = self.jobs.lock().await;
             completed_job_ids = jobs.iter()
                 .filter_map(|(id, job)| {
-                    if matches!(job.status, JobStatus::Completed | JobStatus::Failed) {
+                    if matches!(job.status, JobStatus::Completed | JobStatus::CompletedWithWarnings | JobStatus::Failed | JobStatus::Cancelled) {
                         Some(id.clone())
                     } else {
                         None
@@ -293,49 +1295,139 @@ impl AppState {
                 .collect();
         }
         
-        // Remove from jobs map
-        {
-            let mut jobs = self.jobs.lock().await;
-            for job_id in &completed_job_ids {
-                jobs.remove(job_id);
-            }
+        self.remove_jobs(&completed_job_ids).await;
+    }
+
+    /// Drop one job whatever its status. Returns false if there's no such job.
+    pub async fn remove_job(&self, job_id: &str) -> bool {
+        if !self.jobs.lock().await.contains_key(job_id) {
+            return false;
         }
-        
-        // Remove from queue
-        {
-            let mut queue = self.job_queue.lock().await;
-            queue.retain(|job_id| !completed_job_ids.contains(job_id));
+        self.remove_jobs(&[job_id.to_string()]).await;
+        true
+    }
+
+    /// Drop jobs from the map, the queue and the progress buffers
+    async fn remove_jobs(&self, job_ids: &[String]) {
+        let removed: HashSet<&String> = job_ids.iter().collect();
+        self.jobs.lock().await.retain(|job_id, _| !removed.contains(job_id));
+        self.job_queue.lock().await.retain(|job_id| !removed.contains(job_id));
+        self.progress_samples.lock().await.retain(|job_id, _| !removed.contains(job_id));
+    }
+
+    /// Move the oldest finished jobs beyond `MAX_FINISHED_JOBS` out of memory
+    /// and append them to the archive file. Returns the archived ids.
+    pub async fn archive_finished_jobs(&self, app_handle: &AppHandle) -> Result<Vec<String>, String> {
+        let finished: Vec<ConversionJob> = self.get_all_jobs().await
+            .into_iter()
+            .filter(|job| matches!(job.status, JobStatus::Completed | JobStatus::CompletedWithWarnings | JobStatus::Failed | JobStatus::Cancelled))
+            .collect();
+        if finished.len() <= MAX_FINISHED_JOBS {
+            return Ok(Vec::new());
+        }
+
+        let archived = &finished[..finished.len() - MAX_FINISHED_JOBS];
+        let mut lines = String::new();
+        for job in archived {
+            let line = serde_json::to_string(job)
+                .map_err(|e| format!("Failed to serialize job: {}", e))?;
+            lines.push_str(&line);
+            lines.push('\n');
         }
+        self.append_to_archive(app_handle, &lines)?;
+
+        let job_ids: Vec<String> = archived.iter().map(|job| job.id.clone()).collect();
+        self.remove_jobs(&job_ids).await;
+        Ok(job_ids)
     }
 
-    pub async fn add_to_history(&self, app_handle: &AppHandle, history_item: ConversionHistory) -> Result<(), String> {
-        {
-            let mut history = self.history.lock().await;
-            history.push(history_item);
-            
-            // Keep only last 100 items
-            if history.len() > 100 {
-                let drain_count = history.len() - 100;
-                history.drain(0..drain_count);
-            }
+    fn append_to_archive(&self, app_handle: &AppHandle, lines: &str) -> Result<(), String> {
+        use std::io::Write;
+
+        let data_dir = Self::get_data_dir(app_handle)?;
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+
+        let archive_path = Self::get_archive_file_path(app_handle)?;
+        if fs::metadata(&archive_path).is_ok_and(|metadata| metadata.len() > MAX_ARCHIVE_BYTES) {
+            fs::rename(&archive_path, archive_path.with_extension("old.jsonl"))
+                .map_err(|e| format!("Failed to rotate job archive: {}", e))?;
         }
-        
-        // Save to disk
-        self.save_history(app_handle).await
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&archive_path)
+            .map_err(|e| format!("Failed to open job archive: {}", e))?;
+        file.write_all(lines.as_bytes())
+            .map_err(|e| format!("Failed to write job archive: {}", e))
+    }
+
+    /// Most recently archived jobs first
+    pub fn get_archived_jobs(&self, app_handle: &AppHandle, limit: usize) -> Result<Vec<ConversionJob>, String> {
+        let archive_path = Self::get_archive_file_path(app_handle)?;
+        if !archive_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&archive_path)
+            .map_err(|e| format!("Failed to read job archive: {}", e))?;
+        // Skip lines that don't parse, e.g. one cut short by a crash
+        Ok(content.lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .take(limit)
+            .collect())
     }
 
+    pub async fn add_to_history(&self, app_handle: &AppHandle, history_item: ConversionHistory) -> Result<(), String> {
+        self.with_history(|db| db.insert(&history_item))?;
+        self.prune_history(app_handle).await.map(|_| ())
+    }
+
+    /// Every history entry, oldest first
     pub async fn get_history(&self) -> Vec<ConversionHistory> {
-        let history = self.history.lock().await;
-        history.clone()
+        self.with_history(|db| db.all()).unwrap_or_else(|e| {
+            eprintln!("Failed to read history: {}", e);
+            Vec::new()
+        })
     }
 
-    pub async fn clear_history(&self, app_handle: &AppHandle) -> Result<(), String> {
-        {
-            let mut history = self.history.lock().await;
-            history.clear();
-        }
-        
-        // Save empty history to disk
-        self.save_history(app_handle).await
+    pub async fn query_history(&self, filter: &HistoryFilter, sort: HistorySort, offset: usize, limit: Option<usize>) -> Result<HistoryPage, String> {
+        self.with_history(|db| db.query(filter, sort, offset, limit))
     }
-}
\ No newline at end of file
+
+    /// Whether a previous conversion wrote to `output_path`
+    pub async fn history_has_output(&self, output_path: &str) -> bool {
+        self.with_history(|db| db.has_output(output_path)).unwrap_or(false)
+    }
+
+    pub async fn clear_history(&self) -> Result<(), String> {
+        self.with_history(|db| db.clear())
+    }
+}
+
+/// FFmpeg was killed mid-write, so whatever it left is truncated and won't play
+fn remove_partial_output(output_path: &str) {
+    let path = Path::new(output_path);
+    if !path.exists() {
+        return;
+    }
+    match fs::remove_file(path) {
+        Ok(()) => println!("Removed partial output {}", output_path),
+        Err(e) => eprintln!("Failed to remove partial output {}: {}", output_path, e),
+    }
+}
+
+/// Clear the results of a previous attempt so the job gets analyzed and converted again
+fn reset_for_requeue(job: &mut ConversionJob) {
+    job.status = JobStatus::Queued;
+    job.progress = 0.0;
+    job.error = None;
+    job.error_code = None;
+    job.status_message = Some(StatusMessage::WaitingInQueue);
+    job.resolved_trim = None;
+    job.encode_stats = None;
+    job.resource_usage = None;
+    job.quality_score = None;
+}
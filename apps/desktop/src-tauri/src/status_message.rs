@@ -0,0 +1,92 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// What a job is doing, as a stable key the frontend translates. Variants
+/// that need details carry them and serialize them under `params`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "key", content = "params", rename_all = "camelCase")]
+pub enum StatusMessage {
+    WaitingInQueue,
+    ReadyToConvert,
+    Converting,
+    Cancelled,
+    /// The output was deleted because it came out bigger than the source
+    SkippedLargerThanOriginal,
+}
+
+impl StatusMessage {
+    /// Map the English strings saved by older versions to their keys
+    fn from_legacy(message: &str) -> Option<Self> {
+        match message {
+            "Waiting in queue..." => Some(StatusMessage::WaitingInQueue),
+            "Ready to convert" => Some(StatusMessage::ReadyToConvert),
+            "Converting video..." => Some(StatusMessage::Converting),
+            "Cancelled" => Some(StatusMessage::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// English text, for logs only
+impl fmt::Display for StatusMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            StatusMessage::WaitingInQueue => "Waiting in queue...",
+            StatusMessage::ReadyToConvert => "Ready to convert",
+            StatusMessage::Converting => "Converting video...",
+            StatusMessage::Cancelled => "Cancelled",
+            StatusMessage::SkippedLargerThanOriginal => "Skipped (larger than original)",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Read a status message saved either as a key or as an older English string.
+/// Strings without a key are dropped.
+pub fn deserialize_status_message<'de, D>(deserializer: D) -> Result<Option<StatusMessage>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Saved {
+        Message(StatusMessage),
+        Legacy(String),
+    }
+
+    Ok(match Option::<Saved>::deserialize(deserializer)? {
+        Some(Saved::Message(message)) => Some(message),
+        Some(Saved::Legacy(message)) => StatusMessage::from_legacy(&message),
+        None => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Job {
+        #[serde(default, deserialize_with = "deserialize_status_message")]
+        status_message: Option<StatusMessage>,
+    }
+
+    fn parse(json: &str) -> Option<StatusMessage> {
+        serde_json::from_str::<Job>(json).unwrap().status_message
+    }
+
+    #[test]
+    fn serializes_as_key() {
+        let json = serde_json::to_string(&StatusMessage::WaitingInQueue).unwrap();
+        assert_eq!(json, r#"{"key":"waitingInQueue"}"#);
+        assert_eq!(parse(&format!(r#"{{"status_message":{}}}"#, json)), Some(StatusMessage::WaitingInQueue));
+    }
+
+    #[test]
+    fn reads_legacy_strings() {
+        assert_eq!(parse(r#"{"status_message":"Converting video..."}"#), Some(StatusMessage::Converting));
+        assert_eq!(parse(r#"{"status_message":"Something else"}"#), None);
+        assert_eq!(parse(r#"{"status_message":null}"#), None);
+        assert_eq!(parse("{}"), None);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+use crate::ffmpeg::stderr_log_dir;
+use crate::ffmpeg_version::get_ffmpeg_version;
+use crate::state::AppState;
+use crate::system_info::get_system_info;
+
+/// Jobs included in a bundle, newest first, with their FFmpeg output
+const BUNDLE_JOBS: usize = 20;
+/// Only the end of each log file is included
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleInfo {
+    app_version: String,
+    os: &'static str,
+    arch: &'static str,
+    created_at: String,
+}
+
+/// Zip everything needed to reproduce a bug report: app and FFmpeg versions,
+/// settings, recent jobs with the tail of their FFmpeg output, and logs.
+/// Paths are reduced to file names so the bundle doesn't reveal folder names.
+pub async fn create_support_bundle(app_handle: &AppHandle, state: &AppState, path: &Path) -> Result<(), String> {
+    let info = BundleInfo {
+        app_version: app_handle.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let mut jobs = state.get_all_jobs().await;
+    jobs.reverse();
+    if jobs.len() < BUNDLE_JOBS {
+        jobs.extend(state.get_archived_jobs(app_handle, BUNDLE_JOBS - jobs.len())?);
+    }
+    jobs.truncate(BUNDLE_JOBS);
+
+    let file = fs::File::create(path)
+        .map_err(|e| format!("Failed to create support bundle: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+
+    add_json(&mut zip, "info.json", &info)?;
+    add_json(&mut zip, "system-info.json", &get_system_info(app_handle).await)?;
+    match get_ffmpeg_version(app_handle) {
+        Ok(version) => add_json(&mut zip, "ffmpeg-version.json", &version)?,
+        Err(e) => add_file(&mut zip, "ffmpeg-version.txt", e.as_bytes())?,
+    }
+    if let Some(diagnostic) = state.ffmpeg_diagnostic.lock().await.clone() {
+        add_json(&mut zip, "ffmpeg-diagnostic.json", &diagnostic)?;
+    }
+    add_json(&mut zip, "settings.json", &state.get_settings().await)?;
+    add_json(&mut zip, "jobs.json", &jobs)?;
+
+    if let Ok(stderr_dir) = stderr_log_dir(app_handle) {
+        for job in &jobs {
+            if let Some(log) = read_log_tail(&stderr_dir.join(format!("{}.log", job.id))) {
+                add_file(&mut zip, &format!("ffmpeg/{}.log", job.id), redact_home(&log).as_bytes())?;
+            }
+        }
+    }
+
+    // Frontend log, and the backend debug log in development builds
+    let logs = [
+        app_handle.path().app_local_data_dir().ok().map(|dir| dir.join("app-debug.log")),
+        Some(Path::new("ffmpeg_debug.log").to_path_buf()),
+    ];
+    for log_path in logs.into_iter().flatten() {
+        if let Some(log) = read_log_tail(&log_path) {
+            let name = log_path.file_name().unwrap_or_default().to_string_lossy();
+            add_file(&mut zip, &format!("logs/{}", name), redact_home(&log).as_bytes())?;
+        }
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write support bundle: {}", e))?;
+    Ok(())
+}
+
+fn add_file(zip: &mut ZipWriter<fs::File>, name: &str, content: &[u8]) -> Result<(), String> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to write support bundle: {}", e))?;
+    zip.write_all(content)
+        .map_err(|e| format!("Failed to write support bundle: {}", e))
+}
+
+fn add_json(zip: &mut ZipWriter<fs::File>, name: &str, value: &impl Serialize) -> Result<(), String> {
+    let mut value = serde_json::to_value(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    redact_paths(&mut value);
+    let content = serde_json::to_string_pretty(&value)
+        .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    add_file(zip, name, content.as_bytes())
+}
+
+fn read_log_tail(path: &Path) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(MAX_LOG_BYTES))).ok()?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Replace absolute paths anywhere in the value with just their file name
+fn redact_paths(value: &mut Value) {
+    match value {
+        Value::String(text) if is_absolute_path(text) => {
+            let name = Path::new(text.as_str())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            *text = format!("<redacted>/{}", name);
+        }
+        Value::String(text) => *text = redact_home(text),
+        Value::Array(items) => items.iter_mut().for_each(redact_paths),
+        Value::Object(fields) => fields.values_mut().for_each(redact_paths),
+        _ => {}
+    }
+}
+
+fn is_absolute_path(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    text.starts_with('/')
+        || text.starts_with("\\\\")
+        || (bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/'))
+}
+
+/// Hide the user name in paths inside free text such as logs and errors
+fn redact_home(text: &str) -> String {
+    match std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
+        Ok(home) if !home.is_empty() => text.replace(&home, "~"),
+        _ => text.to_string(),
+    }
+}
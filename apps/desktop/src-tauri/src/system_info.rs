@@ -0,0 +1,108 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::process::Command;
+use crate::ffmpeg::get_ffmpeg_binary;
+use crate::ffmpeg_parser::parse_hardware_encoders;
+use crate::ffmpeg_version::get_ffmpeg_version;
+
+/// Machine and FFmpeg details for the diagnostics panel and support bundles
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+    pub app_version: String,
+    pub os: &'static str,
+    pub os_version: Option<String>,
+    pub arch: &'static str,
+    pub cpu_cores: usize,
+    pub total_memory_bytes: Option<u64>,
+    /// Hardware video encoders the FFmpeg build can use
+    pub hardware_encoders: Vec<String>,
+    pub ffmpeg_version: Option<String>,
+    /// "bundled" for the binary shipped with the app, "development" otherwise
+    pub ffmpeg_source: Option<String>,
+}
+
+pub async fn get_system_info(app_handle: &AppHandle) -> SystemInfo {
+    let ffmpeg_path = get_ffmpeg_binary(app_handle).ok();
+    let hardware_encoders = match &ffmpeg_path {
+        Some(path) => command_output(path.as_os_str(), &["-hide_banner", "-encoders"]).await
+            .map(|output| parse_hardware_encoders(&output))
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let ffmpeg_source = ffmpeg_path.map(|path| {
+        let bundled = app_handle.path().resource_dir().is_ok_and(|dir| path.starts_with(dir));
+        if bundled { "bundled" } else { "development" }.to_string()
+    });
+
+    SystemInfo {
+        app_version: app_handle.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        os_version: os_version().await,
+        arch: std::env::consts::ARCH,
+        cpu_cores: std::thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1),
+        total_memory_bytes: total_memory_bytes().await,
+        hardware_encoders,
+        ffmpeg_version: get_ffmpeg_version(app_handle).ok().map(|info| info.version),
+        ffmpeg_source,
+    }
+}
+
+async fn command_output(program: impl AsRef<std::ffi::OsStr>, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(target_os = "macos")]
+async fn os_version() -> Option<String> {
+    command_output("sw_vers", &["-productVersion"]).await
+}
+
+#[cfg(target_os = "windows")]
+async fn os_version() -> Option<String> {
+    command_output("cmd", &["/C", "ver"]).await
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+async fn os_version() -> Option<String> {
+    let release = std::fs::read_to_string("/etc/os-release").ok();
+    let pretty_name = release.as_deref().and_then(|release| {
+        release.lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+            .map(|name| name.trim_matches('"').to_string())
+    });
+    match pretty_name {
+        Some(name) => Some(name),
+        None => command_output("uname", &["-r"]).await,
+    }
+}
+
+#[cfg(target_os = "macos")]
+async fn total_memory_bytes() -> Option<u64> {
+    command_output("sysctl", &["-n", "hw.memsize"]).await?.parse().ok()
+}
+
+#[cfg(target_os = "windows")]
+async fn total_memory_bytes() -> Option<u64> {
+    command_output(
+        "powershell",
+        &["-NoProfile", "-Command", "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory"],
+    ).await?.parse().ok()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+async fn total_memory_bytes() -> Option<u64> {
+    // "MemTotal:       16318460 kB"
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kb: u64 = meminfo.lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
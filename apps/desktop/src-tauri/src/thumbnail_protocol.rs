@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+use crate::history_thumbnails::history_thumbnail_path;
+
+/// Custom URI scheme the webview loads thumbnails from, so images go straight
+/// from the cache folder into `<img>` instead of through base64 over IPC
+pub const THUMBNAIL_SCHEME: &str = "thumbnail";
+
+/// URL of a history entry's thumbnail, the same one `convertFileSrc` builds
+pub fn history_thumbnail_url(history_id: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost/history/{}", THUMBNAIL_SCHEME, history_id)
+    } else {
+        format!("{}://localhost/history/{}", THUMBNAIL_SCHEME, history_id)
+    }
+}
+
+/// Serve `/job/<job id>` and `/history/<history id>` from the thumbnail caches
+pub fn handle(app_handle: &AppHandle, request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    // convertFileSrc encodes the whole path, slashes included
+    let path = request.uri().path().trim_start_matches('/').replace("%2F", "/").replace("%2f", "/");
+    let Some((kind, id)) = path.split_once('/') else {
+        return empty(StatusCode::NOT_FOUND);
+    };
+
+    let thumbnail = match kind {
+        "job" => job_thumbnail_path(app_handle, id),
+        "history" => history_thumbnail_path(app_handle, id),
+        _ => return empty(StatusCode::NOT_FOUND),
+    };
+    let thumbnail = match thumbnail {
+        Ok(Some(thumbnail)) => thumbnail,
+        Ok(None) => return empty(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Refusing thumbnail request for {}: {}", path, e);
+            return empty(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    match fs::read(&thumbnail) {
+        Ok(image_data) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "image/jpeg")
+            // Regenerated thumbnails keep their path, so always revalidate
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(Cow::Owned(image_data))
+            .unwrap_or_else(|_| empty(StatusCode::INTERNAL_SERVER_ERROR)),
+        Err(e) => {
+            eprintln!("Failed to read thumbnail {}: {}", thumbnail.display(), e);
+            empty(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+fn job_thumbnail_path(app_handle: &AppHandle, job_id: &str) -> Result<Option<PathBuf>, String> {
+    // The id becomes part of a path, so only accept real job ids
+    Uuid::parse_str(job_id).map_err(|_| "Invalid job id".to_string())?;
+    let path = app_handle.path().app_cache_dir()
+        .map_err(|e| format!("Failed to get cache dir: {}", e))?
+        .join("thumbnails")
+        .join(format!("{}.jpg", job_id));
+    Ok(path.is_file().then_some(path))
+}
+
+/// A response with just a status, for errors
+pub fn empty(status: StatusCode) -> Response<Cow<'static, [u8]>> {
+    let mut response = Response::new(Cow::Borrowed(&[][..]));
+    *response.status_mut() = status;
+    response
+}
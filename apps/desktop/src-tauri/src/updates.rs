@@ -0,0 +1,104 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use crate::state::AppState;
+
+/// Public key the update manifests are signed with. Builds without one
+/// (local and CI test builds) can't update themselves.
+pub const UPDATER_PUBKEY: Option<&str> = option_env!("TRANSPOZE_UPDATER_PUBKEY");
+
+/// How often a deferred install checks whether the queue has gone idle
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Pre-releases, published to a rolling `beta` release
+    Beta,
+}
+
+impl UpdateChannel {
+    fn endpoint(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "https://github.com/dendotai/transpoze-app/releases/latest/download/latest.json",
+            UpdateChannel::Beta => "https://github.com/dendotai/transpoze-app/releases/download/beta/latest.json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableUpdate {
+    pub version: String,
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+/// An update that's downloaded and verified but not installed yet
+pub struct PendingUpdate {
+    pub update: Update,
+    pub bytes: Vec<u8>,
+}
+
+pub async fn check_for_update(app_handle: &AppHandle, channel: UpdateChannel) -> Result<Option<Update>, String> {
+    if UPDATER_PUBKEY.is_none() {
+        return Err("This build can't update itself".to_string());
+    }
+
+    let endpoint = channel.endpoint().parse()
+        .map_err(|e| format!("Invalid update endpoint: {}", e))?;
+    let updater = app_handle.updater_builder()
+        .endpoints(vec![endpoint])
+        .map_err(|e| format!("Failed to configure updater: {}", e))?
+        .build()
+        .map_err(|e| format!("Failed to configure updater: {}", e))?;
+
+    updater.check().await
+        .map_err(|e| format!("Failed to check for updates: {}", e))
+}
+
+pub fn describe_update(update: &Update, channel: UpdateChannel) -> AvailableUpdate {
+    AvailableUpdate {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        channel,
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+    }
+}
+
+/// Install the pending update and restart once nothing is converting or
+/// waiting. The queue worker is stopped first so no job starts mid-install.
+pub async fn install_when_idle(app_handle: AppHandle, state: AppState) {
+    loop {
+        if !state.queue_status().await.is_busy() {
+            state.stop_queue_worker().await;
+            // A job may have become ready between the check and the stop
+            if !state.queue_status().await.is_busy() {
+                break;
+            }
+            crate::start_queue_processor_if_needed(app_handle.clone(), state.clone()).await;
+        }
+        tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+    }
+
+    let Some(pending) = state.pending_update.lock().await.take() else {
+        return;
+    };
+
+    println!("Queue is idle, installing update {}", pending.update.version);
+    let _ = app_handle.emit("update-installing", &pending.update.version);
+    if let Err(e) = pending.update.install(&pending.bytes) {
+        eprintln!("Failed to install update: {}", e);
+        let _ = app_handle.emit("update-failed", e.to_string());
+        crate::start_queue_processor_if_needed(app_handle.clone(), state.clone()).await;
+        return;
+    }
+
+    app_handle.restart();
+}
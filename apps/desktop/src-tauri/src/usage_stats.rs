@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UsagePeriod {
+    Day,
+    /// Weeks start on Monday
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub conversions: u32,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Seconds of video converted
+    pub seconds_encoded: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, other: &UsageTotals) {
+        self.conversions += other.conversions;
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+        self.seconds_encoded += other.seconds_encoded;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    /// First day of the period, `YYYY-MM-DD`
+    pub start: String,
+    pub conversions: u32,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub minutes_encoded: f64,
+}
+
+/// Per-day totals of finished conversions, added up from the history
+/// database, so the dashboard covers what the retention settings keep.
+/// Stays local.
+#[derive(Debug, Clone, Default)]
+pub struct UsageLog {
+    days: BTreeMap<NaiveDate, UsageTotals>,
+}
+
+impl UsageLog {
+    pub fn record(&mut self, day: NaiveDate, bytes_in: u64, bytes_out: u64, seconds_encoded: f64) {
+        self.days.entry(day).or_default().add(&UsageTotals {
+            conversions: 1,
+            bytes_in,
+            bytes_out,
+            seconds_encoded: seconds_encoded.max(0.0),
+        });
+    }
+
+    /// Totals per period, oldest first. Periods without conversions are left out.
+    pub fn buckets(&self, period: UsagePeriod) -> Vec<UsageBucket> {
+        let mut totals: BTreeMap<NaiveDate, UsageTotals> = BTreeMap::new();
+        for (day, day_totals) in &self.days {
+            totals.entry(period_start(*day, period)).or_default().add(day_totals);
+        }
+
+        totals.into_iter()
+            .map(|(start, totals)| UsageBucket {
+                start: start.format("%Y-%m-%d").to_string(),
+                conversions: totals.conversions,
+                bytes_in: totals.bytes_in,
+                bytes_out: totals.bytes_out,
+                minutes_encoded: totals.seconds_encoded / 60.0,
+            })
+            .collect()
+    }
+}
+
+fn period_start(day: NaiveDate, period: UsagePeriod) -> NaiveDate {
+    match period {
+        UsagePeriod::Day => day,
+        UsagePeriod::Week => day - Duration::days(i64::from(day.weekday().num_days_from_monday())),
+        UsagePeriod::Month => day.with_day(1).unwrap_or(day),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn sample_log() -> UsageLog {
+        let mut log = UsageLog::default();
+        // Sunday, then Monday and Tuesday of the next week
+        log.record(date("2024-03-31"), 1000, 400, 60.0);
+        log.record(date("2024-04-01"), 2000, 500, 120.0);
+        log.record(date("2024-04-02"), 3000, 600, 30.0);
+        log.record(date("2024-04-02"), 500, 100, 30.0);
+        log
+    }
+
+    #[test]
+    fn groups_by_day() {
+        let buckets = sample_log().buckets(UsagePeriod::Day);
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[2].start, "2024-04-02");
+        assert_eq!(buckets[2].conversions, 2);
+        assert_eq!(buckets[2].bytes_in, 3500);
+        assert_eq!(buckets[2].minutes_encoded, 1.0);
+    }
+
+    #[test]
+    fn groups_by_week_starting_monday() {
+        let buckets = sample_log().buckets(UsagePeriod::Week);
+        let starts: Vec<&str> = buckets.iter().map(|bucket| bucket.start.as_str()).collect();
+        assert_eq!(starts, ["2024-03-25", "2024-04-01"]);
+        assert_eq!(buckets[1].conversions, 3);
+        assert_eq!(buckets[1].bytes_out, 1200);
+    }
+
+    #[test]
+    fn groups_by_month() {
+        let buckets = sample_log().buckets(UsagePeriod::Month);
+        let starts: Vec<&str> = buckets.iter().map(|bucket| bucket.start.as_str()).collect();
+        assert_eq!(starts, ["2024-03-01", "2024-04-01"]);
+        assert_eq!(buckets[0].minutes_encoded, 1.0);
+        assert_eq!(buckets[1].minutes_encoded, 3.0);
+    }
+}
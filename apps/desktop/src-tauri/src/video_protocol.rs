@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+use std::io::SeekFrom;
+use std::path::Path;
+use tauri::http::{header, HeaderValue, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use crate::path_scope;
+use crate::state::AppState;
+use crate::thumbnail_protocol::empty;
+
+/// Custom URI scheme the webview plays source videos from. The path is the
+/// file's absolute path, as `convertFileSrc(path, 'video')` encodes it.
+pub const VIDEO_SCHEME: &str = "video";
+
+/// Most bytes sent for one range, so open ended requests like `bytes=0-`
+/// don't read the whole file into memory
+const MAX_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Serve part of a video the user has access to, as `206 Partial Content`.
+/// Requests without a range get the first chunk, which players follow up
+/// with range requests.
+pub async fn handle(app_handle: &AppHandle, request: &Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let Some(path) = decode_path(request.uri().path()) else {
+        return empty(StatusCode::BAD_REQUEST);
+    };
+    let state = app_handle.state::<AppState>();
+    let path = match path_scope::ensure_allowed(app_handle, &state, &path).await {
+        Ok(path) => path,
+        Err(_) => return empty(StatusCode::FORBIDDEN),
+    };
+
+    let range = request.headers().get(header::RANGE).and_then(|value| value.to_str().ok());
+    match read_range(&path, range).await {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Failed to stream {}: {}", path.display(), e);
+            empty(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+async fn read_range(path: &Path, range: Option<&str>) -> Result<Response<Cow<'static, [u8]>>, std::io::Error> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+
+    let Some((start, end)) = parse_range(range.unwrap_or("bytes=0-"), total) else {
+        let mut response = empty(StatusCode::RANGE_NOT_SATISFIABLE);
+        if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", total)) {
+            response.headers_mut().insert(header::CONTENT_RANGE, value);
+        }
+        return Ok(response);
+    };
+    let end = end.min(start + MAX_CHUNK_SIZE - 1);
+
+    let mut chunk = vec![0; (end - start + 1) as usize];
+    file.seek(SeekFrom::Start(start)).await?;
+    file.read_exact(&mut chunk).await?;
+
+    Ok(Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header(header::CONTENT_TYPE, content_type(path))
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total))
+        .header(header::CONTENT_LENGTH, chunk.len())
+        .body(Cow::Owned(chunk))
+        .unwrap_or_else(|_| empty(StatusCode::INTERNAL_SERVER_ERROR)))
+}
+
+/// The file path from a request path. `convertFileSrc` percent-encodes the
+/// whole path, slashes and Windows drive colons included.
+fn decode_path(request_path: &str) -> Option<String> {
+    let encoded = request_path.strip_prefix('/').unwrap_or(request_path).as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' {
+            let hex = std::str::from_utf8(encoded.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(encoded[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok().filter(|path| !path.is_empty())
+}
+
+/// First and last byte of a single `Range: bytes=...` header, clamped to the
+/// file. None when the range can't be satisfied.
+fn parse_range(range: &str, total: u64) -> Option<(u64, u64)> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    // Players only ask for one range; serve the first of several
+    let (start, end) = spec.split(',').next()?.trim().split_once('-')?;
+    if total == 0 {
+        return None;
+    }
+
+    if start.is_empty() {
+        // `bytes=-500` is the last 500 bytes
+        let suffix: u64 = end.parse().ok()?;
+        if suffix == 0 {
+            return None;
+        }
+        return Some((total.saturating_sub(suffix), total - 1));
+    }
+
+    let start: u64 = start.parse().ok()?;
+    let end = if end.is_empty() { total - 1 } else { end.parse::<u64>().ok()?.min(total - 1) };
+    (start <= end).then_some((start, end))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "ogv" | "ogg" => "video/ogg",
+        "avi" => "video/x-msvideo",
+        "gif" => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-", 1000), Some((0, 999)));
+        assert_eq!(parse_range("bytes=100-199", 1000), Some((100, 199)));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=0-9, 20-29", 1000), Some((0, 9)));
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=50-10", 1000), None);
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+        assert_eq!(parse_range("items=0-10", 1000), None);
+    }
+
+    #[test]
+    fn decodes_encoded_paths() {
+        assert_eq!(decode_path("/%2FUsers%2Fme%2FMy%20Movie.mov").as_deref(), Some("/Users/me/My Movie.mov"));
+        assert_eq!(decode_path("/C%3A%5CVideos%5Cclip.mp4").as_deref(), Some("C:\\Videos\\clip.mp4"));
+        assert_eq!(decode_path("/%2Fclips%2F%C3%A9t%C3%A9.mp4").as_deref(), Some("/clips/été.mp4"));
+        assert_eq!(decode_path("/%2Fbroken%2"), None);
+        assert_eq!(decode_path("/"), None);
+    }
+}
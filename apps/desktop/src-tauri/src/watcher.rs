@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use crate::ffmpeg::{partial_output_path, JobOptions};
+use crate::state::AppState;
+
+/// How often watched folders are checked for new files
+const SCAN_INTERVAL: Duration = Duration::from_secs(5);
+const VIDEO_EXTENSIONS: &[&str] = &["webm", "mp4", "mov", "m4v", "mkv", "avi"];
+
+/// A folder whose new videos are converted with a preset as they show up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchFolder {
+    pub path: String,
+    /// Name of a built-in or user preset, looked up when a file is queued
+    pub preset: String,
+    /// Watched while the app runs; off after `stop_watch_folder`
+    pub enabled: bool,
+}
+
+/// What a scan knows about a file in a watched folder
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SeenFile {
+    /// Queued, or already there when watching started
+    Handled,
+    /// New, with the size it had at the last scan
+    Growing(u64),
+}
+
+/// Start watching a folder unless it's already watched
+pub async fn start(app_handle: &AppHandle, state: &AppState, folder: &str) {
+    let mut watchers = state.watchers.lock().await;
+    if watchers.get(folder).is_some_and(|task| !task.inner().is_finished()) {
+        return;
+    }
+
+    let task = tauri::async_runtime::spawn(watch_folder(app_handle.clone(), state.clone(), folder.to_string()));
+    watchers.insert(folder.to_string(), task);
+    println!("Watching {} for new videos", folder);
+}
+
+pub async fn stop(state: &AppState, folder: &str) {
+    if let Some(task) = state.watchers.lock().await.remove(folder) {
+        task.abort();
+        println!("Stopped watching {}", folder);
+    }
+}
+
+/// Start every enabled watch folder from settings
+pub async fn start_enabled(app_handle: &AppHandle, state: &AppState) {
+    for rule in state.get_settings().await.watch_folders {
+        if rule.enabled {
+            start(app_handle, state, &rule.path).await;
+        }
+    }
+}
+
+async fn watch_folder(app_handle: AppHandle, state: AppState, folder: String) {
+    // Only files that appear from now on are converted
+    let mut seen: HashMap<PathBuf, SeenFile> = scan(Path::new(&folder))
+        .into_iter()
+        .map(|(path, _)| (path, SeenFile::Handled))
+        .collect();
+
+    let mut interval = tokio::time::interval(SCAN_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let files = scan(Path::new(&folder));
+        // Forget deleted files so one copied in again with the same name is converted
+        seen.retain(|path, _| files.iter().any(|(file, _)| file == path));
+
+        for (path, size) in files {
+            match seen.get(&path) {
+                Some(SeenFile::Handled) => {}
+                // Unchanged since the last scan, so it has finished copying
+                Some(SeenFile::Growing(last_size)) if *last_size == size && size > 0 => {
+                    seen.insert(path.clone(), SeenFile::Handled);
+                    if let Err(e) = queue_file(&app_handle, &state, &folder, &path).await {
+                        eprintln!("Failed to queue {} from watch folder: {}", path.display(), e);
+                    }
+                }
+                _ => {
+                    seen.insert(path, SeenFile::Growing(size));
+                }
+            }
+        }
+    }
+}
+
+/// Videos directly inside `folder` with their sizes. Subfolders aren't
+/// scanned, which also keeps outputs in the "converted" subfolder out.
+fn scan(folder: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter(|entry| is_video(&entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| (entry.path(), metadata.len()))
+        })
+        .collect()
+}
+
+fn is_video(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.as_str()))
+}
+
+async fn queue_file(app_handle: &AppHandle, state: &AppState, folder: &str, path: &Path) -> Result<(), String> {
+    let jobs = state.get_all_jobs().await;
+    let input_path = path.to_string_lossy().to_string();
+    // Outputs written back into the watched folder would be converted again
+    let known = jobs.iter().any(|job| {
+        job.input_path == input_path
+            || job.output_path == input_path
+            || partial_output_path(&job.output_path) == input_path
+    });
+    if known {
+        return Ok(());
+    }
+
+    let settings = state.get_settings().await;
+    let rule = settings.watch_folders.iter()
+        .find(|rule| rule.path == folder)
+        .ok_or_else(|| "Watch folder was removed".to_string())?;
+    let preset = state.list_presets().await
+        .into_iter()
+        .find(|preset| preset.name == rule.preset)
+        .ok_or_else(|| format!("Preset '{}' not found", rule.preset))?;
+
+    let output_path = crate::output_path_for_input(app_handle, state, &input_path, &preset, None, None, None).await;
+
+    println!("Queueing {} from watch folder {}", input_path, folder);
+    crate::enqueue_job(
+        app_handle,
+        state,
+        input_path,
+        output_path,
+        preset,
+        JobOptions::default(),
+        None,
+    ).await?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use crate::state::AppSettings;
+
+/// Work files need at least this much room left on the scratch volume
+pub const MIN_FREE_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// Scratch folder for work files such as two-pass logs, chunks and partial
+/// outputs. Lives in the configured temp directory, or the app cache dir.
+pub fn work_dir(app_handle: &AppHandle, settings: &AppSettings) -> Result<PathBuf, String> {
+    let base = if settings.temp_directory.is_empty() {
+        app_handle.path().app_cache_dir()
+            .map_err(|e| format!("Failed to get cache dir: {}", e))?
+    } else {
+        PathBuf::from(&settings.temp_directory)
+    };
+
+    let dir = base.join("work");
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create work directory {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// A temp directory from settings has to be an existing folder with room
+/// for work files
+pub async fn validate_temp_directory(temp_directory: &str) -> Result<(), String> {
+    if temp_directory.is_empty() {
+        return Ok(());
+    }
+    let dir = Path::new(temp_directory);
+    if !dir.is_dir() {
+        return Err(format!("Temp folder {} doesn't exist", temp_directory));
+    }
+    ensure_free_space(dir, MIN_FREE_SPACE).await
+}
+
+pub async fn ensure_free_space(dir: &Path, required_bytes: u64) -> Result<(), String> {
+    match available_space(dir).await {
+        Some(available) if available < required_bytes => Err(format!(
+            "Not enough free space in {}: {} MB available, {} MB needed",
+            dir.display(),
+            available / 1024 / 1024,
+            required_bytes / 1024 / 1024,
+        )),
+        // Carry on when the free space can't be determined
+        _ => Ok(()),
+    }
+}
+
+/// Free bytes on the volume holding `dir`
+#[cfg(unix)]
+async fn available_space(dir: &Path) -> Option<u64> {
+    // POSIX format: "Filesystem 1024-blocks Used Available Capacity Mounted on"
+    let output = tokio::process::Command::new("df").arg("-Pk").arg(dir).output().await.ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available_kb: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb * 1024)
+}
+
+#[cfg(windows)]
+async fn available_space(dir: &Path) -> Option<u64> {
+    let script = format!("(Get-Item -LiteralPath '{}').PSDrive.Free", dir.display().to_string().replace('\'', "''"));
+    let output = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .output()
+        .await
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Remove work files left behind by a previous session that crashed or was
+/// killed mid-conversion. Only call this before any conversion starts.
+pub fn clean_stale_work_files(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        match result {
+            Ok(()) => removed += 1,
+            Err(e) => eprintln!("Failed to remove stale work file {}: {}", path.display(), e),
+        }
+    }
+    if removed > 0 {
+        println!("Removed {} stale work files from {}", removed, dir.display());
+    }
+}
@@ -32,7 +32,11 @@ function App() {
 
   // Check if there are any completed or failed jobs to show the clear button
   const hasCompletedOrFailed = jobs.some(
-    (job) => job.status === 'completed' || job.status === 'failed',
+    (job) =>
+      job.status === 'completed' ||
+      job.status === 'completedWithWarnings' ||
+      job.status === 'failed' ||
+      job.status === 'cancelled',
   );
 
   const navigateToSettings = (highlight = false) => {
@@ -1,4 +1,7 @@
+import { useEffect, useState } from 'react';
 import { ArrowRight } from 'lucide-react';
+import { invoke } from '@tauri-apps/api/core';
+import { useConverter } from '../hooks/useConverter';
 
 interface FilePathPreviewProps {
   inputFileName?: string;
@@ -17,40 +20,26 @@ export function FilePathPreview({
   fileNamePattern = '{name}_converted',
   size = 'small'
 }: FilePathPreviewProps) {
-  // Validate pattern function
-  const validatePattern = (pattern: string | undefined): boolean => {
-    if (!pattern || typeof pattern !== 'string') return true;
-    let braceCount = 0;
-    for (const char of pattern) {
-      if (char === '{') braceCount++;
-      if (char === '}') braceCount--;
-      if (braceCount < 0) return false;
-    }
-    if (braceCount !== 0) return false;
-    
-    const placeholderRegex = /\{([^}]+)\}/g;
-    const matches = pattern.match(placeholderRegex);
-    if (matches) {
-      for (const match of matches) {
-        if (match !== '{name}' && match !== '{number}') return false;
-      }
-    }
-    
-    const invalidChars = /[<>:"|?*\\/]/;
-    const withoutPlaceholders = pattern.replace(placeholderRegex, '');
-    if (invalidChars.test(withoutPlaceholders)) return false;
-    
-    return true;
-  };
+  const selectedPreset = useConverter((state) => state.selectedPreset);
+  const [outputFileName, setOutputFileName] = useState('');
 
-  // Extract base name without extension
-  const baseName = inputFileName.replace('.webm', '');
-  
-  // Generate output filename
-  const safePattern = validatePattern(fileNamePattern) ? fileNamePattern : '{name}_converted';
-  const outputFileName = safePattern
-    .replace(/\{name\}/g, baseName)  // Replace all instances of {name}
-    .replace(/\{number\}/g, '0') + '.mp4';  // Replace all instances of {number}
+  // Let the backend expand the pattern so the preview matches the real output
+  useEffect(() => {
+    if (!selectedPreset) return;
+    let cancelled = false;
+    invoke<string>('resolve_output_path', {
+      inputPath: inputFileName,
+      preset: selectedPreset,
+      pattern: fileNamePattern,
+    })
+      .then((path) => {
+        if (!cancelled) setOutputFileName(path.split(/[\\/]/).pop() || path);
+      })
+      .catch((error) => console.error('Failed to resolve output name:', error));
+    return () => {
+      cancelled = true;
+    };
+  }, [inputFileName, fileNamePattern, selectedPreset]);
 
   // Style configurations
   const styles = {
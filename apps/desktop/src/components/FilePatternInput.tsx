@@ -1,5 +1,6 @@
 import React, { useState, useRef, useEffect, useCallback, forwardRef, useImperativeHandle } from 'react';
 import { Check, X } from 'lucide-react';
+import { PATTERN_VARIABLES, isValidPattern } from '../utils/outputPattern';
 
 interface FilePatternInputProps {
   value: string;
@@ -8,7 +9,7 @@ interface FilePatternInputProps {
   className?: string;
 }
 
-const VARIABLES = ['{name}', '{number}'];
+const VARIABLES = PATTERN_VARIABLES;
 
 export interface FilePatternInputHandle {
   input: HTMLInputElement | null;
@@ -37,32 +38,6 @@ export const FilePatternInput = forwardRef<FilePatternInputHandle, FilePatternIn
     cursorPosition
   }), [cursorPosition]);
 
-  // Validate pattern
-  const validatePattern = (pattern: string | undefined): boolean => {
-    if (!pattern || typeof pattern !== 'string') return true;
-    let braceCount = 0;
-    for (const char of pattern) {
-      if (char === '{') braceCount++;
-      if (char === '}') braceCount--;
-      if (braceCount < 0) return false;
-    }
-    if (braceCount !== 0) return false;
-    
-    const placeholderRegex = /\{([^}]+)\}/g;
-    const matches = pattern.match(placeholderRegex);
-    if (matches) {
-      for (const match of matches) {
-        if (match !== '{name}' && match !== '{number}') return false;
-      }
-    }
-    
-    const invalidChars = /[<>:"|?*\\/]/;
-    const withoutPlaceholders = pattern.replace(placeholderRegex, '');
-    if (invalidChars.test(withoutPlaceholders)) return false;
-    
-    return true;
-  };
-
   // Calculate suggestion
   const calculateSuggestion = useCallback((val: string, cursor: number) => {
     const beforeCursor = val.substring(0, cursor);
@@ -197,7 +172,7 @@ export const FilePatternInput = forwardRef<FilePatternInputHandle, FilePatternIn
     }
   };
 
-  const isValid = validatePattern(value);
+  const isValid = isValidPattern(value);
 
   return (
     <div className="relative flex-1">
@@ -244,7 +219,7 @@ export const FilePatternInput = forwardRef<FilePatternInputHandle, FilePatternIn
               const containsSuggestion = suggestion && partStart < suggestionEnd && partEnd > suggestionStart;
               
               if (part.startsWith('{')) {
-                const isValid = part.endsWith('}') && (part === '{name}' || part === '{number}');
+                const isValid = part.endsWith('}') && VARIABLES.includes(part);
                 const isIncomplete = part === '{' || (part.startsWith('{') && !part.endsWith('}'));
                 
                 // If this part contains suggestion, split it
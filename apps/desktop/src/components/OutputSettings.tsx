@@ -4,6 +4,7 @@ import { Folder, RotateCcw } from 'lucide-react';
 import { open } from '@tauri-apps/plugin-dialog';
 import { FilePathPreview } from './FilePathPreview';
 import { FilePatternInput, FilePatternInputHandle } from './FilePatternInput';
+import { PATTERN_VARIABLES } from '../utils/outputPattern';
 
 interface OutputSettingsProps {
   highlighted?: boolean;
@@ -24,7 +25,7 @@ export function OutputSettings({ highlighted = false, onInteraction }: OutputSet
     const matches = pattern.match(placeholderRegex);
     if (matches) {
       for (const match of matches) {
-        if (match !== '{name}' && match !== '{number}') return false;
+        if (!PATTERN_VARIABLES.includes(match)) return false;
       }
     }
     return !pattern.includes('{') || pattern.split('{').length - 1 === pattern.split('}').length - 1;
@@ -382,7 +383,8 @@ export function OutputSettings({ highlighted = false, onInteraction }: OutputSet
                 handleVariableClick('{number}');
               }}
               className="text-primary-600 dark:text-primary-400 hover:text-primary-700 dark:hover:text-primary-300 cursor-pointer transition-colors"
-            >{'{number}'}</button> for auto-incrementing number
+            >{'{number}'}</button> for auto-incrementing number.
+            Also available: {'{preset}'}, {'{date}'}, {'{resolution}'} and {'{codec}'}
           </p>
           <div className={`overflow-hidden transition-all ease-out ${
             fileNamePattern && !isPatternValid(fileNamePattern) ? 'max-h-20 duration-300' : 'max-h-0 duration-200'
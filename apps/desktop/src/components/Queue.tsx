@@ -1,11 +1,14 @@
-import { useConverter } from '../hooks/useConverter';
-import { FileVideo, CheckCircle, XCircle, Loader2, FolderOpen } from 'lucide-react';
+import { useConverter, type ConversionProgress } from '../hooks/useConverter';
+import { FileVideo, CheckCircle, XCircle, Loader2, FolderOpen, X, AlertTriangle } from 'lucide-react';
 import { logger } from '../utils/simpleLogger';
 import { invoke } from '@tauri-apps/api/core';
 import { VideoThumbnail } from './VideoThumbnail';
+import { formatStatusMessage } from '../utils/statusMessages';
 
 export function Queue() {
   const jobs = useConverter((state) => state.jobs);
+  const removeJob = useConverter((state) => state.removeJob);
+  const liveProgress = useConverter((state) => state.liveProgress);
   
   // Show all jobs - keep completed/failed jobs visible
   const activeJobs = jobs;
@@ -58,7 +61,7 @@ export function Queue() {
           >
             <div className="flex items-start gap-3">
               {job.thumbnailPath ? (
-                <VideoThumbnail jobId={job.id} fileName={fileName} />
+                <VideoThumbnail jobId={job.id} fileName={fileName} version={job.thumbnailTime} />
               ) : (
                 <div className="w-20 h-20 bg-gray-100 dark:bg-gray-700 rounded flex items-center justify-center flex-shrink-0">
                   <FileVideo className="w-8 h-8 text-gray-400" />
@@ -69,7 +72,16 @@ export function Queue() {
                   <h4 className="font-medium text-sm text-gray-800 dark:text-gray-200 truncate">
                     {fileName}
                   </h4>
-                  <StatusIcon status={job.status} />
+                  <div className="flex items-center gap-2">
+                    <StatusIcon status={job.status} />
+                    <button
+                      onClick={() => removeJob(job.id)}
+                      className="p-0.5 text-gray-400 hover:text-gray-600 dark:hover:text-gray-200 rounded transition-colors"
+                      title={job.status === 'processing' ? 'Stop and remove' : 'Remove'}
+                    >
+                      <X className="w-3.5 h-3.5" />
+                    </button>
+                  </div>
                 </div>
                 <div className="mt-1">
                   <span className="text-xs px-2 py-1 bg-green-100 dark:bg-green-900/30 text-green-700 dark:text-green-400 rounded">
@@ -78,13 +90,13 @@ export function Queue() {
                 </div>
                 {(job.status === 'queued' || job.status === 'ready') && job.statusMessage && (
                   <div className="mt-2 text-xs text-gray-500 dark:text-gray-400">
-                    {job.statusMessage}
+                    {formatStatusMessage(job.statusMessage)}
                   </div>
                 )}
                 {job.status === 'processing' && (
                   <div className="mt-3">
                     <div className="flex items-center justify-between text-xs text-gray-600 dark:text-gray-400 mb-1">
-                      <span>{job.statusMessage || 'Processing...'}</span>
+                      <span>{formatStatusMessage(job.statusMessage) || 'Processing...'}</span>
                       <span>{Math.round(job.progress || 0)}%</span>
                     </div>
                     <div className="w-full bg-gray-200 dark:bg-gray-700 rounded-full h-2 overflow-hidden">
@@ -95,6 +107,11 @@ export function Queue() {
                         <div className="absolute inset-0 bg-white/20 animate-pulse" />
                       </div>
                     </div>
+                    {liveProgress[job.id] && (
+                      <div className="mt-1 text-xs text-gray-500 dark:text-gray-400">
+                        {formatProgressStats(liveProgress[job.id]!)}
+                      </div>
+                    )}
                   </div>
                 )}
                 {job.error && (
@@ -104,7 +121,7 @@ export function Queue() {
                 )}
               </div>
             </div>
-            {job.status === 'completed' && (
+            {(job.status === 'completed' || job.status === 'completedWithWarnings') && (
               <div className="absolute bottom-3 right-3">
                 <button
                   onClick={() => handleRevealInFinder(job.outputPath)}
@@ -123,6 +140,18 @@ export function Queue() {
   );
 }
 
+function formatProgressStats(progress: ConversionProgress): string {
+  const parts: string[] = [];
+  if (progress.fps) parts.push(`${Math.round(progress.fps)} fps`);
+  if (progress.speed) parts.push(`${progress.speed.toFixed(2)}x`);
+  if (progress.outputBytes) parts.push(`${(progress.outputBytes / 1024 / 1024).toFixed(1)} MB`);
+  if (progress.etaSeconds !== undefined && progress.etaSeconds !== null) {
+    const seconds = Math.round(progress.etaSeconds);
+    parts.push(`${Math.floor(seconds / 60)}:${String(seconds % 60).padStart(2, '0')} left`);
+  }
+  return parts.join(' · ');
+}
+
 function StatusIcon({ status }: { status: string }) {
   switch (status) {
     case 'queued':
@@ -133,8 +162,12 @@ function StatusIcon({ status }: { status: string }) {
       return <Loader2 className="w-4 h-4 text-blue-500 animate-spin" />;
     case 'completed':
       return <CheckCircle className="w-4 h-4 text-green-500" />;
+    case 'completedWithWarnings':
+      return <AlertTriangle className="w-4 h-4 text-amber-500" />;
     case 'failed':
       return <XCircle className="w-4 h-4 text-red-500" />;
+    case 'cancelled':
+      return <XCircle className="w-4 h-4 text-gray-400" />;
     default:
       return null;
   }
@@ -1,58 +1,53 @@
 import { useState, useEffect } from 'react';
-import { invoke } from '@tauri-apps/api/core';
+import { convertFileSrc } from '@tauri-apps/api/core';
 import { FileVideo } from 'lucide-react';
 
 interface VideoThumbnailProps {
   jobId: string;
   fileName: string;
+  /** Changes when the thumbnail is regenerated, so it gets reloaded */
+  version?: number;
 }
 
-export function VideoThumbnail({ jobId, fileName }: VideoThumbnailProps) {
-  const [thumbnailUrl, setThumbnailUrl] = useState<string | null>(null);
-  const [loading, setLoading] = useState(true);
+export function VideoThumbnail({ jobId, fileName, version }: VideoThumbnailProps) {
+  const [loaded, setLoaded] = useState(false);
   const [error, setError] = useState(false);
   const [retryCount, setRetryCount] = useState(0);
 
   useEffect(() => {
-    async function loadThumbnail() {
-      try {
-        const thumbnailData = await invoke<string>('get_thumbnail_data', { jobId });
-        setThumbnailUrl(thumbnailData);
-        setError(false);
-      } catch (err) {
-        console.error('Failed to load thumbnail for job', jobId, ':', err);
-        // Retry a few times as thumbnail might still be generating
-        if (retryCount < 5) {
-          setTimeout(() => {
-            setRetryCount(prev => prev + 1);
-          }, 1000);
-        } else {
-          setError(true);
-        }
-      } finally {
-        setLoading(false);
-      }
-    }
+    setLoaded(false);
+    setError(false);
+    setRetryCount(0);
+  }, [jobId, version]);
 
-    loadThumbnail();
-  }, [jobId, retryCount]);
+  // Served from the cache folder by the backend's thumbnail protocol
+  const thumbnailUrl = `${convertFileSrc(`job/${jobId}`, 'thumbnail')}?v=${version ?? 0}&retry=${retryCount}`;
 
-  if (loading || error || !thumbnailUrl) {
-    return (
-      <div className="w-20 h-20 bg-gray-100 dark:bg-gray-700 rounded flex items-center justify-center flex-shrink-0">
-        <FileVideo className="w-8 h-8 text-gray-400" />
-      </div>
-    );
+  function handleError() {
+    // Retry a few times as thumbnail might still be generating
+    if (retryCount < 5) {
+      setTimeout(() => {
+        setRetryCount(prev => prev + 1);
+      }, 1000);
+    } else {
+      console.error('Failed to load thumbnail for job', jobId);
+      setError(true);
+    }
   }
 
   return (
     <div className="w-20 h-20 bg-gray-100 dark:bg-gray-700 rounded overflow-hidden flex-shrink-0 flex items-center justify-center relative">
-      <img
-        src={thumbnailUrl}
-        alt={fileName}
-        className="w-full h-full object-cover"
-        onError={() => setError(true)}
-      />
+      {(!loaded || error) && <FileVideo className="w-8 h-8 text-gray-400" />}
+      {!error && (
+        <img
+          key={thumbnailUrl}
+          src={thumbnailUrl}
+          alt={fileName}
+          className={loaded ? 'w-full h-full object-cover' : 'hidden'}
+          onLoad={() => setLoaded(true)}
+          onError={handleError}
+        />
+      )}
     </div>
   );
-}
\ No newline at end of file
+}
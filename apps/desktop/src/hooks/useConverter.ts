@@ -1,7 +1,9 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
+import { ask } from '@tauri-apps/plugin-dialog';
 import { useEffect } from 'react';
+import { type StatusMessage } from '../utils/statusMessages';
 import { logger } from '../utils/simpleLogger';
 
 export interface VideoPreset {
@@ -12,6 +14,9 @@ export interface VideoPreset {
   bitrate?: string;
   crf?: number;
   scale?: string;
+  container?: string;
+  /** Advanced: FFmpeg options added right before the output path */
+  extraArgs?: string[];
 }
 
 export interface ConversionJob {
@@ -19,12 +24,44 @@ export interface ConversionJob {
   inputPath: string;
   outputPath: string;
   preset: VideoPreset;
-  status: 'queued' | 'ready' | 'processing' | 'completed' | 'failed';
+  status: 'queued' | 'ready' | 'processing' | 'completed' | 'completedWithWarnings' | 'failed' | 'cancelled';
   progress: number;
   duration?: number;
   error?: string;
-  statusMessage?: string;
+  /** Kind of the error, e.g. `ffmpegNotFound` or `encodeFailed` */
+  errorCode?: string;
+  statusMessage?: StatusMessage;
   thumbnailPath?: string;
+  thumbnailTime?: number;
+  batchId?: string;
+  /** Rough output size and time, once the source has been analyzed */
+  estimate?: OutputEstimate;
+  /** How close the output is to the source, when quality scoring is on */
+  qualityScore?: QualityScore;
+}
+
+export interface OutputEstimate {
+  sizeBytes: number;
+  seconds?: number;
+  /** Converting would make the file bigger */
+  largerThanSource: boolean;
+}
+
+/** SSIM runs from 0 to 1, VMAF from 0 to 100 */
+export interface QualityScore {
+  metric: 'ssim' | 'vmaf';
+  score: number;
+}
+
+/** Live stats of the running conversion from the `conversion-progress` event */
+export interface ConversionProgress {
+  jobId: string;
+  percent: number;
+  fps?: number;
+  speed?: number;
+  bitrateKbps?: number;
+  outputBytes?: number;
+  etaSeconds?: number;
 }
 
 export interface ConversionHistory {
@@ -36,124 +73,11 @@ export interface ConversionHistory {
   fileSizeBefore: number;
   fileSizeAfter: number;
   duration: number;
-}
-
-function validatePattern(pattern: string | undefined): boolean {
-  if (!pattern || typeof pattern !== 'string') return true;
-  // Check for balanced braces
-  let braceCount = 0;
-  for (const char of pattern) {
-    if (char === '{') braceCount++;
-    if (char === '}') braceCount--;
-    if (braceCount < 0) return false;
-  }
-  if (braceCount !== 0) return false;
-  
-  // Check for valid placeholders ({name} and {number} are supported)
-  const placeholderRegex = /\{([^}]+)\}/g;
-  const matches = pattern.match(placeholderRegex);
-  if (matches) {
-    for (const match of matches) {
-      if (match !== '{name}' && match !== '{number}') return false;
-    }
-  }
-  
-  // Check for invalid characters in filename
-  const invalidChars = /[<>:"|?*\\/]/;
-  const withoutPlaceholders = pattern.replace(placeholderRegex, '');
-  if (invalidChars.test(withoutPlaceholders)) return false;
-  
-  return true;
-}
-
-export function getOutputPath(
-  inputPath: string,
-  outputDirectory: string,
-  useSubdirectory: boolean,
-  subdirectoryName: string,
-  fileNamePattern: string,
-  existingPaths?: string[],
-  forceNumber?: number,
-  totalFiles?: number
-): string {
-  // logger.info('getOutputPath called', {
-  //   inputPath,
-  //   outputDirectory,
-  //   useSubdirectory,
-  //   subdirectoryName,
-  //   fileNamePattern
-  // });
-  
-  const fileName = inputPath.split('/').pop()?.replace('.webm', '') || 'video';
-  
-  // Use safe default pattern if current pattern is invalid
-  const safePattern = validatePattern(fileNamePattern) ? fileNamePattern : '{name}_converted';
-  
-  // Ensure directory doesn't end with slashes to avoid double slashes
-  const normalizeDir = (dir: string) => dir.replace(/\/+$/, '');
-  
-  // Determine base directory
-  let baseDir: string;
-  if (outputDirectory) {
-    const normalizedOutputDir = normalizeDir(outputDirectory);
-    baseDir = useSubdirectory ? `${normalizedOutputDir}/${subdirectoryName}` : normalizedOutputDir;
-  } else {
-    // Same directory as input
-    const inputDir = inputPath.substring(0, inputPath.lastIndexOf('/'));
-    const normalizedInputDir = normalizeDir(inputDir);
-    baseDir = useSubdirectory ? `${normalizedInputDir}/${subdirectoryName}` : normalizedInputDir;
-  }
-  
-  // Calculate padding width based on total files or existing paths
-  const maxNumber = totalFiles ? totalFiles - 1 : (existingPaths ? existingPaths.length + 10 : 0);
-  const padWidth = Math.max(1, Math.ceil(Math.log10(maxNumber + 1)));
-  
-  // Function to generate output path with a given number
-  const generatePath = (num: number, forceNumbering: boolean = false): string => {
-    let outputName = safePattern;
-    
-    // Handle {number} placeholder
-    if (outputName.includes('{number}')) {
-      // Always pad numbers when using {number} placeholder
-      const paddedNum = num.toString().padStart(padWidth, '0');
-      outputName = outputName.replace('{name}', fileName).replace('{number}', paddedNum);
-    } else if (forceNumbering || (num > 0 && existingPaths && existingPaths.length > 0)) {
-      // Auto-numbering when no {number} placeholder and multiple files or overwriting
-      const paddedNum = num.toString().padStart(padWidth, '0');
-      outputName = outputName.replace('{name}', fileName) + `-${paddedNum}`;
-    } else {
-      outputName = outputName.replace('{name}', fileName);
-    }
-    
-    return `${baseDir}/${outputName}.mp4`;
-  };
-  
-  // If forceNumber is provided, use it directly
-  if (forceNumber !== undefined) {
-    return generatePath(forceNumber, true);
-  }
-  
-  // Only force numbering for batch if pattern has {number} placeholder
-  if (totalFiles && totalFiles > 1 && safePattern.includes('{number}')) {
-    return generatePath(0, true);
-  }
-  
-  // If no existing paths provided, return without numbering
-  if (!existingPaths || existingPaths.length === 0) {
-    return generatePath(0);
-  }
-  
-  // Check if the base path (without number) already exists
-  let number = 0;
-  let outputPath = generatePath(number);
-  
-  while (existingPaths.includes(outputPath)) {
-    number++;
-    outputPath = generatePath(number, true);
-  }
-  
-  // logger.info('getOutputPath result', { outputPath });
-  return outputPath;
+  /** The output came out bigger than the source */
+  largerThanOriginal?: boolean;
+  qualityScore?: QualityScore;
+  /** What verifying the output found wrong with it */
+  problems?: string[];
 }
 
 interface AppSettings {
@@ -175,6 +99,7 @@ interface ConverterState {
   selectedPreset: VideoPreset | null;
   presetsLoaded: boolean;
   zoomedThumbnails: boolean;
+  liveProgress: Record<string, ConversionProgress>;
   
   // Actions
   loadPresets: () => Promise<void>;
@@ -184,6 +109,7 @@ interface ConverterState {
   loadHistory: () => Promise<void>;
   loadPersistedData: () => Promise<void>;
   clearCompletedJobs: () => Promise<void>;
+  removeJob: (jobId: string) => Promise<void>;
   clearHistory: () => Promise<void>;
   setOutputDirectory: (dir: string) => void;
   setUseSubdirectory: (use: boolean) => void;
@@ -213,11 +139,12 @@ export const useConverter = create<ConverterState>((set, get) => ({
   selectedPreset: null,
   presetsLoaded: false,
   zoomedThumbnails: false,
+  liveProgress: {},
 
   loadPresets: async () => {
     try {
       logger.info('Loading video presets...');
-      const presets = await invoke<VideoPreset[]>('get_video_presets');
+      const presets = await invoke<VideoPreset[]>('list_presets');
       console.log('Loaded presets:', presets);
       // logger.info('Loaded presets', presets);
       
@@ -294,50 +221,11 @@ export const useConverter = create<ConverterState>((set, get) => ({
     }
 
     try {
-      // Collect all existing output paths from jobs and history
-      const existingOutputPaths = [
-        ...state.jobs.map(job => job.outputPath),
-        ...state.history.map(h => h.outputPath)
-      ];
-      
-      // Also check the file system for existing files
-      const checkPath = async (path: string): Promise<boolean> => {
-        try {
-          const exists = await invoke<boolean>('check_file_exists', { path });
-          return exists;
-        } catch (error) {
-          console.error('Error checking file existence:', error);
-          return false;
-        }
-      };
-      
-      // Generate output path with auto-numbering if needed
-      let outputPath = getOutputPath(
+      // The backend expands the pattern and skips names that are already taken
+      const outputPath = await invoke<string>('resolve_output_path', {
         inputPath,
-        state.outputDirectory || '',
-        state.useSubdirectory,
-        state.subdirectoryName,
-        state.fileNamePattern,
-        existingOutputPaths
-      );
-      
-      // Check if the file already exists on disk
-      let fileExists = await checkPath(outputPath);
-      let number = 1;
-      
-      while (fileExists) {
-        outputPath = getOutputPath(
-          inputPath,
-          state.outputDirectory || '',
-          state.useSubdirectory,
-          state.subdirectoryName,
-          state.fileNamePattern,
-          existingOutputPaths,
-          number
-        );
-        fileExists = await checkPath(outputPath);
-        number++;
-      }
+        preset: state.selectedPreset,
+      });
 
       // logger.info('Adding job:', { inputPath, outputPath, preset: state.selectedPreset });
 
@@ -376,8 +264,9 @@ export const useConverter = create<ConverterState>((set, get) => ({
 
   loadPersistedData: async () => {
     try {
-      // Load persisted data from backend
-      await invoke('load_persisted_data');
+      // Load persisted data from backend, including the queue from the last session
+      const interruptedJobIds = await invoke<string[]>('load_persisted_data');
+      await get().loadJobs();
       
       // Load settings into state
       const settings = await invoke<AppSettings>('get_app_settings');
@@ -390,6 +279,17 @@ export const useConverter = create<ConverterState>((set, get) => ({
       });
       
       logger.info('Loaded persisted settings:', settings);
+
+      if (interruptedJobIds.length > 0) {
+        const count = interruptedJobIds.length;
+        const requeue = await ask(
+          `${count} conversion${count === 1 ? ' was' : 's were'} interrupted when Transpoze closed. Convert ${count === 1 ? 'it' : 'them'} again?`,
+          { title: 'Interrupted conversions', okLabel: 'Convert again', cancelLabel: 'Not now' }
+        );
+        if (requeue) {
+          await invoke('requeue_jobs', { jobIds: interruptedJobIds });
+        }
+      }
     } catch (error) {
       logger.error('Failed to load persisted data:', error);
     }
@@ -463,22 +363,8 @@ export const useConverter = create<ConverterState>((set, get) => ({
         totalFiles: filesToAdd.length
       });
       
-      // Collect all existing output paths from jobs and history
-      const existingOutputPaths = [
-        ...state.jobs.map(job => job.outputPath),
-        ...state.history.map(h => h.outputPath)
-      ];
-      
-      // Check file system for existing files
-      const checkPath = async (path: string): Promise<boolean> => {
-        try {
-          const exists = await invoke<boolean>('check_file_exists', { path });
-          return exists;
-        } catch (error) {
-          console.error('Error checking file existence:', error);
-          return false;
-        }
-      };
+      // Files dropped together are tracked as one batch
+      const batchId = filesToAdd.length > 1 ? crypto.randomUUID() : undefined;
 
       // Process each file
       const totalFiles = filesToAdd.length;
@@ -487,58 +373,17 @@ export const useConverter = create<ConverterState>((set, get) => ({
         const inputPath = filesToAdd[i]!;
         logger.info('Processing file', { index: i, inputPath });
         
-        // Generate output path with batch context
-        // Only pass forceNumber if pattern explicitly uses {number}
-        const forceNumber = state.fileNamePattern.includes('{number}') ? i : undefined;
-        let outputPath = getOutputPath(
+        // Generate output path with batch context. Jobs added earlier in the
+        // loop are already in the queue, so the backend won't reuse their names.
+        // Only pass a number if the pattern explicitly uses {number}
+        const outputPath = await invoke<string>('resolve_output_path', {
           inputPath,
-          state.outputDirectory || '',
-          state.useSubdirectory,
-          state.subdirectoryName,
-          state.fileNamePattern,
-          existingOutputPaths,
-          forceNumber,
-          totalFiles
-        );
+          preset: state.selectedPreset,
+          number: state.fileNamePattern.includes('{number}') ? i : undefined,
+          total: totalFiles,
+        });
         
         logger.info('Generated output path', { outputPath });
-        
-        // Check if file exists on disk and adjust if needed
-        let fileExists = false;
-        try {
-          fileExists = await checkPath(outputPath);
-          logger.info('File exists check', { outputPath, exists: fileExists });
-        } catch (error) {
-          logger.error('Error checking file existence', { outputPath, error });
-          // Continue anyway, assume file doesn't exist
-          fileExists = false;
-        }
-        let attemptNumber = i;
-        
-        while (fileExists) {
-          attemptNumber++;
-          outputPath = getOutputPath(
-            inputPath,
-            state.outputDirectory || '',
-            state.useSubdirectory,
-            state.subdirectoryName,
-            state.fileNamePattern,
-            existingOutputPaths,
-            attemptNumber,
-            totalFiles
-          );
-          logger.info('Trying alternate path', { attemptNumber, outputPath });
-          try {
-            fileExists = await checkPath(outputPath);
-            logger.info('Alternate path exists check', { outputPath, exists: fileExists });
-          } catch (error) {
-            logger.error('Error checking alternate path', { outputPath, error });
-            fileExists = false;
-          }
-        }
-        
-        // Add to existing paths to prevent duplicates within this batch
-        existingOutputPaths.push(outputPath);
 
         // logger.info('Adding batch job:', { 
         //   inputPath, 
@@ -559,6 +404,7 @@ export const useConverter = create<ConverterState>((set, get) => ({
             inputPath,
             outputPath,
             preset: state.selectedPreset,
+            batchId,
           });
           
           logger.info('Job added successfully', { jobId, inputPath });
@@ -591,6 +437,16 @@ export const useConverter = create<ConverterState>((set, get) => ({
     }
   },
 
+  removeJob: async (jobId) => {
+    try {
+      await invoke('remove_job', { jobId });
+      logger.info('Removed job', { jobId });
+    } catch (error) {
+      logger.error('Failed to remove job:', error);
+      alert('Failed to remove job: ' + error);
+    }
+  },
+
   clearHistory: async () => {
     try {
       await invoke('clear_conversion_history');
@@ -666,65 +522,100 @@ export const useConverter = create<ConverterState>((set, get) => ({
   },
 }));
 
+const JOBS_CHANGED_EVENT_VERSION = 2;
+
+interface QueueStatus {
+  processing: number;
+  waiting: number;
+}
+
+interface JobUpdate {
+  change: 'updated' | 'progress' | 'completed' | 'failed';
+  job: ConversionJob;
+}
+
+interface JobsChangedEvent {
+  version: number;
+  updates: JobUpdate[];
+  removedJobIds: string[];
+}
+
 export function useConverterEvents() {
   const loadJobs = useConverter((state) => state.loadJobs);
   const loadHistory = useConverter((state) => state.loadHistory);
 
   useEffect(() => {
-    const unlistenProgress = listen<[string, number]>('conversion-progress', (event) => {
-      const [jobId, progress] = event.payload;
-      logger.info('Conversion progress', { jobId, progress });
-      useConverter.setState((state) => ({
-        jobs: state.jobs.map((job) =>
-          job.id === jobId 
-            ? { ...job, progress, statusMessage: job.statusMessage || 'Converting video...' } 
-            : job
-        ),
-      }));
-    });
+    const unlistenJobState = listen<JobsChangedEvent>('jobs-changed', (event) => {
+      const { version, updates, removedJobIds } = event.payload;
+      if (version !== JOBS_CHANGED_EVENT_VERSION) {
+        logger.error('Unsupported jobs-changed version, reloading jobs', { version });
+        loadJobs();
+        return;
+      }
 
-    const unlistenJobUpdate = listen<string>('job-updated', async (event) => {
-      const jobId = event.payload;
-      logger.info('JOB-UPDATED EVENT RECEIVED', { jobId });
-      // Refresh jobs from backend
-      try {
-        const jobs = await invoke<ConversionJob[]>('get_conversion_jobs');
-        const updatedJob = jobs.find(j => j.id === jobId);
-        logger.info('Jobs fetched after update', { 
-          jobId, 
-          updatedJob,
-          statusMessage: updatedJob?.statusMessage,
-          status: updatedJob?.status
-        });
-        console.log('JOB-UPDATED: Frontend received update for job', jobId, 'with status message:', updatedJob?.statusMessage);
-        useConverter.setState({ jobs });
-      } catch (error) {
-        logger.error('Failed to get jobs', error);
+      for (const { change, job } of updates) {
+        if (change !== 'progress') {
+          logger.info('Job state changed', { change, jobId: job.id, status: job.status, statusMessage: job.statusMessage?.key });
+        }
       }
-    });
 
-    const unlistenComplete = listen<string>('conversion-complete', (event) => {
-      logger.info('Conversion complete', { jobId: event.payload });
-      loadJobs();
-      loadHistory();
+      // Apply the whole batch in one update so huge queues re-render once
+      useConverter.setState((state) => {
+        const removed = new Set(removedJobIds);
+        const changed = new Map(updates.map((update) => [update.job.id, update]));
+        const jobs = state.jobs
+          .filter((existing) => !removed.has(existing.id))
+          .map((existing) => {
+            const update = changed.get(existing.id);
+            if (!update) return existing;
+            changed.delete(existing.id);
+            // Progress snapshots can arrive after the final one; never move a finished job back
+            const finished = ['completed', 'completedWithWarnings', 'failed', 'cancelled'].includes(existing.status);
+            if (update.change === 'progress' && finished) return existing;
+            return update.job;
+          });
+        const added = [...changed.values()].map((update) => update.job);
+        const liveProgress = { ...state.liveProgress };
+        for (const jobId of removedJobIds) delete liveProgress[jobId];
+        return { jobs: [...jobs, ...added], liveProgress };
+      });
+
+      if (updates.some((update) => update.change === 'completed')) {
+        loadHistory();
+      }
     });
 
-    const unlistenFailed = listen<string>('conversion-failed', (event) => {
-      logger.error('Conversion failed', { jobId: event.payload });
-      loadJobs();
+    const unlistenProgress = listen<ConversionProgress>('conversion-progress', (event) => {
+      const progress = event.payload;
+      useConverter.setState((state) => ({
+        liveProgress: { ...state.liveProgress, [progress.jobId]: progress },
+      }));
     });
 
-    const unlistenCleared = listen('jobs-cleared', () => {
-      logger.info('Jobs cleared, reloading...');
-      loadJobs();
+    const unlistenQuit = listen<QueueStatus>('quit-requested', async (event) => {
+      const { processing, waiting } = event.payload;
+      const total = processing + waiting;
+      const quit = await ask(
+        `${total} conversion${total === 1 ? '' : 's'} in progress — quit anyway?`,
+        { title: 'Quit Transpoze', kind: 'warning', okLabel: 'Quit', cancelLabel: 'Cancel' }
+      );
+      if (!quit) return;
+
+      const finishCurrent = processing > 0 && await ask(
+        'Finish the current conversion before quitting? Waiting files will not be converted.',
+        { title: 'Quit Transpoze', okLabel: 'Finish first', cancelLabel: 'Quit now' }
+      );
+      try {
+        await invoke('confirm_quit', { finishCurrent });
+      } catch (error) {
+        logger.error('Failed to quit', error);
+      }
     });
 
     return () => {
+      unlistenJobState.then((fn) => fn());
       unlistenProgress.then((fn) => fn());
-      unlistenJobUpdate.then((fn) => fn());
-      unlistenComplete.then((fn) => fn());
-      unlistenFailed.then((fn) => fn());
-      unlistenCleared.then((fn) => fn());
+      unlistenQuit.then((fn) => fn());
     };
   }, [loadJobs, loadHistory]);
-}
\ No newline at end of file
+}
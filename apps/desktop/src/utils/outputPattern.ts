@@ -0,0 +1,22 @@
+/** Placeholders the backend expands in the output file name pattern */
+export const PATTERN_VARIABLES = ['{name}', '{number}', '{preset}', '{date}', '{resolution}', '{codec}'];
+
+/** Whether a pattern only uses known placeholders and characters allowed in file names */
+export function isValidPattern(pattern: string | undefined): boolean {
+  if (!pattern || typeof pattern !== 'string') return true;
+  let braceCount = 0;
+  for (const char of pattern) {
+    if (char === '{') braceCount++;
+    if (char === '}') braceCount--;
+    if (braceCount < 0) return false;
+  }
+  if (braceCount !== 0) return false;
+
+  const placeholderRegex = /\{([^}]+)\}/g;
+  const matches = pattern.match(placeholderRegex);
+  if (matches?.some((match) => !PATTERN_VARIABLES.includes(match))) return false;
+
+  const invalidChars = /[<>:"|?*\\/]/;
+  const withoutPlaceholders = pattern.replace(placeholderRegex, '');
+  return !invalidChars.test(withoutPlaceholders);
+}
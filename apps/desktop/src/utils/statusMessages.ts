@@ -0,0 +1,22 @@
+/** Status message sent by the backend as a stable key plus parameters */
+export interface StatusMessage {
+  key: 'waitingInQueue' | 'readyToConvert' | 'converting' | 'cancelled' | 'skippedLargerThanOriginal';
+  params?: Record<string, string | number>;
+}
+
+const EN: Record<StatusMessage['key'], string> = {
+  waitingInQueue: 'Waiting in queue...',
+  readyToConvert: 'Ready to convert',
+  converting: 'Converting video...',
+  cancelled: 'Cancelled',
+  skippedLargerThanOriginal: 'Skipped (larger than original)',
+};
+
+/** Text for a status message, with `{param}` placeholders filled in */
+export function formatStatusMessage(message?: StatusMessage): string | undefined {
+  if (!message) return undefined;
+  const template = EN[message.key] ?? message.key;
+  return template.replace(/\{(\w+)\}/g, (placeholder, name) =>
+    message.params?.[name] !== undefined ? String(message.params[name]) : placeholder
+  );
+}